@@ -111,6 +111,28 @@ class CodeEditor extends React.Component {
 
     this.state = {};
     this._onChange = this.onChange.bind(this);
+    this._onLoad = this.onLoad.bind(this);
+    this._editor = null;
+  }
+
+  onLoad(editor) {
+    this._editor = editor;
+    this.gotoCursor();
+  }
+
+  gotoCursor() {
+    const { path } = this.props;
+    const { cursors } = this.props.storage();
+    const cursor = cursors.get(path);
+    if (!!this._editor && !!cursor) {
+      cursors.delete(path);
+      this._editor.gotoLine(cursor.line, Math.max(0, (cursor.column || 1) - 1), true);
+      this._editor.focus();
+    }
+  }
+
+  componentDidUpdate() {
+    this.gotoCursor();
   }
 
   onChange(code) {
@@ -132,6 +154,7 @@ class CodeEditor extends React.Component {
         value={code}
         tabSize={langMeta.tabSize}
         onChange={this._onChange}
+        onLoad={this._onLoad}
         style={style.editor}
         setOptions={{
           useWorker: false,
@@ -148,6 +171,7 @@ class CodeWindow extends React.Component {
 
     const storage = props.storage();
     storage.fileContents = storage.fileContents || new Map();
+    storage.cursors = storage.cursors || new Map();
     storage.openFiles = storage.openFiles || [];
     storage.active = storage.active || 0;
     this.state = {};
@@ -229,9 +253,9 @@ class CodeWindow extends React.Component {
     }
   }
 
-  onOpenFile({ path, name, contents }) {
+  onOpenFile({ path, name, contents, line, column }) {
     const storage = this.props.storage();
-    const { openFiles, fileContents } = storage;
+    const { openFiles, fileContents, cursors } = storage;
     const found = openFiles.find(item => item.path === path);
     let active = storage.active;
     if (!found) {
@@ -241,6 +265,9 @@ class CodeWindow extends React.Component {
       active = openFiles.indexOf(found);
     }
     fileContents.set(path, contents);
+    if (!!line) {
+      cursors.set(path, { line, column });
+    }
     storage.active = active;
     this.setState({});
   }
@@ -11,6 +11,22 @@ struct PayloadOpen {
     pub path: String,
     pub name: String,
     pub contents: String,
+    #[serde(default)]
+    pub line: Option<usize>,
+    #[serde(default)]
+    pub column: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum OpenFile {
+    Path(String),
+    Location {
+        path: String,
+        line: usize,
+        #[serde(default)]
+        column: Option<usize>,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -23,7 +39,11 @@ struct PayloadSave {
 pub fn query(query: &str, data: JsValue) -> Result<(), JsValue> {
     match query {
         "open-file" => {
-            if let Ok(path) = data.into_serde::<String>() {
+            if let Ok(open) = data.into_serde::<OpenFile>() {
+                let (path, line, column) = match open {
+                    OpenFile::Path(path) => (path, None, None),
+                    OpenFile::Location { path, line, column } => (path, Some(line), column),
+                };
                 let path = if path.starts_with('/') || path.starts_with('\\') {
                     path[1..].to_owned()
                 } else {
@@ -36,6 +56,8 @@ pub fn query(query: &str, data: JsValue) -> Result<(), JsValue> {
                     path,
                     name,
                     contents,
+                    line,
+                    column,
                 };
                 if let Ok(value) = JsValue::from_serde(&payload) {
                     ensure_window_focused("Code")?;
@@ -0,0 +1,305 @@
+use crate::oxygengine::{
+    composite_renderer::{Command, Image, Renderable},
+    math::{Rect, Vec2},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Separates atlas path from frame name in image names, e.g. `sprites.json@idle`.
+pub const FRAME_SEPARATOR: char = '@';
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AtlasFrame {
+    /// TexturePacker style frame entry.
+    Packed {
+        frame: Rect,
+    },
+    Rect(Rect),
+}
+
+impl AtlasFrame {
+    pub fn rect(&self) -> Rect {
+        match self {
+            Self::Packed { frame } => *frame,
+            Self::Rect(rect) => *rect,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AtlasMeta {
+    pub image: String,
+}
+
+/// Sprite atlas JSON, either `{ image, frames }` or TexturePacker `{ frames, meta: { image } }`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpriteAtlas {
+    #[serde(default)]
+    pub image: Option<String>,
+    #[serde(default)]
+    pub meta: Option<AtlasMeta>,
+    pub frames: HashMap<String, AtlasFrame>,
+}
+
+impl SpriteAtlas {
+    /// Image path relative to project, resolved against atlas file location.
+    pub fn image_path(&self, atlas_path: &str) -> Option<String> {
+        let image = self
+            .image
+            .as_ref()
+            .or_else(|| self.meta.as_ref().map(|meta| &meta.image))?;
+        if image.starts_with('/') {
+            return Some(image.to_owned());
+        }
+        match atlas_path.rfind(|c| c == '/' || c == '\\') {
+            Some(index) => Some(format!("{}/{}", &atlas_path[..index], image)),
+            None => Some(image.to_owned()),
+        }
+    }
+
+    /// Frames by their image names, with atlas image name and source rectangle.
+    pub fn frames(&self, atlas_path: &str, image: &str) -> AtlasFrames {
+        self.frames
+            .iter()
+            .map(|(name, frame)| {
+                (
+                    frame_name(atlas_path, name),
+                    (image.to_owned(), frame.rect()),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Atlas image name and source rectangle by frame image name.
+pub type AtlasFrames = HashMap<String, (String, Rect)>;
+
+pub fn frame_name(atlas_path: &str, frame: &str) -> String {
+    format!("{}{}{}", atlas_path, FRAME_SEPARATOR, frame)
+}
+
+/// Sizes of frames, so frames can be measured like standalone images.
+pub fn frame_sizes(frames: &AtlasFrames) -> impl Iterator<Item = (String, Vec2)> + '_ {
+    frames
+        .iter()
+        .map(|(name, (_, rect))| (name.clone(), Vec2::new(rect.w, rect.h)))
+}
+
+/// Rewrites images referencing atlas frames into atlas image with source rectangle.
+pub fn resolve_frames<'a>(commands: Vec<Command<'a>>, frames: &AtlasFrames) -> Vec<Command<'a>> {
+    if frames.is_empty() {
+        return commands;
+    }
+    commands
+        .into_iter()
+        .map(|command| match command {
+            Command::Draw(renderable) => Command::Draw(resolve_renderable(renderable, frames)),
+            Command::Stroke(width, renderable) => {
+                Command::Stroke(width, resolve_renderable(renderable, frames))
+            }
+            command => command,
+        })
+        .collect()
+}
+
+fn resolve_renderable<'a>(renderable: Renderable<'a>, frames: &AtlasFrames) -> Renderable<'a> {
+    match renderable {
+        Renderable::Image(image) => {
+            let (atlas_image, frame) = match frames.get(image.image.as_ref()) {
+                Some(found) => found,
+                None => return Renderable::Image(image),
+            };
+            let source = match image.source {
+                Some(source) => frame_source(frame, &source),
+                None => *frame,
+            };
+            Renderable::Image(Image {
+                image: atlas_image.clone().into(),
+                source: Some(source),
+                ..image
+            })
+        }
+        Renderable::Commands(commands) => Renderable::Commands(resolve_frames(commands, frames)),
+        renderable => renderable,
+    }
+}
+
+/// Source rectangle relative to frame moved into atlas space. It gets clipped to
+/// frame bounds, so neighbouring frames never bleed in.
+fn frame_source(frame: &Rect, source: &Rect) -> Rect {
+    let x = frame.x + source.x.max(0.0).min(frame.w);
+    let y = frame.y + source.y.max(0.0).min(frame.h);
+    let right = (frame.x + source.x + source.w).min(frame.x + frame.w);
+    let bottom = (frame.y + source.y + source.h).min(frame.y + frame.h);
+    Rect {
+        x,
+        y,
+        w: (right - x).max(0.0),
+        h: (bottom - y).max(0.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAIN: &str = r#"{
+        "image": "sprites.png",
+        "frames": {
+            "idle": { "x": 0, "y": 0, "w": 16, "h": 16 },
+            "run": { "x": 18, "y": 0, "w": 16, "h": 16 }
+        }
+    }"#;
+    const PACKED: &str = r#"{
+        "frames": {
+            "idle": { "frame": { "x": 2, "y": 2, "w": 32, "h": 24 } }
+        },
+        "meta": { "image": "packed.png" }
+    }"#;
+
+    fn rect(x: f32, y: f32, w: f32, h: f32) -> Rect {
+        Rect { x, y, w, h }
+    }
+
+    fn assert_rect(a: Rect, b: Rect) {
+        assert_eq!((a.x, a.y, a.w, a.h), (b.x, b.y, b.w, b.h));
+    }
+
+    fn frames() -> AtlasFrames {
+        let atlas = serde_json::from_str::<SpriteAtlas>(PLAIN).unwrap();
+        atlas.frames("sprites.json", "sprites.png")
+    }
+
+    fn image(name: &str, source: Option<Rect>) -> Renderable<'static> {
+        Renderable::Image(Image {
+            image: name.to_owned().into(),
+            source,
+            destination: None,
+            alignment: Vec2::new(0.0, 0.0),
+        })
+    }
+
+    /// Image name and source of resolved image renderable.
+    fn resolved(renderable: &Renderable) -> (String, Option<Rect>) {
+        match renderable {
+            Renderable::Image(image) => (image.image.to_string(), image.source),
+            _ => panic!("expected image"),
+        }
+    }
+
+    #[test]
+    fn test_parse_formats() {
+        let plain = serde_json::from_str::<SpriteAtlas>(PLAIN).unwrap();
+        assert_eq!(
+            plain.image_path("assets/sprites.json").as_deref(),
+            Some("assets/sprites.png")
+        );
+        assert_rect(plain.frames["run"].rect(), rect(18.0, 0.0, 16.0, 16.0));
+        let packed = serde_json::from_str::<SpriteAtlas>(PACKED).unwrap();
+        assert_eq!(
+            packed.image_path("assets\\packed.json").as_deref(),
+            Some("assets/packed.png")
+        );
+        assert_rect(packed.frames["idle"].rect(), rect(2.0, 2.0, 32.0, 24.0));
+    }
+
+    #[test]
+    fn test_image_path() {
+        let mut atlas = serde_json::from_str::<SpriteAtlas>(PLAIN).unwrap();
+        assert_eq!(
+            atlas.image_path("sprites.json").as_deref(),
+            Some("sprites.png")
+        );
+        atlas.image = Some("/shared/sprites.png".to_owned());
+        assert_eq!(
+            atlas.image_path("assets/sprites.json").as_deref(),
+            Some("/shared/sprites.png")
+        );
+        atlas.image = None;
+        assert_eq!(atlas.image_path("assets/sprites.json"), None);
+    }
+
+    #[test]
+    fn test_frame_lookup() {
+        let frames = frames();
+        assert_eq!(frames.len(), 2);
+        let (atlas_image, frame) = &frames[&frame_name("sprites.json", "run")];
+        assert_eq!(atlas_image, "sprites.png");
+        assert_rect(*frame, rect(18.0, 0.0, 16.0, 16.0));
+        let sizes = frame_sizes(&frames).collect::<HashMap<_, _>>();
+        let size = sizes["sprites.json@idle"];
+        assert_eq!((size.x, size.y), (16.0, 16.0));
+    }
+
+    #[test]
+    fn test_resolve_frames() {
+        let commands = vec![
+            Command::Draw(image("sprites.json@run", None)),
+            Command::Stroke(1.0, image("sprites.json@idle", None)),
+            Command::Draw(Renderable::Commands(vec![Command::Draw(image(
+                "sprites.json@run",
+                Some(rect(4.0, 2.0, 8.0, 8.0)),
+            ))])),
+            Command::Draw(image("logo.png", None)),
+            Command::Store,
+        ];
+        let result = resolve_frames(commands, &frames());
+        assert_eq!(result.len(), 5);
+        let (name, source) = match &result[0] {
+            Command::Draw(renderable) => resolved(renderable),
+            _ => panic!("expected draw"),
+        };
+        assert_eq!(name, "sprites.png");
+        assert_rect(source.unwrap(), rect(18.0, 0.0, 16.0, 16.0));
+        match &result[1] {
+            Command::Stroke(_, renderable) => {
+                assert_rect(resolved(renderable).1.unwrap(), rect(0.0, 0.0, 16.0, 16.0))
+            }
+            _ => panic!("expected stroke"),
+        }
+        match &result[2] {
+            Command::Draw(Renderable::Commands(commands)) => match &commands[0] {
+                Command::Draw(renderable) => {
+                    assert_rect(resolved(renderable).1.unwrap(), rect(22.0, 2.0, 8.0, 8.0))
+                }
+                _ => panic!("expected draw"),
+            },
+            _ => panic!("expected nested commands"),
+        }
+        match &result[3] {
+            Command::Draw(renderable) => {
+                assert_eq!(resolved(renderable), ("logo.png".to_owned(), None))
+            }
+            _ => panic!("expected draw"),
+        }
+    }
+
+    #[test]
+    fn test_padding_between_frames() {
+        // frames are 2 pixels apart, padding never belongs to either of them.
+        let frames = frames();
+        let idle = frames["sprites.json@idle"].1;
+        let run = frames["sprites.json@run"].1;
+        assert_eq!(run.x - (idle.x + idle.w), 2.0);
+        let source = frame_source(&idle, &rect(0.0, 0.0, 20.0, 16.0));
+        assert!(source.x + source.w <= run.x - 2.0);
+    }
+
+    #[test]
+    fn test_source_overflow_is_clipped() {
+        let frame = rect(18.0, 0.0, 16.0, 16.0);
+        assert_rect(
+            frame_source(&frame, &rect(8.0, 8.0, 16.0, 16.0)),
+            rect(26.0, 8.0, 8.0, 8.0),
+        );
+        assert_rect(
+            frame_source(&frame, &rect(-4.0, -4.0, 8.0, 8.0)),
+            rect(18.0, 0.0, 4.0, 4.0),
+        );
+        assert_rect(
+            frame_source(&frame, &rect(20.0, 0.0, 4.0, 4.0)),
+            rect(34.0, 0.0, 0.0, 4.0),
+        );
+    }
+}
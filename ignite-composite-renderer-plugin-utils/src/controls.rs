@@ -0,0 +1,446 @@
+use crate::{
+    geometry::{inverse, transform_point, Bounds},
+    oxygengine::{component::CompositeTransform, math::Vec2},
+    Camera, Scalar,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Camera placement that controllers animate towards and bookmarks store.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CameraPose {
+    pub translation: Vec2,
+    #[serde(default)]
+    pub rotation: Scalar,
+    pub scale: Vec2,
+}
+
+impl CameraPose {
+    pub fn from_transform(transform: &CompositeTransform) -> Self {
+        Self {
+            translation: transform.get_translation(),
+            rotation: transform.get_rotation(),
+            scale: transform.get_scale(),
+        }
+    }
+
+    pub fn apply(&self, transform: &mut CompositeTransform) {
+        transform.set_translation(self.translation);
+        transform.set_rotation(self.rotation);
+        transform.set_scale(self.scale);
+    }
+
+    /// Zoom level, where 1 means one world unit per camera unit.
+    pub fn zoom(&self) -> Scalar {
+        1.0 / self.scale.x.abs().max(std::f32::EPSILON)
+    }
+
+    /// Keeps aspect of scale, degenerate zero width scale falls back to uniform one.
+    fn with_zoom(mut self, zoom: Scalar) -> Self {
+        let ratio = if self.scale.x.abs() > std::f32::EPSILON {
+            self.scale.y / self.scale.x
+        } else {
+            1.0
+        };
+        let zoom = zoom.max(std::f32::EPSILON);
+        self.scale = Vec2::new(1.0 / zoom, ratio / zoom);
+        self
+    }
+
+    fn lerp(&self, other: &Self, factor: Scalar) -> Self {
+        let mix = |a: Scalar, b: Scalar| a + (b - a) * factor;
+        Self {
+            translation: Vec2::new(
+                mix(self.translation.x, other.translation.x),
+                mix(self.translation.y, other.translation.y),
+            ),
+            rotation: mix(self.rotation, other.rotation),
+            scale: Vec2::new(
+                mix(self.scale.x, other.scale.x),
+                mix(self.scale.y, other.scale.y),
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CameraControls {
+    #[serde(default = "CameraControls::default_min_zoom")]
+    pub min_zoom: Scalar,
+    #[serde(default = "CameraControls::default_max_zoom")]
+    pub max_zoom: Scalar,
+    /// Zoom multiplier applied per wheel step.
+    #[serde(default = "CameraControls::default_zoom_step")]
+    pub zoom_step: Scalar,
+    /// Speed of transitions towards target pose, zero applies changes immediately.
+    #[serde(default = "CameraControls::default_smoothing")]
+    pub smoothing: Scalar,
+    /// Fraction of view left empty around framed bounds.
+    #[serde(default = "CameraControls::default_frame_padding")]
+    pub frame_padding: Scalar,
+}
+
+impl Default for CameraControls {
+    fn default() -> Self {
+        Self {
+            min_zoom: Self::default_min_zoom(),
+            max_zoom: Self::default_max_zoom(),
+            zoom_step: Self::default_zoom_step(),
+            smoothing: Self::default_smoothing(),
+            frame_padding: Self::default_frame_padding(),
+        }
+    }
+}
+
+impl CameraControls {
+    fn default_min_zoom() -> Scalar {
+        0.01
+    }
+
+    fn default_max_zoom() -> Scalar {
+        100.0
+    }
+
+    fn default_zoom_step() -> Scalar {
+        1.1
+    }
+
+    fn default_smoothing() -> Scalar {
+        15.0
+    }
+
+    fn default_frame_padding() -> Scalar {
+        0.1
+    }
+}
+
+/// Navigation state of single camera.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct CameraController {
+    #[serde(default)]
+    pub controls: CameraControls,
+    #[serde(default)]
+    pub bookmarks: HashMap<String, CameraPose>,
+    #[serde(skip)]
+    target: Option<CameraPose>,
+}
+
+impl CameraController {
+    pub fn target(&self) -> Option<CameraPose> {
+        self.target
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.target.is_some()
+    }
+
+    /// Cancels running transition.
+    pub fn stop(&mut self) {
+        self.target = None;
+    }
+
+    /// Moves camera so world point under `from` screen position lands under `to`.
+    pub fn pan(&mut self, camera: &mut Camera, view_size: Vec2, from: Vec2, to: Vec2) {
+        let from = screen_to_world(camera, view_size, from);
+        let to = screen_to_world(camera, view_size, to);
+        let (from, to) = match (from, to) {
+            (Some(from), Some(to)) => (from, to),
+            _ => return,
+        };
+        let delta = Vec2::new(from.x - to.x, from.y - to.y);
+        let mut pose = CameraPose::from_transform(&camera.transform);
+        pose.translation = Vec2::new(pose.translation.x + delta.x, pose.translation.y + delta.y);
+        pose.apply(&mut camera.transform);
+        if let Some(target) = &mut self.target {
+            target.translation = Vec2::new(
+                target.translation.x + delta.x,
+                target.translation.y + delta.y,
+            );
+        }
+    }
+
+    /// Multiplies zoom by `factor`, keeping world point under screen `point` in place.
+    pub fn zoom_at(&mut self, camera: &mut Camera, view_size: Vec2, point: Vec2, factor: Scalar) {
+        let pose = self
+            .target
+            .unwrap_or_else(|| CameraPose::from_transform(&camera.transform));
+        let zoom = (pose.zoom() * factor)
+            .max(self.controls.min_zoom)
+            .min(self.controls.max_zoom);
+        let zoomed = pose.with_zoom(zoom);
+        let target = match (
+            pose_screen_to_world(camera, &pose, view_size, point),
+            pose_screen_to_world(camera, &zoomed, view_size, point),
+        ) {
+            (Some(before), Some(after)) => CameraPose {
+                translation: Vec2::new(
+                    zoomed.translation.x + before.x - after.x,
+                    zoomed.translation.y + before.y - after.y,
+                ),
+                ..zoomed
+            },
+            _ => return,
+        };
+        self.transition(camera, target);
+    }
+
+    /// Zooms by `steps` wheel steps, positive steps zoom in.
+    pub fn zoom_steps(&mut self, camera: &mut Camera, view_size: Vec2, point: Vec2, steps: Scalar) {
+        let factor = self.controls.zoom_step.max(1.0).powf(steps);
+        self.zoom_at(camera, view_size, point, factor);
+    }
+
+    /// Fits world space bounds into view.
+    pub fn frame(&mut self, camera: &mut Camera, view_size: Vec2, bounds: Bounds) {
+        let pose = CameraPose::from_transform(&camera.transform);
+        let view = camera.view_matrix(view_size);
+        let [a, b, ..] = view.0;
+        let pixels_per_unit = (a * a + b * b).sqrt();
+        if pixels_per_unit <= 0.0 {
+            return;
+        }
+        let padding = 1.0 - self.controls.frame_padding.max(0.0).min(0.9);
+        let width = bounds.width().max(std::f32::EPSILON);
+        let height = bounds.height().max(std::f32::EPSILON);
+        let viewport = camera.viewport_rect(view_size);
+        let wanted = (viewport.w / width).min(viewport.h / height) * padding;
+        let zoom = (pose.zoom() * wanted / pixels_per_unit)
+            .max(self.controls.min_zoom)
+            .min(self.controls.max_zoom);
+        let zoomed = pose.with_zoom(zoom);
+        let center = Vec2::new(viewport.x + viewport.w * 0.5, viewport.y + viewport.h * 0.5);
+        let target = match pose_screen_to_world(camera, &zoomed, view_size, center) {
+            Some(current) => {
+                let wanted = bounds.center();
+                CameraPose {
+                    translation: Vec2::new(
+                        zoomed.translation.x + wanted.x - current.x,
+                        zoomed.translation.y + wanted.y - current.y,
+                    ),
+                    ..zoomed
+                }
+            }
+            None => return,
+        };
+        self.transition(camera, target);
+    }
+
+    pub fn save_bookmark(&mut self, camera: &Camera, name: &str) {
+        let pose = self
+            .target
+            .unwrap_or_else(|| CameraPose::from_transform(&camera.transform));
+        self.bookmarks.insert(name.to_owned(), pose);
+    }
+
+    /// Starts transition to bookmarked pose, returns false when bookmark does not exist.
+    pub fn restore_bookmark(&mut self, camera: &mut Camera, name: &str) -> bool {
+        match self.bookmarks.get(name).copied() {
+            Some(pose) => {
+                self.transition(camera, pose);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Advances transition by `delta_time` seconds, returns true when camera moved.
+    pub fn update(&mut self, camera: &mut Camera, delta_time: Scalar) -> bool {
+        let target = match self.target {
+            Some(target) => target,
+            None => return false,
+        };
+        let current = CameraPose::from_transform(&camera.transform);
+        let factor = 1.0 - (-self.controls.smoothing * delta_time.max(0.0)).exp();
+        let next = current.lerp(&target, factor);
+        let distance = (next.translation.x - target.translation.x).abs()
+            + (next.translation.y - target.translation.y).abs();
+        let scale = ((next.scale.x - target.scale.x) / target.scale.x).abs();
+        if distance * target.zoom() < 0.1 && scale < 0.001 {
+            target.apply(&mut camera.transform);
+            self.target = None;
+        } else {
+            next.apply(&mut camera.transform);
+        }
+        true
+    }
+
+    fn transition(&mut self, camera: &mut Camera, target: CameraPose) {
+        if self.controls.smoothing > 0.0 {
+            self.target = Some(target);
+        } else {
+            target.apply(&mut camera.transform);
+            self.target = None;
+        }
+    }
+}
+
+fn screen_to_world(camera: &Camera, view_size: Vec2, point: Vec2) -> Option<Vec2> {
+    let view = camera.view_matrix(view_size);
+    Some(transform_point(&inverse(&view)?, point))
+}
+
+fn pose_screen_to_world(
+    camera: &Camera,
+    pose: &CameraPose,
+    view_size: Vec2,
+    point: Vec2,
+) -> Option<Vec2> {
+    let mut camera = camera.clone();
+    pose.apply(&mut camera.transform);
+    let view = camera.view_matrix(view_size);
+    Some(transform_point(&inverse(&view)?, point))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oxygengine::math::Rect;
+
+    const VIEW_SIZE: Vec2 = Vec2 { x: 200.0, y: 100.0 };
+
+    fn assert_near(a: Vec2, b: Vec2) {
+        assert!(
+            (a.x - b.x).abs() < 1.0e-3 && (a.y - b.y).abs() < 1.0e-3,
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    fn immediate() -> CameraController {
+        CameraController {
+            controls: CameraControls {
+                smoothing: 0.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn view_point(camera: &Camera, point: Vec2) -> Vec2 {
+        transform_point(&camera.view_matrix(VIEW_SIZE), point)
+    }
+
+    #[test]
+    fn test_pan() {
+        let mut camera = Camera::default();
+        let mut controller = immediate();
+        let from = Vec2::new(50.0, 50.0);
+        let to = Vec2::new(80.0, 30.0);
+        let world = screen_to_world(&camera, VIEW_SIZE, from).unwrap();
+        controller.pan(&mut camera, VIEW_SIZE, from, to);
+        assert_near(view_point(&camera, world), to);
+    }
+
+    #[test]
+    fn test_zoom_at() {
+        let mut camera = Camera::default();
+        let mut controller = immediate();
+        let point = Vec2::new(30.0, 70.0);
+        let world = screen_to_world(&camera, VIEW_SIZE, point).unwrap();
+        controller.zoom_at(&mut camera, VIEW_SIZE, point, 2.0);
+        let pose = CameraPose::from_transform(&camera.transform);
+        assert!((pose.zoom() - 2.0).abs() < 1.0e-4);
+        assert_near(view_point(&camera, world), point);
+        controller.zoom_steps(&mut camera, VIEW_SIZE, point, 1000.0);
+        let pose = CameraPose::from_transform(&camera.transform);
+        assert!((pose.zoom() - controller.controls.max_zoom).abs() < 1.0e-2);
+    }
+
+    #[test]
+    fn test_with_zoom() {
+        let pose = CameraPose {
+            translation: Vec2::new(0.0, 0.0),
+            rotation: 0.0,
+            scale: Vec2::new(1.0, 2.0),
+        };
+        assert_near(pose.with_zoom(2.0).scale, Vec2::new(0.5, 1.0));
+        let degenerate = CameraPose {
+            scale: Vec2::new(0.0, 2.0),
+            ..pose
+        };
+        assert_near(degenerate.with_zoom(2.0).scale, Vec2::new(0.5, 0.5));
+        let zoomed = pose.with_zoom(0.0);
+        assert!(zoomed.scale.x.is_finite() && zoomed.scale.y.is_finite());
+    }
+
+    #[test]
+    fn test_frame() {
+        let mut camera = Camera::default();
+        let mut controller = immediate();
+        let bounds = Bounds::from_rect(&Rect {
+            x: 1000.0,
+            y: -500.0,
+            w: 40.0,
+            h: 10.0,
+        });
+        controller.frame(&mut camera, VIEW_SIZE, bounds);
+        assert_near(view_point(&camera, bounds.center()), Vec2::new(100.0, 50.0));
+        for corner in bounds.corners().iter() {
+            let point = view_point(&camera, *corner);
+            assert!(point.x >= 0.0 && point.x <= VIEW_SIZE.x, "{:?}", point);
+            assert!(point.y >= 0.0 && point.y <= VIEW_SIZE.y, "{:?}", point);
+        }
+        // padding leaves wider side short of view edges.
+        let left = view_point(&camera, bounds.min);
+        assert!(left.x > 1.0);
+    }
+
+    #[test]
+    fn test_smooth_transition() {
+        let mut camera = Camera::default();
+        let mut controller = CameraController::default();
+        let before = CameraPose::from_transform(&camera.transform);
+        controller.zoom_at(&mut camera, VIEW_SIZE, Vec2::new(100.0, 50.0), 4.0);
+        assert!(controller.is_animating());
+        assert_eq!(CameraPose::from_transform(&camera.transform), before);
+        let target = controller.target().unwrap();
+        let mut steps = 0;
+        while controller.update(&mut camera, 1.0 / 60.0) {
+            steps += 1;
+            assert!(steps < 1000, "transition never finished");
+        }
+        assert!(steps > 1);
+        assert!(!controller.is_animating());
+        assert_eq!(CameraPose::from_transform(&camera.transform), target);
+        assert!(!controller.update(&mut camera, 1.0 / 60.0));
+    }
+
+    #[test]
+    fn test_pan_moves_target() {
+        let mut camera = Camera::default();
+        let mut controller = CameraController::default();
+        controller.zoom_at(&mut camera, VIEW_SIZE, Vec2::new(100.0, 50.0), 2.0);
+        let target = controller.target().unwrap();
+        controller.pan(
+            &mut camera,
+            VIEW_SIZE,
+            Vec2::new(0.0, 0.0),
+            Vec2::new(10.0, 0.0),
+        );
+        let moved = controller.target().unwrap();
+        assert!(moved.translation.x < target.translation.x);
+        assert_eq!(moved.translation.y, target.translation.y);
+        assert_eq!(moved.scale, target.scale);
+        controller.stop();
+        assert!(!controller.is_animating());
+    }
+
+    #[test]
+    fn test_bookmarks() {
+        let mut camera = Camera::default();
+        let mut controller = immediate();
+        let home = CameraPose::from_transform(&camera.transform);
+        controller.save_bookmark(&camera, "home");
+        controller.pan(
+            &mut camera,
+            VIEW_SIZE,
+            Vec2::new(0.0, 0.0),
+            Vec2::new(25.0, 25.0),
+        );
+        assert_ne!(CameraPose::from_transform(&camera.transform), home);
+        assert!(!controller.restore_bookmark(&mut camera, "missing"));
+        assert!(controller.restore_bookmark(&mut camera, "home"));
+        assert_eq!(CameraPose::from_transform(&camera.transform), home);
+    }
+}
@@ -0,0 +1,274 @@
+use crate::{
+    geometry::{renderable_bounds, text_bounds, Bounds, TransformStack},
+    oxygengine::{
+        composite_renderer::{Command, Renderable},
+        math::{Mat2d, Vec2},
+    },
+    Scalar,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Screen space margin in pixels kept around view, covers anti-aliasing.
+const CULLING_MARGIN: Scalar = 2.0;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CullingStats {
+    pub drawn: usize,
+    pub culled: usize,
+}
+
+impl CullingStats {
+    pub fn add(&mut self, other: &Self) {
+        self.drawn += other.drawn;
+        self.culled += other.culled;
+    }
+}
+
+/// Drops draws whose screen space bounds fall outside of view. Nested command lists
+/// are culled in place and share transform state with their parent list, like canvas
+/// executes them. Commands that cannot be measured and state commands are never dropped.
+pub fn cull_commands<'a>(
+    commands: &[Command<'a>],
+    view: &Mat2d,
+    view_size: Vec2,
+    image_sizes: &HashMap<String, Vec2>,
+) -> (Vec<Command<'a>>, CullingStats) {
+    let mut culler = Culler {
+        transforms: TransformStack::new(*view),
+        screen: Bounds {
+            min: Vec2::new(0.0, 0.0),
+            max: view_size,
+        }
+        .expand(CULLING_MARGIN),
+        image_sizes,
+        stats: Default::default(),
+    };
+    let result = culler.commands(commands);
+    (result, culler.stats)
+}
+
+struct Culler<'b> {
+    transforms: TransformStack,
+    screen: Bounds,
+    image_sizes: &'b HashMap<String, Vec2>,
+    stats: CullingStats,
+}
+
+impl Culler<'_> {
+    fn commands<'a>(&mut self, commands: &[Command<'a>]) -> Vec<Command<'a>> {
+        commands
+            .iter()
+            .filter_map(|command| self.command(command))
+            .collect()
+    }
+
+    fn command<'a>(&mut self, command: &Command<'a>) -> Option<Command<'a>> {
+        if self.transforms.apply(command) {
+            return Some(command.clone());
+        }
+        let (stroke, renderable) = match command {
+            Command::Draw(renderable) => (None, renderable),
+            Command::Stroke(width, renderable) => (Some(*width), renderable),
+            _ => return Some(command.clone()),
+        };
+        if let Renderable::Commands(commands) = renderable {
+            let commands = Renderable::Commands(self.commands(commands));
+            return Some(match stroke {
+                Some(width) => Command::Stroke(width, commands),
+                None => Command::Draw(commands),
+            });
+        }
+        let visible = culling_bounds(renderable, stroke, self.image_sizes)
+            .map(|bounds| {
+                bounds
+                    .transformed(&self.transforms.current)
+                    .intersects(&self.screen)
+            })
+            .unwrap_or(true);
+        if visible {
+            self.stats.drawn += 1;
+            Some(command.clone())
+        } else {
+            self.stats.culled += 1;
+            None
+        }
+    }
+}
+
+/// Local bounds that renderable surely fits in, `None` when it should not be culled.
+/// Text extents are only estimated, so its bounds get inflated to glyphs as wide as
+/// they are tall plus one line of margin for accents, italics and stroke.
+fn culling_bounds(
+    renderable: &Renderable,
+    stroke: Option<Scalar>,
+    image_sizes: &HashMap<String, Vec2>,
+) -> Option<Bounds> {
+    match renderable {
+        Renderable::Text(text) => {
+            let bounds = text_bounds(text);
+            Some(bounds.expand(bounds.width() + text.size + stroke.unwrap_or(0.0)))
+        }
+        renderable => renderable_bounds(renderable, stroke, image_sizes),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        geometry::identity,
+        oxygengine::{
+            composite_renderer::{Rectangle, Text, TextAlign, TextBaseLine},
+            math::{Color, Rect},
+        },
+    };
+
+    fn rectangle(x: Scalar, y: Scalar) -> Command<'static> {
+        Command::Draw(Renderable::Rectangle(Rectangle {
+            color: Color::rgba(255, 255, 255, 255),
+            rect: Rect {
+                x,
+                y,
+                w: 10.0,
+                h: 10.0,
+            },
+        }))
+    }
+
+    fn text(x: Scalar, content: &'static str) -> Command<'static> {
+        Command::Draw(Renderable::Text(
+            Text::new_owned("Verdana".to_owned(), content.to_owned())
+                .align(TextAlign::Left)
+                .baseline(TextBaseLine::Top)
+                .position(Vec2::new(x, 50.0))
+                .size(20.0),
+        ))
+    }
+
+    fn cull(commands: &[Command<'static>]) -> (Vec<Command<'static>>, CullingStats) {
+        cull_commands(
+            commands,
+            &identity(),
+            Vec2::new(100.0, 100.0),
+            &HashMap::new(),
+        )
+    }
+
+    #[test]
+    fn test_offscreen_draws_are_culled() {
+        let (result, stats) = cull(&[
+            rectangle(10.0, 10.0),
+            rectangle(200.0, 10.0),
+            rectangle(-20.0, -20.0),
+            rectangle(-13.0, 10.0),
+        ]);
+        assert_eq!(result.len(), 2);
+        assert_eq!(
+            stats,
+            CullingStats {
+                drawn: 2,
+                culled: 2
+            }
+        );
+    }
+
+    #[test]
+    fn test_state_commands_are_kept() {
+        let (result, stats) = cull(&[
+            Command::Store,
+            Command::Transform(1.0, 0.0, 0.0, 1.0, 500.0, 0.0),
+            rectangle(10.0, 10.0),
+            Command::Restore,
+            rectangle(10.0, 10.0),
+        ]);
+        assert_eq!(result.len(), 4);
+        assert_eq!(
+            stats,
+            CullingStats {
+                drawn: 1,
+                culled: 1
+            }
+        );
+        assert!(matches!(result[1], Command::Transform(..)));
+        assert!(matches!(result[2], Command::Restore));
+    }
+
+    #[test]
+    fn test_wide_text_is_kept() {
+        // 5 glyphs estimated at 50 pixels, but wide glyphs can reach 100 pixels
+        // and get into view.
+        let (result, stats) = cull(&[text(-90.0, "WWWWW"), text(-400.0, "WWWWW")]);
+        assert_eq!(result.len(), 1);
+        assert_eq!(
+            stats,
+            CullingStats {
+                drawn: 1,
+                culled: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_nested_commands() {
+        // nested transform moves rectangle back into view.
+        let nested = Command::Draw(Renderable::Commands(vec![
+            Command::Transform(1.0, 0.0, 0.0, 1.0, -1000.0, 0.0),
+            rectangle(10.0, 10.0),
+        ]));
+        let (result, stats) = cull(&[Command::Transform(1.0, 0.0, 0.0, 1.0, 1000.0, 0.0), nested]);
+        assert_eq!(result.len(), 2);
+        assert_eq!(
+            stats,
+            CullingStats {
+                drawn: 1,
+                culled: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_nested_transform_propagates() {
+        // nested list executes inline, its transform moves later draws out of view.
+        let nested = Command::Draw(Renderable::Commands(vec![
+            rectangle(10.0, 10.0),
+            Command::Transform(1.0, 0.0, 0.0, 1.0, 1000.0, 0.0),
+            rectangle(10.0, 10.0),
+        ]));
+        let (result, stats) = cull(&[nested, rectangle(10.0, 10.0)]);
+        assert_eq!(result.len(), 1);
+        match &result[0] {
+            Command::Draw(Renderable::Commands(commands)) => {
+                assert_eq!(commands.len(), 2);
+                assert!(matches!(commands[1], Command::Transform(..)));
+            }
+            _ => panic!("expected nested commands"),
+        }
+        assert_eq!(
+            stats,
+            CullingStats {
+                drawn: 1,
+                culled: 2
+            }
+        );
+    }
+
+    #[test]
+    fn test_nested_restore() {
+        let nested = Command::Draw(Renderable::Commands(vec![
+            Command::Store,
+            Command::Transform(1.0, 0.0, 0.0, 1.0, 1000.0, 0.0),
+            rectangle(10.0, 10.0),
+            Command::Restore,
+        ]));
+        let (result, stats) = cull(&[nested, rectangle(10.0, 10.0)]);
+        assert_eq!(result.len(), 2);
+        assert_eq!(
+            stats,
+            CullingStats {
+                drawn: 1,
+                culled: 1
+            }
+        );
+    }
+}
@@ -0,0 +1,689 @@
+use crate::{
+    oxygengine::{
+        composite_renderer::{
+            Command, Image, PathElement, Renderable, Text, TextAlign, TextBaseLine,
+        },
+        math::{Mat2d, Rect, Vec2},
+    },
+    Scalar,
+};
+use std::{collections::HashMap, f32::consts::PI};
+
+/// Number of line segments used to approximate curves.
+const CURVE_SEGMENTS: usize = 16;
+
+pub fn identity() -> Mat2d {
+    Mat2d([1.0, 0.0, 0.0, 1.0, 0.0, 0.0])
+}
+
+/// Matrix of `Command::Transform` arguments, same layout as canvas `transform()`.
+pub fn matrix(a: Scalar, b: Scalar, c: Scalar, d: Scalar, e: Scalar, f: Scalar) -> Mat2d {
+    Mat2d([a, b, c, d, e, f])
+}
+
+/// Composes matrices so that `child` gets applied first, like canvas `transform()` does.
+pub fn multiply(parent: &Mat2d, child: &Mat2d) -> Mat2d {
+    let [pa, pb, pc, pd, pe, pf] = parent.0;
+    let [ca, cb, cc, cd, ce, cf] = child.0;
+    Mat2d([
+        pa * ca + pc * cb,
+        pb * ca + pd * cb,
+        pa * cc + pc * cd,
+        pb * cc + pd * cd,
+        pa * ce + pc * cf + pe,
+        pb * ce + pd * cf + pf,
+    ])
+}
+
+pub fn inverse(matrix: &Mat2d) -> Option<Mat2d> {
+    let [a, b, c, d, e, f] = matrix.0;
+    let det = a * d - b * c;
+    if det.abs() < std::f32::EPSILON {
+        return None;
+    }
+    let inv = 1.0 / det;
+    Some(Mat2d([
+        d * inv,
+        -b * inv,
+        -c * inv,
+        a * inv,
+        (c * f - d * e) * inv,
+        (b * e - a * f) * inv,
+    ]))
+}
+
+pub fn transform_point(matrix: &Mat2d, point: Vec2) -> Vec2 {
+    let [a, b, c, d, e, f] = matrix.0;
+    Vec2::new(a * point.x + c * point.y + e, b * point.x + d * point.y + f)
+}
+
+/// Axis aligned bounding box.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Bounds {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Bounds {
+    pub fn from_points<I>(points: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = Vec2>,
+    {
+        let mut iter = points.into_iter();
+        let first = iter.next()?;
+        Some(iter.fold(
+            Self {
+                min: first,
+                max: first,
+            },
+            |bounds, point| bounds.include(point),
+        ))
+    }
+
+    pub fn from_rect(rect: &Rect) -> Self {
+        let a = Vec2::new(rect.x, rect.y);
+        let b = Vec2::new(rect.x + rect.w, rect.y + rect.h);
+        Self {
+            min: Vec2::new(a.x.min(b.x), a.y.min(b.y)),
+            max: Vec2::new(a.x.max(b.x), a.y.max(b.y)),
+        }
+    }
+
+    pub fn include(self, point: Vec2) -> Self {
+        Self {
+            min: Vec2::new(self.min.x.min(point.x), self.min.y.min(point.y)),
+            max: Vec2::new(self.max.x.max(point.x), self.max.y.max(point.y)),
+        }
+    }
+
+    pub fn union(self, other: Self) -> Self {
+        self.include(other.min).include(other.max)
+    }
+
+    pub fn width(&self) -> Scalar {
+        self.max.x - self.min.x
+    }
+
+    pub fn height(&self) -> Scalar {
+        self.max.y - self.min.y
+    }
+
+    pub fn center(&self) -> Vec2 {
+        Vec2::new(
+            (self.min.x + self.max.x) * 0.5,
+            (self.min.y + self.max.y) * 0.5,
+        )
+    }
+
+    pub fn corners(&self) -> [Vec2; 4] {
+        [
+            self.min,
+            Vec2::new(self.max.x, self.min.y),
+            self.max,
+            Vec2::new(self.min.x, self.max.y),
+        ]
+    }
+
+    pub fn contains(&self, point: Vec2) -> bool {
+        point.x >= self.min.x
+            && point.x <= self.max.x
+            && point.y >= self.min.y
+            && point.y <= self.max.y
+    }
+
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.min.x <= other.max.x
+            && self.max.x >= other.min.x
+            && self.min.y <= other.max.y
+            && self.max.y >= other.min.y
+    }
+
+    pub fn expand(self, margin: Scalar) -> Self {
+        Self {
+            min: Vec2::new(self.min.x - margin, self.min.y - margin),
+            max: Vec2::new(self.max.x + margin, self.max.y + margin),
+        }
+    }
+
+    /// Bounds of this box after transformation.
+    pub fn transformed(&self, matrix: &Mat2d) -> Self {
+        let corners = self.corners();
+        Self::from_points(corners.iter().map(|point| transform_point(matrix, *point))).unwrap()
+    }
+}
+
+/// Polylines approximating path elements, each `MoveTo` and `Rectangle` starts new one.
+pub fn flatten_path(elements: &[PathElement]) -> Vec<Vec<Vec2>> {
+    let mut result = vec![];
+    let mut current: Vec<Vec2> = vec![];
+    for element in elements {
+        match element {
+            PathElement::MoveTo(point) => {
+                if current.len() > 1 {
+                    result.push(std::mem::take(&mut current));
+                }
+                current.clear();
+                current.push(*point);
+            }
+            PathElement::LineTo(point) => current.push(*point),
+            PathElement::BezierCurveTo(c1, c2, point) => {
+                let from = current.last().copied().unwrap_or(*c1);
+                current.extend((1..=CURVE_SEGMENTS).map(|index| {
+                    let t = index as Scalar / CURVE_SEGMENTS as Scalar;
+                    let u = 1.0 - t;
+                    Vec2::new(
+                        u * u * u * from.x
+                            + 3.0 * u * u * t * c1.x
+                            + 3.0 * u * t * t * c2.x
+                            + t * t * t * point.x,
+                        u * u * u * from.y
+                            + 3.0 * u * u * t * c1.y
+                            + 3.0 * u * t * t * c2.y
+                            + t * t * t * point.y,
+                    )
+                }));
+            }
+            PathElement::QuadraticCurveTo(control, point) => {
+                let from = current.last().copied().unwrap_or(*control);
+                current.extend((1..=CURVE_SEGMENTS).map(|index| {
+                    let t = index as Scalar / CURVE_SEGMENTS as Scalar;
+                    let u = 1.0 - t;
+                    Vec2::new(
+                        u * u * from.x + 2.0 * u * t * control.x + t * t * point.x,
+                        u * u * from.y + 2.0 * u * t * control.y + t * t * point.y,
+                    )
+                }));
+            }
+            PathElement::Arc(center, radius, range) => {
+                current.extend(arc_points(
+                    *center,
+                    Vec2::new(*radius, *radius),
+                    0.0,
+                    range.start,
+                    range.end,
+                ));
+            }
+            PathElement::Ellipse(center, radius, rotation, range) => {
+                current.extend(arc_points(
+                    *center,
+                    *radius,
+                    *rotation,
+                    range.start,
+                    range.end,
+                ));
+            }
+            PathElement::Rectangle(rect) => {
+                if current.len() > 1 {
+                    result.push(std::mem::take(&mut current));
+                }
+                current.clear();
+                let corners = Bounds::from_rect(rect).corners();
+                let mut contour = corners.to_vec();
+                contour.push(corners[0]);
+                result.push(contour);
+            }
+        }
+    }
+    if current.len() > 1 {
+        result.push(current);
+    }
+    result
+}
+
+fn arc_points(
+    center: Vec2,
+    radius: Vec2,
+    rotation: Scalar,
+    start: Scalar,
+    end: Scalar,
+) -> impl Iterator<Item = Vec2> {
+    let sweep = end - start;
+    let segments = ((sweep.abs() / (2.0 * PI)) * 64.0)
+        .ceil()
+        .max(4.0)
+        .min(256.0) as usize;
+    let (sin, cos) = rotation.sin_cos();
+    (0..=segments).map(move |index| {
+        let angle = start + sweep * index as Scalar / segments as Scalar;
+        let x = radius.x * angle.cos();
+        let y = radius.y * angle.sin();
+        Vec2::new(center.x + x * cos - y * sin, center.y + x * sin + y * cos)
+    })
+}
+
+/// Approximate width to height ratio of glyphs, used to estimate text extents.
+pub const GLYPH_ASPECT: Scalar = 0.5;
+
+/// Estimated local space bounds of text, measured with fixed glyph aspect.
+pub fn text_bounds(text: &Text) -> Bounds {
+    let lines = text.text.lines().count().max(1) as Scalar;
+    let columns = text
+        .text
+        .lines()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0) as Scalar;
+    let width = columns * text.size * GLYPH_ASPECT;
+    let height = lines * text.size;
+    let x = match text.align {
+        TextAlign::Left => text.position.x,
+        TextAlign::Center => text.position.x - width * 0.5,
+        TextAlign::Right => text.position.x - width,
+    };
+    let y = match text.baseline {
+        TextBaseLine::Top | TextBaseLine::Hanging => text.position.y,
+        TextBaseLine::Middle => text.position.y - text.size * 0.5,
+        TextBaseLine::Bottom => text.position.y - text.size,
+        _ => text.position.y - text.size * 0.75,
+    };
+    Bounds {
+        min: Vec2::new(x, y),
+        max: Vec2::new(x + width, y + height),
+    }
+}
+
+/// Local space rectangle covered by image, `None` when its size is unknown.
+pub fn image_rect(image: &Image, image_sizes: &HashMap<String, Vec2>) -> Option<Rect> {
+    let mut destination = match (image.destination, image.source) {
+        (Some(destination), _) => destination,
+        (None, Some(source)) => Rect {
+            x: 0.0,
+            y: 0.0,
+            w: source.w,
+            h: source.h,
+        },
+        (None, None) => {
+            let size = image_sizes.get(image.image.as_ref())?;
+            Rect {
+                x: 0.0,
+                y: 0.0,
+                w: size.x,
+                h: size.y,
+            }
+        }
+    };
+    destination.x -= destination.w * image.alignment.x;
+    destination.y -= destination.h * image.alignment.y;
+    Some(destination)
+}
+
+/// Local space bounds of drawn renderable, expanded by half of stroke width.
+/// Fullscreen rectangles, masks and renderables of unknown size have no bounds.
+pub fn renderable_bounds(
+    renderable: &Renderable,
+    stroke: Option<Scalar>,
+    image_sizes: &HashMap<String, Vec2>,
+) -> Option<Bounds> {
+    let margin = stroke.unwrap_or(0.0) * 0.5;
+    let bounds = match renderable {
+        Renderable::Rectangle(rectangle) => Bounds::from_rect(&rectangle.rect),
+        Renderable::Path(path) => {
+            Bounds::from_points(flatten_path(&path.elements).into_iter().flatten())?
+        }
+        Renderable::Image(image) => Bounds::from_rect(&image_rect(image, image_sizes)?),
+        Renderable::Text(text) => text_bounds(text),
+        Renderable::Commands(commands) => {
+            return commands_bounds(commands, &identity(), image_sizes);
+        }
+        _ => return None,
+    };
+    Some(bounds.expand(margin))
+}
+
+/// Bounds of all drawn renderables in space of `transform`, following nested
+/// transforms and store/restore pairs.
+pub fn commands_bounds(
+    commands: &[Command],
+    transform: &Mat2d,
+    image_sizes: &HashMap<String, Vec2>,
+) -> Option<Bounds> {
+    commands_item_bounds(commands, transform, image_sizes)
+        .into_iter()
+        .flatten()
+        .fold(None, |result, bounds| match result {
+            Some(result) => Some(bounds.union(result)),
+            None => Some(bounds),
+        })
+}
+
+/// Bounds of each top level command in space of `transform`, `None` for commands
+/// that do not draw anything measurable.
+pub fn commands_item_bounds(
+    commands: &[Command],
+    transform: &Mat2d,
+    image_sizes: &HashMap<String, Vec2>,
+) -> Vec<Option<Bounds>> {
+    let mut transforms = TransformStack::new(*transform);
+    commands
+        .iter()
+        .map(|command| item_bounds(command, &mut transforms, image_sizes))
+        .collect()
+}
+
+/// Nested command lists share transform state with their parent list.
+fn item_bounds(
+    command: &Command,
+    transforms: &mut TransformStack,
+    image_sizes: &HashMap<String, Vec2>,
+) -> Option<Bounds> {
+    if transforms.apply(command) {
+        return None;
+    }
+    let (stroke, renderable) = match command {
+        Command::Draw(renderable) => (None, renderable),
+        Command::Stroke(width, renderable) => (Some(*width), renderable),
+        _ => return None,
+    };
+    if let Renderable::Commands(commands) = renderable {
+        return commands
+            .iter()
+            .filter_map(|command| item_bounds(command, transforms, image_sizes))
+            .fold(None, |result, bounds| match result {
+                Some(result) => Some(bounds.union(result)),
+                None => Some(bounds),
+            });
+    }
+    renderable_bounds(renderable, stroke, image_sizes)
+        .map(|bounds| bounds.transformed(&transforms.current))
+}
+
+/// Transform of command stream at current command, following `Transform`, `Store`
+/// and `Restore` commands the same way canvas does.
+#[derive(Debug, Clone)]
+pub struct TransformStack {
+    pub current: Mat2d,
+    stack: Vec<Mat2d>,
+}
+
+impl TransformStack {
+    pub fn new(transform: Mat2d) -> Self {
+        Self {
+            current: transform,
+            stack: vec![],
+        }
+    }
+
+    /// Applies state command, returns false for commands that do not change transform.
+    pub fn apply(&mut self, command: &Command) -> bool {
+        match command {
+            Command::Transform(a, b, c, d, e, f) => {
+                self.current = multiply(&self.current, &matrix(*a, *b, *c, *d, *e, *f));
+            }
+            Command::Store => self.stack.push(self.current),
+            Command::Restore => {
+                if let Some(transform) = self.stack.pop() {
+                    self.current = transform;
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// Tells if point lies inside closed contours using non-zero winding rule.
+pub fn contours_contain(contours: &[Vec<Vec2>], point: Vec2) -> bool {
+    let mut winding = 0;
+    for contour in contours.iter().filter(|contour| contour.len() > 2) {
+        for (from, to) in contour
+            .iter()
+            .zip(contour.iter().skip(1).chain(contour.first()))
+        {
+            let side = (to.x - from.x) * (point.y - from.y) - (point.x - from.x) * (to.y - from.y);
+            if from.y <= point.y {
+                if to.y > point.y && side > 0.0 {
+                    winding += 1;
+                }
+            } else if to.y <= point.y && side < 0.0 {
+                winding -= 1;
+            }
+        }
+    }
+    winding != 0
+}
+
+/// Shortest distance from point to any segment of polylines.
+pub fn polylines_distance(polylines: &[Vec<Vec2>], point: Vec2) -> Scalar {
+    polylines
+        .iter()
+        .flat_map(|polyline| polyline.iter().zip(polyline.iter().skip(1)))
+        .map(|(from, to)| {
+            let (dx, dy) = (to.x - from.x, to.y - from.y);
+            let length = dx * dx + dy * dy;
+            let t = if length > 0.0 {
+                (((point.x - from.x) * dx + (point.y - from.y) * dy) / length)
+                    .max(0.0)
+                    .min(1.0)
+            } else {
+                0.0
+            };
+            let (x, y) = (from.x + dx * t - point.x, from.y + dy * t - point.y);
+            (x * x + y * y).sqrt()
+        })
+        .fold(Scalar::INFINITY, |a, b| a.min(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oxygengine::{composite_renderer::Rectangle, math::Color};
+
+    fn assert_near(a: Vec2, b: Vec2) {
+        assert!(
+            (a.x - b.x).abs() < 1.0e-4 && (a.y - b.y).abs() < 1.0e-4,
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    fn rect(x: Scalar, y: Scalar, w: Scalar, h: Scalar) -> Rect {
+        Rect { x, y, w, h }
+    }
+
+    #[test]
+    fn test_matrices() {
+        let translate = matrix(1.0, 0.0, 0.0, 1.0, 10.0, 20.0);
+        let scale = matrix(2.0, 0.0, 0.0, 3.0, 0.0, 0.0);
+        // child gets applied first: scale, then translate.
+        let transform = multiply(&translate, &scale);
+        assert_near(
+            transform_point(&transform, Vec2::new(1.0, 1.0)),
+            Vec2::new(12.0, 23.0),
+        );
+        let inverted = inverse(&transform).unwrap();
+        assert_near(
+            transform_point(&inverted, Vec2::new(12.0, 23.0)),
+            Vec2::new(1.0, 1.0),
+        );
+        let identity = multiply(&transform, &inverted);
+        assert_near(
+            transform_point(&identity, Vec2::new(5.0, -7.0)),
+            Vec2::new(5.0, -7.0),
+        );
+        assert!(inverse(&matrix(1.0, 2.0, 2.0, 4.0, 0.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn test_bounds() {
+        let bounds = Bounds::from_rect(&rect(10.0, 10.0, -10.0, 20.0));
+        assert_near(bounds.min, Vec2::new(0.0, 10.0));
+        assert_near(bounds.max, Vec2::new(10.0, 30.0));
+        assert_near(bounds.center(), Vec2::new(5.0, 20.0));
+        assert!(bounds.contains(Vec2::new(10.0, 30.0)));
+        assert!(!bounds.contains(Vec2::new(10.1, 30.0)));
+        let other = Bounds::from_rect(&rect(11.0, 0.0, 5.0, 5.0));
+        assert!(!bounds.intersects(&other));
+        assert!(bounds.expand(1.0).intersects(&other));
+        let union = bounds.union(other);
+        assert_near(union.min, Vec2::new(0.0, 0.0));
+        assert_near(union.max, Vec2::new(16.0, 30.0));
+        // quarter turn around origin.
+        let rotated = bounds.transformed(&matrix(0.0, 1.0, -1.0, 0.0, 0.0, 0.0));
+        assert_near(rotated.min, Vec2::new(-30.0, 0.0));
+        assert_near(rotated.max, Vec2::new(-10.0, 10.0));
+    }
+
+    #[test]
+    fn test_flatten_path() {
+        let contours = flatten_path(&[
+            PathElement::MoveTo(Vec2::new(0.0, 0.0)),
+            PathElement::LineTo(Vec2::new(10.0, 0.0)),
+            PathElement::LineTo(Vec2::new(10.0, 10.0)),
+            PathElement::MoveTo(Vec2::new(50.0, 50.0)),
+            PathElement::Rectangle(rect(20.0, 20.0, 5.0, 5.0)),
+            PathElement::MoveTo(Vec2::new(0.0, 0.0)),
+            PathElement::QuadraticCurveTo(Vec2::new(5.0, 10.0), Vec2::new(10.0, 0.0)),
+        ]);
+        assert_eq!(contours.len(), 3);
+        assert_eq!(contours[0].len(), 3);
+        assert_eq!(contours[1].len(), 5);
+        assert_eq!(contours[2].len(), CURVE_SEGMENTS + 1);
+        assert_near(*contours[2].last().unwrap(), Vec2::new(10.0, 0.0));
+        assert_near(contours[2][CURVE_SEGMENTS / 2], Vec2::new(5.0, 5.0));
+    }
+
+    #[test]
+    fn test_arc() {
+        let contours = flatten_path(&[PathElement::Arc(Vec2::new(5.0, 5.0), 2.0, 0.0..(2.0 * PI))]);
+        assert_eq!(contours.len(), 1);
+        assert!(contours[0].iter().all(|point| {
+            let (x, y) = (point.x - 5.0, point.y - 5.0);
+            ((x * x + y * y).sqrt() - 2.0).abs() < 1.0e-4
+        }));
+        assert!(contours_contain(&contours, Vec2::new(5.0, 5.0)));
+        assert!(!contours_contain(&contours, Vec2::new(7.5, 5.0)));
+    }
+
+    #[test]
+    fn test_contours() {
+        let square = vec![vec![
+            Vec2::new(0.0, 0.0),
+            Vec2::new(10.0, 0.0),
+            Vec2::new(10.0, 10.0),
+            Vec2::new(0.0, 10.0),
+        ]];
+        assert!(contours_contain(&square, Vec2::new(5.0, 5.0)));
+        assert!(!contours_contain(&square, Vec2::new(15.0, 5.0)));
+        assert!((polylines_distance(&square, Vec2::new(5.0, -3.0)) - 3.0).abs() < 1.0e-4);
+        assert!((polylines_distance(&square, Vec2::new(13.0, 14.0)) - 5.0).abs() < 1.0e-4);
+        assert_eq!(
+            polylines_distance(&[], Vec2::new(0.0, 0.0)),
+            Scalar::INFINITY
+        );
+    }
+
+    #[test]
+    fn test_text_bounds() {
+        let text = |align, baseline| {
+            Text::new_owned("Verdana".to_owned(), "abcd\nab".to_owned())
+                .align(align)
+                .baseline(baseline)
+                .position(Vec2::new(100.0, 100.0))
+                .size(10.0)
+        };
+        let bounds = text_bounds(&text(TextAlign::Left, TextBaseLine::Top));
+        assert_near(bounds.min, Vec2::new(100.0, 100.0));
+        assert_near(bounds.max, Vec2::new(120.0, 120.0));
+        let bounds = text_bounds(&text(TextAlign::Center, TextBaseLine::Middle));
+        assert_near(bounds.min, Vec2::new(90.0, 95.0));
+        let bounds = text_bounds(&text(TextAlign::Right, TextBaseLine::Bottom));
+        assert_near(bounds.min, Vec2::new(80.0, 90.0));
+    }
+
+    #[test]
+    fn test_image_rect() {
+        let image = |source, destination, alignment| Image {
+            image: "logo.png".into(),
+            source,
+            destination,
+            alignment,
+        };
+        let assert_rect = |rect: Option<Rect>, x, y, w, h| {
+            let rect = rect.unwrap();
+            assert_eq!((rect.x, rect.y, rect.w, rect.h), (x, y, w, h));
+        };
+        let mut sizes = HashMap::new();
+        assert!(image_rect(&image(None, None, Vec2::new(0.0, 0.0)), &sizes).is_none());
+        sizes.insert("logo.png".to_owned(), Vec2::new(40.0, 20.0));
+        assert_rect(
+            image_rect(&image(None, None, Vec2::new(0.5, 0.5)), &sizes),
+            -20.0,
+            -10.0,
+            40.0,
+            20.0,
+        );
+        let source = Some(rect(5.0, 5.0, 8.0, 4.0));
+        assert_rect(
+            image_rect(&image(source, None, Vec2::new(0.0, 0.0)), &sizes),
+            0.0,
+            0.0,
+            8.0,
+            4.0,
+        );
+        let destination = Some(rect(1.0, 2.0, 4.0, 4.0));
+        assert_rect(
+            image_rect(&image(source, destination, Vec2::new(0.5, 1.0)), &sizes),
+            -1.0,
+            -2.0,
+            4.0,
+            4.0,
+        );
+    }
+
+    #[test]
+    fn test_commands_bounds() {
+        let square = |x, y| {
+            Command::Draw(Renderable::Rectangle(Rectangle {
+                color: Color::rgba(255, 255, 255, 255),
+                rect: rect(x, y, 10.0, 10.0),
+            }))
+        };
+        let commands = vec![
+            Command::Store,
+            Command::Transform(1.0, 0.0, 0.0, 1.0, 100.0, 0.0),
+            square(0.0, 0.0),
+            Command::Restore,
+            Command::Stroke(
+                2.0,
+                Renderable::Rectangle(Rectangle {
+                    color: Color::rgba(255, 255, 255, 255),
+                    rect: rect(0.0, 0.0, 10.0, 10.0),
+                }),
+            ),
+            Command::Draw(Renderable::FullscreenRectangle(Color::rgba(0, 0, 0, 255))),
+        ];
+        let items = commands_item_bounds(&commands, &identity(), &HashMap::new());
+        assert_eq!(items.len(), 6);
+        assert!(items[0].is_none() && items[1].is_none() && items[3].is_none());
+        assert_near(items[2].unwrap().min, Vec2::new(100.0, 0.0));
+        assert_near(items[4].unwrap().min, Vec2::new(-1.0, -1.0));
+        assert!(items[5].is_none());
+        let bounds = commands_bounds(&commands, &identity(), &HashMap::new()).unwrap();
+        assert_near(bounds.min, Vec2::new(-1.0, -1.0));
+        assert_near(bounds.max, Vec2::new(110.0, 11.0));
+    }
+
+    #[test]
+    fn test_nested_commands_bounds() {
+        let square = |x| {
+            Command::Draw(Renderable::Rectangle(Rectangle {
+                color: Color::rgba(255, 255, 255, 255),
+                rect: rect(x, 0.0, 10.0, 10.0),
+            }))
+        };
+        let commands = vec![
+            Command::Draw(Renderable::Commands(vec![
+                square(0.0),
+                Command::Transform(1.0, 0.0, 0.0, 1.0, 100.0, 0.0),
+                square(0.0),
+            ])),
+            square(0.0),
+        ];
+        let items = commands_item_bounds(&commands, &identity(), &HashMap::new());
+        assert_near(items[0].unwrap().min, Vec2::new(0.0, 0.0));
+        assert_near(items[0].unwrap().max, Vec2::new(110.0, 10.0));
+        // transform set inside nested list applies to later commands too.
+        assert_near(items[1].unwrap().min, Vec2::new(100.0, 0.0));
+    }
+}
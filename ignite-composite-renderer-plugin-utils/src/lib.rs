@@ -1,8 +1,20 @@
+pub mod atlas;
+pub mod controls;
+pub mod culling;
+pub mod geometry;
+pub mod overlays;
+pub mod picking;
+#[cfg(feature = "raster")]
+pub mod raster;
+#[cfg(feature = "scene")]
+pub mod scene;
+pub mod stats;
+
 pub use oxygengine_composite_renderer as oxygengine;
 use oxygengine_composite_renderer::{
     component::{CompositeCamera, CompositeTransform},
-    composite_renderer::{Command, RenderState},
-    math::{Color, Mat2d, Vec2},
+    composite_renderer::{Command, Mask, PathElement, RenderState, Renderable},
+    math::{Color, Mat2d, Rect, Vec2},
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -36,7 +48,7 @@ impl Into<RenderState> for RenderStateProxy {
     }
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Camera {
     #[serde(default)]
     pub order: f32,
@@ -44,13 +56,114 @@ pub struct Camera {
     pub camera: CompositeCamera,
     #[serde(default)]
     pub transform: CompositeTransform,
+    /// Area of view covered by camera, in fractions of view size. Whole view when not set.
+    #[serde(default)]
+    pub viewport: Option<Rect>,
+    /// Color filling viewport before camera layers get drawn.
+    #[serde(default)]
+    pub clear_color: Option<Color>,
+    /// Restricts drawing to viewport.
+    #[serde(default = "Camera::default_clip")]
+    pub clip: bool,
     #[serde(skip)]
     pub world_transform: Mat2d,
     #[serde(skip)]
     pub world_inverse_transform: Mat2d,
 }
 
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            order: 0.0,
+            camera: Default::default(),
+            transform: Default::default(),
+            viewport: None,
+            clear_color: None,
+            clip: Self::default_clip(),
+            world_transform: Default::default(),
+            world_inverse_transform: Default::default(),
+        }
+    }
+}
+
 impl Camera {
+    fn default_clip() -> bool {
+        true
+    }
+
+    /// Viewport in view pixels.
+    pub fn viewport_rect(&self, view_size: Vec2) -> Rect {
+        match self.viewport {
+            Some(viewport) => Rect {
+                x: viewport.x * view_size.x,
+                y: viewport.y * view_size.y,
+                w: viewport.w * view_size.x,
+                h: viewport.h * view_size.y,
+            },
+            None => Rect {
+                x: 0.0,
+                y: 0.0,
+                w: view_size.x,
+                h: view_size.y,
+            },
+        }
+    }
+
+    /// View matrix mapping world space into view pixels, fitted into viewport.
+    pub fn view_matrix(&self, view_size: Vec2) -> Mat2d {
+        let viewport = self.viewport_rect(view_size);
+        let matrix = self
+            .camera
+            .view_matrix(&self.transform, Vec2::new(viewport.w, viewport.h));
+        geometry::multiply(
+            &geometry::matrix(1.0, 0.0, 0.0, 1.0, viewport.x, viewport.y),
+            &matrix,
+        )
+    }
+
+    /// Tells if view point lies in area this camera draws to.
+    pub fn covers(&self, view_size: Vec2, point: Vec2) -> bool {
+        if !self.clip {
+            return true;
+        }
+        let viewport = self.viewport_rect(view_size);
+        point.x >= viewport.x
+            && point.y >= viewport.y
+            && point.x < viewport.x + viewport.w
+            && point.y < viewport.y + viewport.h
+    }
+
+    /// Mask command clipping further drawing to viewport.
+    pub fn viewport_mask(&self, view_size: Vec2) -> Command<'static> {
+        let Rect { x, y, w, h } = self.viewport_rect(view_size);
+        Command::Draw(Renderable::Mask(Mask {
+            elements: vec![
+                PathElement::MoveTo(Vec2::new(x, y)),
+                PathElement::LineTo(Vec2::new(x + w, y)),
+                PathElement::LineTo(Vec2::new(x + w, y + h)),
+                PathElement::LineTo(Vec2::new(x, y + h)),
+                PathElement::LineTo(Vec2::new(x, y)),
+            ],
+        }))
+    }
+
+    /// Tags of commands drawn by this camera, in drawing order. Cameras without
+    /// tags draw every tag sorted by name.
+    pub fn visible_tags<T>(&self, commands: &HashMap<String, T>) -> Vec<String> {
+        if self.camera.tags.is_empty() {
+            let mut tags = commands.keys().cloned().collect::<Vec<_>>();
+            tags.sort();
+            tags
+        } else {
+            self.camera
+                .tags
+                .iter()
+                .map(|tag| tag.to_string())
+                .filter(|tag| commands.contains_key(tag))
+                .collect()
+        }
+    }
+
     pub fn screen_to_world_space(&self, point: Vec2) -> Vec2 {
         self.world_inverse_transform * point
     }
@@ -62,3 +175,83 @@ impl Camera {
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct RenderCommands(pub HashMap<String, Vec<Command<'static>>>);
+
+/// Splice of single tag commands list: removes `remove` commands starting at
+/// `start` and puts `insert` in their place, covering inserts, removals and
+/// replacements of ranges.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenderCommandsPatch {
+    pub tag: String,
+    #[serde(default)]
+    pub start: usize,
+    #[serde(default)]
+    pub remove: usize,
+    #[serde(default)]
+    pub insert: Vec<Command<'static>>,
+    /// User ids of inserted commands.
+    #[serde(default)]
+    pub ids: Option<Vec<Option<String>>>,
+}
+
+impl RenderCommandsPatch {
+    /// Applies patch to commands and their ids, returns false when range is out of bounds.
+    pub fn apply(
+        self,
+        commands: &mut HashMap<String, Vec<Command<'static>>>,
+        ids: &mut picking::CommandIds,
+    ) -> bool {
+        let end = self.start + self.remove;
+        let list = match commands.get_mut(&self.tag) {
+            Some(list) if end <= list.len() => list,
+            Some(_) => return false,
+            None if end == 0 => commands.entry(self.tag.clone()).or_default(),
+            None => return false,
+        };
+        let count = self.insert.len();
+        list.splice(self.start..end, self.insert);
+        let empty = list.is_empty();
+        if self.ids.is_some() || ids.contains_key(&self.tag) {
+            let tag_ids = ids.entry(self.tag.clone()).or_default();
+            tag_ids.resize(tag_ids.len().max(end), None);
+            let mut inserted = self.ids.unwrap_or_default();
+            inserted.resize(count, None);
+            tag_ids.splice(self.start..end, inserted);
+        }
+        if empty {
+            commands.remove(&self.tag);
+            ids.remove(&self.tag);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patch(tag: &str, start: usize, remove: usize, insert: usize) -> RenderCommandsPatch {
+        RenderCommandsPatch {
+            tag: tag.to_owned(),
+            start,
+            remove,
+            insert: vec![Command::Store; insert],
+            ids: None,
+        }
+    }
+
+    #[test]
+    fn test_patch_apply() {
+        let mut commands = HashMap::new();
+        let mut ids = picking::CommandIds::new();
+        assert!(!patch("a", 1, 0, 1).apply(&mut commands, &mut ids));
+        assert!(!patch("a", 0, 1, 0).apply(&mut commands, &mut ids));
+        assert!(commands.is_empty());
+        assert!(patch("a", 0, 0, 2).apply(&mut commands, &mut ids));
+        assert_eq!(commands["a"].len(), 2);
+        assert!(!patch("a", 1, 2, 0).apply(&mut commands, &mut ids));
+        assert_eq!(commands["a"].len(), 2);
+        assert!(patch("a", 0, 2, 0).apply(&mut commands, &mut ids));
+        assert!(commands.is_empty());
+        assert!(ids.is_empty());
+    }
+}
@@ -0,0 +1,711 @@
+use crate::{
+    geometry::{commands_item_bounds, inverse, transform_point, Bounds},
+    oxygengine::{
+        composite_renderer::{
+            Command, Path, PathElement, Rectangle, Renderable, Text, TextAlign, TextBaseLine,
+        },
+        math::{Color, Mat2d, Rect, Vec2},
+    },
+    picking::CommandIds,
+    Camera, Scalar,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Upper limit of lines generated per grid level, protects from degenerate zoom.
+const MAX_GRID_LINES: usize = 512;
+/// Upper limit of subdivision steps taken while searching for grid spacing.
+const MAX_GRID_STEPS: usize = 64;
+/// Upper limit of grid `min_pixels`, larger cells would not fit any view.
+const MAX_GRID_PIXELS: Scalar = 4096.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GridOverlay {
+    #[serde(default)]
+    pub enabled: bool,
+    /// World space size of the smallest grid cell.
+    #[serde(default = "GridOverlay::default_spacing")]
+    pub spacing: Scalar,
+    /// Every that many cells gets a major line, also used as zoom step.
+    #[serde(default = "GridOverlay::default_subdivisions")]
+    pub subdivisions: usize,
+    /// Cells smaller than that many pixels on screen get merged.
+    #[serde(default = "GridOverlay::default_min_pixels")]
+    pub min_pixels: Scalar,
+    #[serde(default = "GridOverlay::default_color")]
+    pub color: Color,
+    #[serde(default = "GridOverlay::default_major_color")]
+    pub major_color: Color,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Default for GridOverlay {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            spacing: Self::default_spacing(),
+            subdivisions: Self::default_subdivisions(),
+            min_pixels: Self::default_min_pixels(),
+            color: Self::default_color(),
+            major_color: Self::default_major_color(),
+            tags: vec![],
+        }
+    }
+}
+
+impl GridOverlay {
+    fn default_spacing() -> Scalar {
+        10.0
+    }
+
+    fn default_subdivisions() -> usize {
+        10
+    }
+
+    fn default_min_pixels() -> Scalar {
+        8.0
+    }
+
+    fn default_color() -> Color {
+        Color::rgba(255, 255, 255, 24)
+    }
+
+    fn default_major_color() -> Color {
+        Color::rgba(255, 255, 255, 64)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AxesOverlay {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "AxesOverlay::default_x_color")]
+    pub x_color: Color,
+    #[serde(default = "AxesOverlay::default_y_color")]
+    pub y_color: Color,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Default for AxesOverlay {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            x_color: Self::default_x_color(),
+            y_color: Self::default_y_color(),
+            tags: vec![],
+        }
+    }
+}
+
+impl AxesOverlay {
+    fn default_x_color() -> Color {
+        Color::rgba(220, 60, 60, 200)
+    }
+
+    fn default_y_color() -> Color {
+        Color::rgba(60, 200, 60, 200)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RulersOverlay {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Thickness in pixels of rulers along top and left screen edges.
+    #[serde(default = "RulersOverlay::default_size")]
+    pub size: Scalar,
+    #[serde(default = "RulersOverlay::default_font")]
+    pub font: String,
+    #[serde(default = "RulersOverlay::default_background")]
+    pub background: Color,
+    #[serde(default = "RulersOverlay::default_color")]
+    pub color: Color,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Default for RulersOverlay {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            size: Self::default_size(),
+            font: Self::default_font(),
+            background: Self::default_background(),
+            color: Self::default_color(),
+            tags: vec![],
+        }
+    }
+}
+
+impl RulersOverlay {
+    fn default_size() -> Scalar {
+        20.0
+    }
+
+    fn default_font() -> String {
+        "Verdana".to_owned()
+    }
+
+    fn default_background() -> Color {
+        Color::rgba(30, 30, 30, 230)
+    }
+
+    fn default_color() -> Color {
+        Color::rgba(200, 200, 200, 255)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelectionOverlay {
+    #[serde(default = "SelectionOverlay::default_enabled")]
+    pub enabled: bool,
+    /// User ids of selected commands, as attached to render commands.
+    #[serde(default)]
+    pub ids: Vec<String>,
+    #[serde(default = "SelectionOverlay::default_color")]
+    pub color: Color,
+    /// Size in pixels of resize handles, zero hides them.
+    #[serde(default = "SelectionOverlay::default_handle_size")]
+    pub handle_size: Scalar,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Default for SelectionOverlay {
+    fn default() -> Self {
+        Self {
+            enabled: Self::default_enabled(),
+            ids: vec![],
+            color: Self::default_color(),
+            handle_size: Self::default_handle_size(),
+            tags: vec![],
+        }
+    }
+}
+
+impl SelectionOverlay {
+    fn default_enabled() -> bool {
+        true
+    }
+
+    fn default_color() -> Color {
+        Color::rgba(80, 160, 255, 255)
+    }
+
+    fn default_handle_size() -> Scalar {
+        6.0
+    }
+}
+
+/// Render statistics box drawn once per frame in top right view corner.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsOverlay {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "StatsOverlay::default_font")]
+    pub font: String,
+    #[serde(default = "StatsOverlay::default_size")]
+    pub size: Scalar,
+    #[serde(default = "StatsOverlay::default_background")]
+    pub background: Color,
+    #[serde(default = "StatsOverlay::default_color")]
+    pub color: Color,
+}
+
+impl Default for StatsOverlay {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            font: Self::default_font(),
+            size: Self::default_size(),
+            background: Self::default_background(),
+            color: Self::default_color(),
+        }
+    }
+}
+
+impl StatsOverlay {
+    fn default_font() -> String {
+        "monospace".to_owned()
+    }
+
+    fn default_size() -> Scalar {
+        12.0
+    }
+
+    fn default_background() -> Color {
+        Color::rgba(0, 0, 0, 180)
+    }
+
+    fn default_color() -> Color {
+        Color::rgba(255, 255, 255, 255)
+    }
+
+    /// Screen space commands of text box with given lines.
+    pub fn commands(&self, lines: &[String], view_size: Vec2) -> Vec<Command<'static>> {
+        if !self.enabled || lines.is_empty() {
+            return vec![];
+        }
+        let padding = self.size * 0.5;
+        let line_height = self.size * 1.2;
+        let width = lines
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0) as Scalar
+            * self.size
+            * 0.6
+            + padding * 2.0;
+        let height = lines.len() as Scalar * line_height + padding * 2.0;
+        let x = view_size.x - width - padding;
+        let mut result = vec![rectangle(self.background, x, padding, width, height)];
+        for (index, line) in lines.iter().enumerate() {
+            result.push(Command::Draw(Renderable::Text(
+                Text::new_owned(self.font.clone(), line.clone())
+                    .color(self.color)
+                    .align(TextAlign::Left)
+                    .baseline(TextBaseLine::Top)
+                    .position(Vec2::new(
+                        x + padding,
+                        padding * 2.0 + index as Scalar * line_height,
+                    ))
+                    .size(self.size),
+            )));
+        }
+        result
+    }
+}
+
+/// Editor layers drawn in screen space on top of user commands of each camera.
+/// Overlay with non-empty `tags` shows only through cameras having one of them.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Overlays {
+    #[serde(default)]
+    pub grid: GridOverlay,
+    #[serde(default)]
+    pub axes: AxesOverlay,
+    #[serde(default)]
+    pub rulers: RulersOverlay,
+    #[serde(default)]
+    pub selection: SelectionOverlay,
+    #[serde(default)]
+    pub stats: StatsOverlay,
+}
+
+impl Overlays {
+    /// Toggles overlay by name, returns false for unknown names.
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        match name {
+            "grid" => self.grid.enabled = enabled,
+            "axes" => self.axes.enabled = enabled,
+            "rulers" => self.rulers.enabled = enabled,
+            "selection" => self.selection.enabled = enabled,
+            "stats" => self.stats.enabled = enabled,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Screen space commands of all enabled overlays visible through camera.
+    pub fn commands(
+        &self,
+        camera: &Camera,
+        view: &Mat2d,
+        view_size: Vec2,
+        commands: &HashMap<String, Vec<Command<'static>>>,
+        ids: &CommandIds,
+        image_sizes: &HashMap<String, Vec2>,
+    ) -> Vec<Command<'static>> {
+        let mut result = vec![];
+        let inverse_view = match inverse(view) {
+            Some(inverse_view) => inverse_view,
+            None => return result,
+        };
+        let screen = Bounds {
+            min: Vec2::new(0.0, 0.0),
+            max: view_size,
+        };
+        let world = screen.transformed(&inverse_view);
+        let spacing = self.grid_spacing(view);
+        if self.grid.enabled && is_visible(&self.grid.tags, camera) {
+            self.grid_commands(view, &world, spacing, &mut result);
+        }
+        if self.axes.enabled && is_visible(&self.axes.tags, camera) {
+            line(
+                &mut result,
+                transform_point(view, Vec2::new(world.min.x, 0.0)),
+                transform_point(view, Vec2::new(world.max.x, 0.0)),
+                self.axes.x_color,
+                1.0,
+            );
+            line(
+                &mut result,
+                transform_point(view, Vec2::new(0.0, world.min.y)),
+                transform_point(view, Vec2::new(0.0, world.max.y)),
+                self.axes.y_color,
+                1.0,
+            );
+        }
+        if self.selection.enabled && is_visible(&self.selection.tags, camera) {
+            self.selection_commands(camera, view, commands, ids, image_sizes, &mut result);
+        }
+        if self.rulers.enabled && is_visible(&self.rulers.tags, camera) {
+            self.rulers_commands(view, view_size, &world, spacing, &mut result);
+        }
+        result
+    }
+
+    /// World space grid cell size that is at least `min_pixels` wide on screen.
+    fn grid_spacing(&self, view: &Mat2d) -> Scalar {
+        let [a, b, ..] = view.0;
+        let pixels_per_unit = (a * a + b * b).sqrt().max(std::f32::EPSILON);
+        let step = self.grid.subdivisions.max(2) as Scalar;
+        let min_pixels = self.grid.min_pixels.max(1.0).min(MAX_GRID_PIXELS);
+        let mut spacing = self.grid.spacing.abs().max(std::f32::EPSILON);
+        for _ in 0..MAX_GRID_STEPS {
+            if spacing * pixels_per_unit >= min_pixels {
+                break;
+            }
+            spacing *= step;
+        }
+        for _ in 0..MAX_GRID_STEPS {
+            if spacing * pixels_per_unit < min_pixels * step {
+                break;
+            }
+            spacing /= step;
+        }
+        spacing
+    }
+
+    fn grid_commands(
+        &self,
+        view: &Mat2d,
+        world: &Bounds,
+        spacing: Scalar,
+        result: &mut Vec<Command<'static>>,
+    ) {
+        let major = spacing * self.grid.subdivisions.max(2) as Scalar;
+        for (size, color) in &[(spacing, self.grid.color), (major, self.grid.major_color)] {
+            let mut elements = vec![];
+            for x in grid_values(world.min.x, world.max.x, *size) {
+                elements.push(PathElement::MoveTo(transform_point(
+                    view,
+                    Vec2::new(x, world.min.y),
+                )));
+                elements.push(PathElement::LineTo(transform_point(
+                    view,
+                    Vec2::new(x, world.max.y),
+                )));
+            }
+            for y in grid_values(world.min.y, world.max.y, *size) {
+                elements.push(PathElement::MoveTo(transform_point(
+                    view,
+                    Vec2::new(world.min.x, y),
+                )));
+                elements.push(PathElement::LineTo(transform_point(
+                    view,
+                    Vec2::new(world.max.x, y),
+                )));
+            }
+            if !elements.is_empty() {
+                result.push(Command::Stroke(
+                    1.0,
+                    Renderable::Path(Path {
+                        color: *color,
+                        elements,
+                    }),
+                ));
+            }
+        }
+    }
+
+    fn rulers_commands(
+        &self,
+        view: &Mat2d,
+        view_size: Vec2,
+        world: &Bounds,
+        spacing: Scalar,
+        result: &mut Vec<Command<'static>>,
+    ) {
+        let size = self.rulers.size;
+        let step = spacing * self.grid.subdivisions.max(2) as Scalar;
+        result.push(rectangle(
+            self.rulers.background,
+            0.0,
+            0.0,
+            view_size.x,
+            size,
+        ));
+        result.push(rectangle(
+            self.rulers.background,
+            0.0,
+            size,
+            size,
+            view_size.y - size,
+        ));
+        let mut ticks = vec![];
+        for x in grid_values(world.min.x, world.max.x, step) {
+            let screen = transform_point(view, Vec2::new(x, world.min.y));
+            if screen.x >= size {
+                ticks.push(PathElement::MoveTo(Vec2::new(screen.x, size * 0.5)));
+                ticks.push(PathElement::LineTo(Vec2::new(screen.x, size)));
+                result.push(label(&self.rulers, x, Vec2::new(screen.x + 2.0, 1.0)));
+            }
+        }
+        for y in grid_values(world.min.y, world.max.y, step) {
+            let screen = transform_point(view, Vec2::new(world.min.x, y));
+            if screen.y >= size {
+                ticks.push(PathElement::MoveTo(Vec2::new(size * 0.5, screen.y)));
+                ticks.push(PathElement::LineTo(Vec2::new(size, screen.y)));
+                result.push(label(&self.rulers, y, Vec2::new(1.0, screen.y + 2.0)));
+            }
+        }
+        if !ticks.is_empty() {
+            result.push(Command::Stroke(
+                1.0,
+                Renderable::Path(Path {
+                    color: self.rulers.color,
+                    elements: ticks,
+                }),
+            ));
+        }
+    }
+
+    fn selection_commands(
+        &self,
+        camera: &Camera,
+        view: &Mat2d,
+        commands: &HashMap<String, Vec<Command<'static>>>,
+        ids: &CommandIds,
+        image_sizes: &HashMap<String, Vec2>,
+        result: &mut Vec<Command<'static>>,
+    ) {
+        if self.selection.ids.is_empty() {
+            return;
+        }
+        for (tag, list) in commands {
+            if !camera.camera.tags.is_empty() && !camera.camera.tags.iter().any(|item| item == tag)
+            {
+                continue;
+            }
+            let tag_ids = match ids.get(tag) {
+                Some(tag_ids) => tag_ids,
+                None => continue,
+            };
+            let bounds = commands_item_bounds(list, view, image_sizes);
+            for (id, bounds) in tag_ids.iter().zip(bounds.into_iter()) {
+                let bounds = match (id, bounds) {
+                    (Some(id), Some(bounds)) if self.selection.ids.contains(id) => bounds,
+                    _ => continue,
+                };
+                result.push(Command::Stroke(
+                    1.0,
+                    Renderable::Rectangle(Rectangle {
+                        color: self.selection.color,
+                        rect: Rect {
+                            x: bounds.min.x,
+                            y: bounds.min.y,
+                            w: bounds.width(),
+                            h: bounds.height(),
+                        },
+                    }),
+                ));
+                let half = self.selection.handle_size * 0.5;
+                if half > 0.0 {
+                    let center = bounds.center();
+                    let handles = bounds.corners().to_vec().into_iter().chain(vec![
+                        Vec2::new(center.x, bounds.min.y),
+                        Vec2::new(bounds.max.x, center.y),
+                        Vec2::new(center.x, bounds.max.y),
+                        Vec2::new(bounds.min.x, center.y),
+                    ]);
+                    for handle in handles {
+                        result.push(rectangle(
+                            self.selection.color,
+                            handle.x - half,
+                            handle.y - half,
+                            half * 2.0,
+                            half * 2.0,
+                        ));
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn is_visible(tags: &[String], camera: &Camera) -> bool {
+    tags.is_empty()
+        || camera.camera.tags.is_empty()
+        || camera
+            .camera
+            .tags
+            .iter()
+            .any(|tag| tags.iter().any(|item| item == tag))
+}
+
+fn grid_values(from: Scalar, to: Scalar, step: Scalar) -> impl Iterator<Item = Scalar> {
+    let first = (from / step).ceil() as i64;
+    let last = (to / step).floor() as i64;
+    let count = ((last - first + 1).max(0) as usize).min(MAX_GRID_LINES);
+    (0..count).map(move |index| (first + index as i64) as Scalar * step)
+}
+
+fn line(result: &mut Vec<Command<'static>>, from: Vec2, to: Vec2, color: Color, width: Scalar) {
+    result.push(Command::Stroke(
+        width,
+        Renderable::Path(Path {
+            color,
+            elements: vec![PathElement::MoveTo(from), PathElement::LineTo(to)],
+        }),
+    ));
+}
+
+fn rectangle(color: Color, x: Scalar, y: Scalar, w: Scalar, h: Scalar) -> Command<'static> {
+    Command::Draw(Renderable::Rectangle(Rectangle {
+        color,
+        rect: Rect { x, y, w, h },
+    }))
+}
+
+fn label(rulers: &RulersOverlay, value: Scalar, position: Vec2) -> Command<'static> {
+    let value = (value * 1000.0).round() / 1000.0;
+    Command::Draw(Renderable::Text(
+        Text::new_owned(rulers.font.clone(), format!("{}", value))
+            .color(rulers.color)
+            .align(TextAlign::Left)
+            .baseline(TextBaseLine::Top)
+            .position(position)
+            .size(rulers.size * 0.45),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{identity, matrix};
+
+    fn scaled(scale: Scalar) -> Mat2d {
+        matrix(scale, 0.0, 0.0, scale, 0.0, 0.0)
+    }
+
+    fn camera(tags: &[&str]) -> Camera {
+        let mut camera = Camera::default();
+        camera.camera.tags = tags.iter().map(|tag| tag.to_string().into()).collect();
+        camera
+    }
+
+    fn selected() -> (HashMap<String, Vec<Command<'static>>>, CommandIds) {
+        let mut commands = HashMap::new();
+        commands.insert(
+            "main".to_owned(),
+            vec![
+                rectangle(Color::rgba(255, 0, 0, 255), 10.0, 10.0, 20.0, 20.0),
+                rectangle(Color::rgba(0, 255, 0, 255), 50.0, 50.0, 10.0, 10.0),
+            ],
+        );
+        let mut ids = CommandIds::new();
+        ids.insert(
+            "main".to_owned(),
+            vec![Some("a".to_owned()), Some("b".to_owned())],
+        );
+        (commands, ids)
+    }
+
+    #[test]
+    fn test_grid_spacing() {
+        let overlays = Overlays::default();
+        assert_eq!(overlays.grid_spacing(&scaled(1.0)), 10.0);
+        assert_eq!(overlays.grid_spacing(&scaled(0.1)), 100.0);
+        assert_eq!(overlays.grid_spacing(&scaled(0.01)), 1000.0);
+        assert_eq!(overlays.grid_spacing(&scaled(10.0)), 1.0);
+        assert!((overlays.grid_spacing(&scaled(2.0)) - 10.0).abs() < 1.0e-4);
+        assert!((overlays.grid_spacing(&scaled(20.0)) - 1.0).abs() < 1.0e-4);
+    }
+
+    #[test]
+    fn test_grid_spacing_degenerate() {
+        for min_pixels in &[0.0, -5.0, Scalar::INFINITY, Scalar::NAN] {
+            let mut overlays = Overlays::default();
+            overlays.grid.min_pixels = *min_pixels;
+            let spacing = overlays.grid_spacing(&scaled(1.0));
+            assert!(spacing.is_finite() && spacing > 0.0, "{}", spacing);
+        }
+        let overlays = Overlays::default();
+        assert!(overlays.grid_spacing(&scaled(0.0)) > 0.0);
+        overlays.grid_spacing(&scaled(Scalar::NAN));
+        overlays.grid_spacing(&scaled(Scalar::INFINITY));
+    }
+
+    #[test]
+    fn test_camera_tags() {
+        let mut overlays = Overlays::default();
+        overlays.selection.enabled = false;
+        overlays.grid.enabled = true;
+        overlays.grid.tags = vec!["editor".to_owned()];
+        let view_size = Vec2::new(100.0, 100.0);
+        let count = |camera: &Camera| {
+            overlays
+                .commands(
+                    camera,
+                    &identity(),
+                    view_size,
+                    &HashMap::new(),
+                    &CommandIds::new(),
+                    &HashMap::new(),
+                )
+                .len()
+        };
+        assert_eq!(count(&camera(&["editor", "game"])), 2);
+        assert_eq!(count(&camera(&[])), 2);
+        assert_eq!(count(&camera(&["game"])), 0);
+        overlays.grid.tags.clear();
+        assert_eq!(count(&camera(&["game"])), 2);
+        assert!(is_visible(&[], &camera(&["game"])));
+        assert!(!is_visible(&["editor".to_owned()], &camera(&["game"])));
+    }
+
+    #[test]
+    fn test_selection_gizmos() {
+        let (commands, ids) = selected();
+        let mut overlays = Overlays::default();
+        overlays.selection.ids = vec!["a".to_owned()];
+        let gizmos = |overlays: &Overlays, camera: &Camera, view: &Mat2d| {
+            overlays.commands(
+                camera,
+                view,
+                Vec2::new(100.0, 100.0),
+                &commands,
+                &ids,
+                &HashMap::new(),
+            )
+        };
+        let result = gizmos(&overlays, &camera(&[]), &scaled(2.0));
+        // outline and eight handles.
+        assert_eq!(result.len(), 9);
+        match &result[0] {
+            Command::Stroke(_, Renderable::Rectangle(rectangle)) => {
+                let rect = rectangle.rect;
+                assert_eq!((rect.x, rect.y, rect.w, rect.h), (20.0, 20.0, 40.0, 40.0));
+            }
+            _ => panic!("expected selection rectangle"),
+        }
+        match &result[1] {
+            Command::Draw(Renderable::Rectangle(rectangle)) => {
+                let rect = rectangle.rect;
+                assert_eq!((rect.x, rect.y, rect.w, rect.h), (17.0, 17.0, 6.0, 6.0));
+            }
+            _ => panic!("expected selection rectangle"),
+        }
+        assert!(gizmos(&overlays, &camera(&["other"]), &identity()).is_empty());
+        assert_eq!(gizmos(&overlays, &camera(&["main"]), &identity()).len(), 9);
+        overlays.selection.handle_size = 0.0;
+        assert_eq!(gizmos(&overlays, &camera(&[]), &identity()).len(), 1);
+        overlays.selection.ids = vec!["a".to_owned(), "b".to_owned()];
+        assert_eq!(gizmos(&overlays, &camera(&[]), &identity()).len(), 2);
+        overlays.selection.ids.clear();
+        assert!(gizmos(&overlays, &camera(&[]), &identity()).is_empty());
+    }
+}
@@ -0,0 +1,274 @@
+use crate::{
+    geometry::{
+        contours_contain, flatten_path, image_rect, inverse, polylines_distance, renderable_bounds,
+        transform_point, Bounds, TransformStack,
+    },
+    oxygengine::{
+        composite_renderer::{Command, Renderable},
+        math::{Mat2d, Vec2},
+    },
+    Camera, Scalar,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Optional user ids attached to commands, per tag and command index.
+pub type CommandIds = HashMap<String, Vec<Option<String>>>;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PickMode {
+    /// Hit when point lies inside screen space bounding box of renderable.
+    Bounds,
+    /// Hit when point lies inside actual shape of renderable.
+    Precise,
+}
+
+impl Default for PickMode {
+    fn default() -> Self {
+        Self::Precise
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PickResult {
+    pub tag: String,
+    /// Index of top level command in tag commands list.
+    pub index: usize,
+    #[serde(default)]
+    pub id: Option<String>,
+}
+
+/// Finds topmost command drawn at screen point, walking cameras in render order.
+pub fn pick<'a, I>(
+    commands: &HashMap<String, Vec<Command>>,
+    ids: &CommandIds,
+    cameras: I,
+    view_size: Vec2,
+    point: Vec2,
+    mode: PickMode,
+    image_sizes: &HashMap<String, Vec2>,
+) -> Option<PickResult>
+where
+    I: IntoIterator<Item = &'a Camera>,
+{
+    let mut cameras = cameras.into_iter().collect::<Vec<_>>();
+    cameras.sort_by(|a, b| a.order.total_cmp(&b.order));
+    let mut result = None;
+    for camera in cameras {
+        if !camera.covers(view_size, point) {
+            continue;
+        }
+        let view = camera.view_matrix(view_size);
+        for tag in camera.visible_tags(commands) {
+            if let Some(index) = pick_commands(&commands[&tag], &view, point, mode, image_sizes) {
+                let id = ids
+                    .get(&tag)
+                    .and_then(|ids| ids.get(index))
+                    .cloned()
+                    .flatten();
+                result = Some(PickResult { tag, index, id });
+            }
+        }
+    }
+    result
+}
+
+/// Index of topmost command in list that is hit by screen point.
+pub fn pick_commands(
+    commands: &[Command],
+    view: &Mat2d,
+    point: Vec2,
+    mode: PickMode,
+    image_sizes: &HashMap<String, Vec2>,
+) -> Option<usize> {
+    let mut walker = Walker {
+        transforms: TransformStack::new(*view),
+        point,
+        mode,
+        image_sizes,
+    };
+    let mut result = None;
+    for (index, command) in commands.iter().enumerate() {
+        if walker.command(command) {
+            result = Some(index);
+        }
+    }
+    result
+}
+
+struct Walker<'a> {
+    transforms: TransformStack,
+    point: Vec2,
+    mode: PickMode,
+    image_sizes: &'a HashMap<String, Vec2>,
+}
+
+impl Walker<'_> {
+    fn command(&mut self, command: &Command) -> bool {
+        if self.transforms.apply(command) {
+            return false;
+        }
+        match command {
+            Command::Draw(renderable) => self.renderable(renderable, None),
+            Command::Stroke(width, renderable) => self.renderable(renderable, Some(*width)),
+            _ => false,
+        }
+    }
+
+    fn renderable(&mut self, renderable: &Renderable, stroke: Option<Scalar>) -> bool {
+        if let Renderable::Commands(commands) = renderable {
+            let mut hit = false;
+            for command in commands {
+                hit |= self.command(command);
+            }
+            return hit;
+        }
+        match self.mode {
+            PickMode::Bounds => renderable_bounds(renderable, stroke, self.image_sizes)
+                .map(|bounds| {
+                    bounds
+                        .transformed(&self.transforms.current)
+                        .contains(self.point)
+                })
+                .unwrap_or(false),
+            PickMode::Precise => {
+                let local = match inverse(&self.transforms.current) {
+                    Some(inverse) => transform_point(&inverse, self.point),
+                    None => return false,
+                };
+                self.shape(renderable, stroke, local)
+            }
+        }
+    }
+
+    fn shape(&self, renderable: &Renderable, stroke: Option<Scalar>, point: Vec2) -> bool {
+        let contours = match renderable {
+            Renderable::Rectangle(rectangle) => {
+                let corners = Bounds::from_rect(&rectangle.rect).corners();
+                let mut contour = corners.to_vec();
+                contour.push(corners[0]);
+                vec![contour]
+            }
+            Renderable::Path(path) => flatten_path(&path.elements),
+            Renderable::Image(image) => {
+                return image_rect(image, self.image_sizes)
+                    .map(|rect| Bounds::from_rect(&rect).contains(point))
+                    .unwrap_or(false);
+            }
+            Renderable::Text(_) => {
+                return renderable_bounds(renderable, stroke, self.image_sizes)
+                    .map(|bounds| bounds.contains(point))
+                    .unwrap_or(false);
+            }
+            _ => return false,
+        };
+        match stroke {
+            Some(width) => polylines_distance(&contours, point) <= width * 0.5,
+            None => contours_contain(&contours, point),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oxygengine::{
+        composite_renderer::Rectangle,
+        math::{Color, Rect},
+    };
+
+    fn rectangle() -> Command<'static> {
+        Command::Draw(Renderable::Rectangle(Rectangle {
+            color: Color::rgba(255, 255, 255, 255),
+            rect: Rect {
+                x: -100.0,
+                y: -100.0,
+                w: 300.0,
+                h: 300.0,
+            },
+        }))
+    }
+
+    #[test]
+    fn test_overlapping_tags() {
+        let commands = vec![
+            ("b".to_owned(), vec![rectangle()]),
+            ("a".to_owned(), vec![rectangle(), rectangle()]),
+            ("c".to_owned(), vec![]),
+        ]
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+        let ids = vec![("b".to_owned(), vec![Some("top".to_owned())])]
+            .into_iter()
+            .collect::<CommandIds>();
+        let view_size = Vec2::new(10.0, 10.0);
+        let point = Vec2::new(5.0, 5.0);
+        let sizes = HashMap::new();
+
+        let camera = Camera::default();
+        assert_eq!(camera.visible_tags(&commands), vec!["a", "b", "c"]);
+        for mode in [PickMode::Bounds, PickMode::Precise].iter() {
+            let result = pick(
+                &commands,
+                &ids,
+                vec![&camera],
+                view_size,
+                point,
+                *mode,
+                &sizes,
+            );
+            assert_eq!(
+                result,
+                Some(PickResult {
+                    tag: "b".to_owned(),
+                    index: 0,
+                    id: Some("top".to_owned()),
+                })
+            );
+        }
+
+        let mut camera = Camera::default();
+        camera.camera.tags = vec!["b".into(), "missing".into(), "a".into()];
+        assert_eq!(camera.visible_tags(&commands), vec!["b", "a"]);
+        let result = pick(
+            &commands,
+            &ids,
+            vec![&camera],
+            view_size,
+            point,
+            PickMode::Precise,
+            &sizes,
+        );
+        assert_eq!(
+            result,
+            Some(PickResult {
+                tag: "a".to_owned(),
+                index: 1,
+                id: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_nan_camera_order() {
+        let commands = vec![("a".to_owned(), vec![rectangle()])]
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+        let first = Camera {
+            order: Scalar::NAN,
+            ..Default::default()
+        };
+        let second = Camera::default();
+        let result = pick(
+            &commands,
+            &CommandIds::new(),
+            vec![&first, &second],
+            Vec2::new(10.0, 10.0),
+            Vec2::new(5.0, 5.0),
+            PickMode::Bounds,
+            &HashMap::new(),
+        );
+        assert_eq!(result.map(|result| result.index), Some(0));
+    }
+}
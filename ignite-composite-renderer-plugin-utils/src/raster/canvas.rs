@@ -0,0 +1,341 @@
+use crate::{oxygengine::math::Vec2, Scalar};
+
+/// Vertical samples per pixel used for anti-aliasing.
+const SUBSAMPLES: usize = 4;
+
+/// RGBA8 pixel buffer with straight (not premultiplied) alpha.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RasterImage {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl RasterImage {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width * height * 4],
+        }
+    }
+
+    pub fn from_rgba(width: usize, height: usize, pixels: Vec<u8>) -> Result<Self, String> {
+        if pixels.len() != width * height * 4 {
+            return Err(format!(
+                "Expected {} bytes of RGBA data for {}x{} image, got: {}",
+                width * height * 4,
+                width,
+                height,
+                pixels.len()
+            ));
+        }
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Decodes 8-bit RGBA or RGB PNG image.
+    pub fn from_png(bytes: &[u8]) -> Result<Self, String> {
+        let mut decoder = png::Decoder::new(bytes);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder
+            .read_info()
+            .map_err(|error| format!("{:?}", error))?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader
+            .next_frame(&mut buffer)
+            .map_err(|error| format!("{:?}", error))?;
+        let (width, height) = (info.width as usize, info.height as usize);
+        let pixels = match info.color_type {
+            png::ColorType::Rgba => buffer[..(width * height * 4)].to_vec(),
+            png::ColorType::Rgb => buffer[..(width * height * 3)]
+                .chunks(3)
+                .flat_map(|pixel| vec![pixel[0], pixel[1], pixel[2], 255])
+                .collect(),
+            png::ColorType::GrayscaleAlpha => buffer[..(width * height * 2)]
+                .chunks(2)
+                .flat_map(|pixel| vec![pixel[0], pixel[0], pixel[0], pixel[1]])
+                .collect(),
+            png::ColorType::Grayscale => buffer[..(width * height)]
+                .iter()
+                .flat_map(|value| vec![*value, *value, *value, 255])
+                .collect(),
+            color_type => return Err(format!("Unsupported PNG color type: {:?}", color_type)),
+        };
+        Self::from_rgba(width, height, pixels)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn into_pixels(self) -> Vec<u8> {
+        self.pixels
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Option<[u8; 4]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let index = (y * self.width + x) * 4;
+        let mut result = [0; 4];
+        result.copy_from_slice(&self.pixels[index..(index + 4)]);
+        Some(result)
+    }
+
+    pub fn fill(&mut self, color: [u8; 4]) {
+        for pixel in self.pixels.chunks_mut(4) {
+            pixel.copy_from_slice(&color);
+        }
+    }
+
+    /// Blends color over pixel, `coverage` scales source alpha.
+    pub fn blend(&mut self, x: usize, y: usize, color: [u8; 4], coverage: Scalar) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        let source_alpha = color[3] as Scalar / 255.0 * coverage.max(0.0).min(1.0);
+        if source_alpha <= 0.0 {
+            return;
+        }
+        let index = (y * self.width + x) * 4;
+        let target = &mut self.pixels[index..(index + 4)];
+        let target_alpha = target[3] as Scalar / 255.0;
+        let alpha = source_alpha + target_alpha * (1.0 - source_alpha);
+        if alpha <= 0.0 {
+            return;
+        }
+        for channel in 0..3 {
+            let value = (color[channel] as Scalar * source_alpha
+                + target[channel] as Scalar * target_alpha * (1.0 - source_alpha))
+                / alpha;
+            target[channel] = value.round().max(0.0).min(255.0) as u8;
+        }
+        target[3] = (alpha * 255.0).round() as u8;
+    }
+
+    /// Samples pixel at normalized position, bilinear when `smooth` is set.
+    pub fn sample(&self, u: Scalar, v: Scalar, smooth: bool) -> [u8; 4] {
+        if self.width == 0 || self.height == 0 {
+            return [0; 4];
+        }
+        let x = u * self.width as Scalar;
+        let y = v * self.height as Scalar;
+        if !smooth {
+            let x = (x.floor().max(0.0) as usize).min(self.width - 1);
+            let y = (y.floor().max(0.0) as usize).min(self.height - 1);
+            return self.pixel(x, y).unwrap_or_default();
+        }
+        let x = (x - 0.5).max(0.0);
+        let y = (y - 0.5).max(0.0);
+        let x0 = (x.floor() as usize).min(self.width - 1);
+        let y0 = (y.floor() as usize).min(self.height - 1);
+        let x1 = (x0 + 1).min(self.width - 1);
+        let y1 = (y0 + 1).min(self.height - 1);
+        let fx = x - x0 as Scalar;
+        let fy = y - y0 as Scalar;
+        let p00 = self.pixel(x0, y0).unwrap_or_default();
+        let p10 = self.pixel(x1, y0).unwrap_or_default();
+        let p01 = self.pixel(x0, y1).unwrap_or_default();
+        let p11 = self.pixel(x1, y1).unwrap_or_default();
+        let mut result = [0; 4];
+        for channel in 0..4 {
+            let top = p00[channel] as Scalar * (1.0 - fx) + p10[channel] as Scalar * fx;
+            let bottom = p01[channel] as Scalar * (1.0 - fx) + p11[channel] as Scalar * fx;
+            result[channel] = (top * (1.0 - fy) + bottom * fy).round() as u8;
+        }
+        result
+    }
+
+    pub fn to_png(&self) -> Result<Vec<u8>, String> {
+        let mut result = vec![];
+        {
+            let mut encoder = png::Encoder::new(&mut result, self.width as u32, self.height as u32);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder
+                .write_header()
+                .map_err(|error| format!("{:?}", error))?;
+            writer
+                .write_image_data(&self.pixels)
+                .map_err(|error| format!("{:?}", error))?;
+        }
+        Ok(result)
+    }
+}
+
+/// Per pixel coverage of area inside polygons, in range 0-1.
+#[derive(Debug, Clone)]
+pub struct Coverage {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    values: Vec<Scalar>,
+}
+
+impl Coverage {
+    /// Rasterizes closed polygons with non-zero winding rule, clipped to target size.
+    pub fn polygons(contours: &[Vec<Vec2>], width: usize, height: usize) -> Option<Self> {
+        let points = contours.iter().flatten();
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (
+            Scalar::INFINITY,
+            Scalar::INFINITY,
+            Scalar::NEG_INFINITY,
+            Scalar::NEG_INFINITY,
+        );
+        for point in points {
+            min_x = min_x.min(point.x);
+            min_y = min_y.min(point.y);
+            max_x = max_x.max(point.x);
+            max_y = max_y.max(point.y);
+        }
+        if !min_x.is_finite() || !max_x.is_finite() || !min_y.is_finite() || !max_y.is_finite() {
+            return None;
+        }
+        let x0 = min_x.floor().max(0.0) as usize;
+        let y0 = min_y.floor().max(0.0) as usize;
+        let x1 = (max_x.ceil().max(0.0) as usize).min(width);
+        let y1 = (max_y.ceil().max(0.0) as usize).min(height);
+        if x0 >= x1 || y0 >= y1 {
+            return None;
+        }
+        let mut result = Self {
+            x: x0,
+            y: y0,
+            width: x1 - x0,
+            height: y1 - y0,
+            values: vec![0.0; (x1 - x0) * (y1 - y0)],
+        };
+        let edges = contours
+            .iter()
+            .filter(|contour| contour.len() > 2)
+            .flat_map(|contour| {
+                contour
+                    .iter()
+                    .zip(contour.iter().skip(1).chain(contour.first()))
+                    .filter(|(from, to)| (from.y - to.y).abs() > std::f32::EPSILON)
+                    .map(|(from, to)| (*from, *to))
+            })
+            .collect::<Vec<_>>();
+        let weight = 1.0 / SUBSAMPLES as Scalar;
+        let mut crossings = vec![];
+        for row in 0..result.height {
+            for sample in 0..SUBSAMPLES {
+                let y = (y0 + row) as Scalar + (sample as Scalar + 0.5) * weight;
+                crossings.clear();
+                for (from, to) in &edges {
+                    let (top, bottom) = if from.y < to.y {
+                        (from, to)
+                    } else {
+                        (to, from)
+                    };
+                    if y >= top.y && y < bottom.y {
+                        let x = from.x + (y - from.y) * (to.x - from.x) / (to.y - from.y);
+                        if x.is_finite() {
+                            crossings.push((x, if to.y > from.y { 1 } else { -1 }));
+                        }
+                    }
+                }
+                crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+                let mut winding = 0;
+                let mut start = 0.0;
+                for (x, direction) in &crossings {
+                    let was_inside = winding != 0;
+                    winding += direction;
+                    if !was_inside && winding != 0 {
+                        start = *x;
+                    } else if was_inside && winding == 0 {
+                        result.add_span(row, start - x0 as Scalar, *x - x0 as Scalar, weight);
+                    }
+                }
+            }
+        }
+        Some(result)
+    }
+
+    fn add_span(&mut self, row: usize, from: Scalar, to: Scalar, weight: Scalar) {
+        let from = from.max(0.0);
+        let to = to.min(self.width as Scalar);
+        if from >= to {
+            return;
+        }
+        let offset = row * self.width;
+        let first = from.floor() as usize;
+        let last = (to.ceil() as usize).min(self.width);
+        for column in first..last {
+            let overlap = to.min(column as Scalar + 1.0) - from.max(column as Scalar);
+            if overlap > 0.0 {
+                self.values[offset + column] += overlap * weight;
+            }
+        }
+    }
+
+    /// Iterates over (x, y, coverage) of pixels that are at least partially covered.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize, Scalar)> + '_ {
+        self.values
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| **value > 0.0)
+            .map(move |(index, value)| {
+                (
+                    self.x + index % self.width,
+                    self.y + index / self.width,
+                    value.min(1.0),
+                )
+            })
+    }
+}
+
+/// Clip mask covering whole target, stores per pixel visibility in range 0-1.
+#[derive(Debug, Clone)]
+pub struct ClipMask {
+    width: usize,
+    values: Vec<Scalar>,
+}
+
+impl ClipMask {
+    pub fn new(coverage: &Coverage, width: usize, height: usize) -> Self {
+        let mut values = vec![0.0; width * height];
+        for (x, y, value) in coverage.iter() {
+            values[y * width + x] = value;
+        }
+        Self { width, values }
+    }
+
+    /// Mask that hides everything.
+    pub fn empty(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            values: vec![0.0; width * height],
+        }
+    }
+
+    /// Intersection of two masks.
+    pub fn intersect(&self, other: &Self) -> Self {
+        Self {
+            width: self.width,
+            values: self
+                .values
+                .iter()
+                .zip(other.values.iter())
+                .map(|(a, b)| a.min(*b))
+                .collect(),
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Scalar {
+        self.values.get(y * self.width + x).copied().unwrap_or(0.0)
+    }
+}
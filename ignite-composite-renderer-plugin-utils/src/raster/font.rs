@@ -0,0 +1,71 @@
+use crate::{oxygengine::math::Vec2, Scalar};
+use embedded_graphics::{
+    mono_font::{ascii::FONT_10X20, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    prelude::*,
+    text::{Baseline, Text},
+};
+use std::convert::Infallible;
+
+/// Height in pixels of bundled font glyphs, text size is relative to it.
+pub const FONT_HEIGHT: Scalar = 20.0;
+/// Distance in glyph pixels from top of glyph cell to alphabetic baseline.
+pub const FONT_BASELINE: Scalar = 15.0;
+
+struct Glyphs(Vec<(i32, i32)>);
+
+impl OriginDimensions for Glyphs {
+    fn size(&self) -> Size {
+        Size::new(u32::MAX >> 1, FONT_10X20.character_size.height)
+    }
+}
+
+impl DrawTarget for Glyphs {
+    type Color = BinaryColor;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.0.extend(
+            pixels
+                .into_iter()
+                .filter(|Pixel(_, color)| color.is_on())
+                .map(|Pixel(point, _)| (point.x, point.y)),
+        );
+        Ok(())
+    }
+}
+
+/// Width of single line of text rendered with bundled font at given size.
+pub fn text_width(text: &str, size: Scalar) -> Scalar {
+    let scale = size / FONT_HEIGHT;
+    FONT_10X20.character_size.width as Scalar * text.chars().count() as Scalar * scale
+}
+
+/// Square per lit glyph pixel, positioned relative to text anchor.
+/// `align` is fraction of text width to shift left (0 - left, 0.5 - center, 1 - right)
+/// and `baseline` is glyph pixel row that gets placed at anchor.
+pub fn text_quads(text: &str, size: Scalar, align: Scalar, baseline: Scalar) -> Vec<Vec<Vec2>> {
+    let mut glyphs = Glyphs(vec![]);
+    let style = MonoTextStyle::new(&FONT_10X20, BinaryColor::On);
+    let _ = Text::with_baseline(text, Point::zero(), style, Baseline::Top).draw(&mut glyphs);
+    let scale = size / FONT_HEIGHT;
+    let offset_x = -text_width(text, size) * align;
+    let offset_y = -baseline * scale;
+    glyphs
+        .0
+        .into_iter()
+        .map(|(x, y)| {
+            let left = offset_x + x as Scalar * scale;
+            let top = offset_y + y as Scalar * scale;
+            vec![
+                Vec2::new(left, top),
+                Vec2::new(left + scale, top),
+                Vec2::new(left + scale, top + scale),
+                Vec2::new(left, top + scale),
+            ]
+        })
+        .collect()
+}
@@ -0,0 +1,596 @@
+pub mod canvas;
+pub mod font;
+
+use crate::{
+    geometry::{flatten_path, identity, inverse, matrix, multiply, transform_point, Bounds},
+    oxygengine::{
+        composite_renderer::{
+            Command, CompositeRenderer, Image, Rectangle, RenderState, Renderable, Text, TextAlign,
+            TextBaseLine,
+        },
+        math::{Color, Mat2d, Rect, Vec2},
+    },
+    Camera, RenderCommands, Scalar,
+};
+pub use canvas::RasterImage;
+use canvas::{ClipMask, Coverage};
+use oxygengine_core::error::Result;
+use std::{collections::HashMap, f32::consts::PI};
+
+/// Segments used to approximate round stroke joints.
+const JOINT_SEGMENTS: usize = 8;
+
+#[derive(Debug, Clone)]
+struct DrawState {
+    transform: Mat2d,
+    alpha: Scalar,
+    clip: Option<ClipMask>,
+}
+
+impl Default for DrawState {
+    fn default() -> Self {
+        Self {
+            transform: identity(),
+            alpha: 1.0,
+            clip: None,
+        }
+    }
+}
+
+/// CPU implementation of `CompositeRenderer` that draws into RGBA buffer.
+/// Text is rendered with bundled monospace font regardless of requested font face.
+pub struct RasterCompositeRenderer {
+    state: RenderState,
+    target: RasterImage,
+    images: HashMap<String, RasterImage>,
+    surfaces: HashMap<String, RasterImage>,
+    draw_state: DrawState,
+    stack: Vec<DrawState>,
+}
+
+impl RasterCompositeRenderer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self::with_state(width, height, Default::default())
+    }
+
+    pub fn with_state(width: usize, height: usize, state: RenderState) -> Self {
+        Self {
+            state,
+            target: RasterImage::new(width, height),
+            images: Default::default(),
+            surfaces: Default::default(),
+            draw_state: Default::default(),
+            stack: vec![],
+        }
+    }
+
+    /// Resizes target, clearing its content.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.target = RasterImage::new(width, height);
+    }
+
+    pub fn target(&self) -> &RasterImage {
+        &self.target
+    }
+
+    pub fn to_png(&self) -> std::result::Result<Vec<u8>, String> {
+        self.target.to_png()
+    }
+
+    pub fn add_image(&mut self, name: &str, image: RasterImage) {
+        self.images.insert(name.to_owned(), image);
+    }
+
+    pub fn remove_image(&mut self, name: &str) -> Option<RasterImage> {
+        self.images.remove(name)
+    }
+
+    pub fn has_image(&self, name: &str) -> bool {
+        self.images.contains_key(name)
+    }
+
+    /// Clears target and renders tagged commands through cameras sorted by order,
+    /// the same way editor canvas does.
+    pub fn render_commands<'a, I>(&mut self, commands: &RenderCommands, cameras: I)
+    where
+        I: IntoIterator<Item = &'a Camera>,
+    {
+        self.target.fill([0, 0, 0, 0]);
+        if let Some(color) = self.state.clear_color {
+            drop(self.execute(std::iter::once(Command::Draw(
+                Renderable::FullscreenRectangle(color),
+            ))));
+        }
+        let view_size = self.view_size();
+        let mut cameras = cameras.into_iter().collect::<Vec<_>>();
+        cameras.sort_by(|a, b| a.order.total_cmp(&b.order));
+        for camera in cameras {
+            let matrix = camera.view_matrix(view_size);
+            let transform = {
+                let [a, b, c, d, e, f] = matrix.0;
+                Command::Transform(a, b, c, d, e, f)
+            };
+            let mut prelude = vec![Command::Store];
+            if camera.clip {
+                prelude.push(camera.viewport_mask(view_size));
+            }
+            if let Some(color) = camera.clear_color {
+                prelude.push(Command::Draw(Renderable::Rectangle(Rectangle {
+                    color,
+                    rect: camera.viewport_rect(view_size),
+                })));
+            }
+            prelude.push(transform);
+            let commands: Vec<Command> = camera
+                .visible_tags(&commands.0)
+                .iter()
+                .flat_map(|tag| commands.0[tag].iter().cloned())
+                .collect();
+            let commands = prelude
+                .into_iter()
+                .chain(commands.into_iter())
+                .chain(std::iter::once(Command::Restore));
+            drop(self.execute(commands));
+        }
+    }
+
+    fn execute_command(&mut self, command: Command) -> (usize, usize) {
+        match command {
+            Command::Draw(renderable) => self.draw(renderable, None),
+            Command::Stroke(width, renderable) => self.draw(renderable, Some(width)),
+            Command::Transform(a, b, c, d, e, f) => {
+                self.draw_state.transform =
+                    multiply(&self.draw_state.transform, &matrix(a, b, c, d, e, f));
+                (1, 0)
+            }
+            Command::Alpha(alpha) => {
+                self.draw_state.alpha = alpha.max(0.0).min(1.0);
+                (1, 0)
+            }
+            Command::Store => {
+                self.stack.push(self.draw_state.clone());
+                (1, 0)
+            }
+            Command::Restore => {
+                if let Some(state) = self.stack.pop() {
+                    self.draw_state = state;
+                }
+                (1, 0)
+            }
+            _ => (0, 0),
+        }
+    }
+
+    fn draw(&mut self, renderable: Renderable, stroke: Option<Scalar>) -> (usize, usize) {
+        match renderable {
+            Renderable::None => (0, 0),
+            Renderable::FullscreenRectangle(color) => {
+                let (width, height) = (self.target.width(), self.target.height());
+                let color = self.color(color);
+                for y in 0..height {
+                    for x in 0..width {
+                        let visibility = self.visibility(x, y);
+                        self.target.blend(x, y, color, visibility);
+                    }
+                }
+                (1, 1)
+            }
+            Renderable::Rectangle(Rectangle { color, rect }) => {
+                let mut contour = Bounds::from_rect(&rect).corners().to_vec();
+                if stroke.is_some() {
+                    contour.push(contour[0]);
+                }
+                self.shape(vec![contour], color, stroke);
+                (1, 1)
+            }
+            Renderable::Path(path) => {
+                self.shape(flatten_path(&path.elements), path.color, stroke);
+                (1, 1)
+            }
+            Renderable::Mask(mask) => {
+                let contours = self.transformed(flatten_path(&mask.elements));
+                let (width, height) = (self.target.width(), self.target.height());
+                let clip = match Coverage::polygons(&contours, width, height) {
+                    Some(coverage) => ClipMask::new(&coverage, width, height),
+                    None => ClipMask::empty(width, height),
+                };
+                self.draw_state.clip = Some(match &self.draw_state.clip {
+                    Some(current) => current.intersect(&clip),
+                    None => clip,
+                });
+                (1, 1)
+            }
+            Renderable::Image(image) => {
+                self.image(image);
+                (1, 1)
+            }
+            Renderable::Text(text) => {
+                self.text(text);
+                (1, 1)
+            }
+            Renderable::Commands(commands) => {
+                commands
+                    .into_iter()
+                    .fold((0, 0), |(ops, renderables), command| {
+                        let (o, r) = self.execute_command(command);
+                        (ops + o, renderables + r)
+                    })
+            }
+            _ => (0, 0),
+        }
+    }
+
+    fn color(&self, color: Color) -> [u8; 4] {
+        [
+            color.r,
+            color.g,
+            color.b,
+            (color.a as Scalar * self.draw_state.alpha).round() as u8,
+        ]
+    }
+
+    fn visibility(&self, x: usize, y: usize) -> Scalar {
+        match &self.draw_state.clip {
+            Some(clip) => clip.get(x, y),
+            None => 1.0,
+        }
+    }
+
+    fn transformed(&self, contours: Vec<Vec<Vec2>>) -> Vec<Vec<Vec2>> {
+        contours
+            .into_iter()
+            .map(|contour| {
+                contour
+                    .into_iter()
+                    .map(|point| transform_point(&self.draw_state.transform, point))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Fills contours or strokes them as polylines of given width, in local space.
+    fn shape(&mut self, contours: Vec<Vec<Vec2>>, color: Color, stroke: Option<Scalar>) {
+        let contours = match stroke {
+            Some(width) => contours
+                .iter()
+                .flat_map(|polyline| stroke_polygons(polyline, width * 0.5))
+                .collect(),
+            None => contours,
+        };
+        let contours = self.transformed(contours);
+        self.fill(&contours, self.color(color));
+    }
+
+    fn fill(&mut self, contours: &[Vec<Vec2>], color: [u8; 4]) {
+        let (width, height) = (self.target.width(), self.target.height());
+        if let Some(coverage) = Coverage::polygons(contours, width, height) {
+            for (x, y, value) in coverage.iter() {
+                let visibility = self.visibility(x, y);
+                self.target.blend(x, y, color, value * visibility);
+            }
+        }
+    }
+
+    fn image(&mut self, image: Image) {
+        let source_image = match self
+            .images
+            .get(image.image.as_ref())
+            .or_else(|| self.surfaces.get(image.image.as_ref()))
+        {
+            Some(source_image) => source_image,
+            None => return,
+        };
+        let (image_width, image_height) = (
+            source_image.width() as Scalar,
+            source_image.height() as Scalar,
+        );
+        let source = image.source.unwrap_or(Rect {
+            x: 0.0,
+            y: 0.0,
+            w: image_width,
+            h: image_height,
+        });
+        let mut destination = image.destination.unwrap_or(Rect {
+            x: 0.0,
+            y: 0.0,
+            w: source.w,
+            h: source.h,
+        });
+        destination.x -= destination.w * image.alignment.x;
+        destination.y -= destination.h * image.alignment.y;
+        if destination.w.abs() < std::f32::EPSILON || destination.h.abs() < std::f32::EPSILON {
+            return;
+        }
+        let inverse_transform = match inverse(&self.draw_state.transform) {
+            Some(inverse_transform) => inverse_transform,
+            None => return,
+        };
+        let contour = Bounds::from_rect(&destination)
+            .corners()
+            .iter()
+            .map(|point| transform_point(&self.draw_state.transform, *point))
+            .collect::<Vec<_>>();
+        let (width, height) = (self.target.width(), self.target.height());
+        let coverage = match Coverage::polygons(&[contour], width, height) {
+            Some(coverage) => coverage,
+            None => return,
+        };
+        let smooth = self.state.image_smoothing;
+        let alpha = self.draw_state.alpha;
+        for (x, y, value) in coverage.iter() {
+            let local = transform_point(
+                &inverse_transform,
+                Vec2::new(x as Scalar + 0.5, y as Scalar + 0.5),
+            );
+            let u = source.x + (local.x - destination.x) / destination.w * source.w;
+            let v = source.y + (local.y - destination.y) / destination.h * source.h;
+            let u = u.max(source.x).min(source.x + source.w);
+            let v = v.max(source.y).min(source.y + source.h);
+            let color = source_image.sample(u / image_width, v / image_height, smooth);
+            let visibility = match &self.draw_state.clip {
+                Some(clip) => clip.get(x, y),
+                None => 1.0,
+            };
+            self.target.blend(x, y, color, value * visibility * alpha);
+        }
+    }
+
+    fn text(&mut self, text: Text) {
+        let align = match text.align {
+            TextAlign::Left => 0.0,
+            TextAlign::Center => 0.5,
+            TextAlign::Right => 1.0,
+        };
+        let baseline = match text.baseline {
+            TextBaseLine::Top | TextBaseLine::Hanging => 0.0,
+            TextBaseLine::Middle => font::FONT_HEIGHT * 0.5,
+            TextBaseLine::Bottom => font::FONT_HEIGHT,
+            _ => font::FONT_BASELINE,
+        };
+        let mut y = 0.0;
+        let mut contours = vec![];
+        for line in text.text.lines() {
+            contours.extend(
+                font::text_quads(line, text.size, align, baseline)
+                    .into_iter()
+                    .map(|quad| {
+                        quad.into_iter()
+                            .map(|point| {
+                                Vec2::new(text.position.x + point.x, text.position.y + point.y + y)
+                            })
+                            .collect::<Vec<_>>()
+                    }),
+            );
+            y += text.size;
+        }
+        let contours = self.transformed(contours);
+        self.fill(&contours, self.color(text.color));
+    }
+}
+
+impl CompositeRenderer for RasterCompositeRenderer {
+    fn execute<'a, I>(&mut self, commands: I) -> Result<(usize, usize)>
+    where
+        I: IntoIterator<Item = Command<'a>>,
+    {
+        Ok(commands
+            .into_iter()
+            .fold((0, 0), |(ops, renderables), command| {
+                let (o, r) = self.execute_command(command);
+                (ops + o, renderables + r)
+            }))
+    }
+
+    fn images_count(&self) -> usize {
+        self.images.len()
+    }
+
+    fn surfaces_count(&self) -> usize {
+        self.surfaces.len()
+    }
+
+    fn state(&self) -> &RenderState {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut RenderState {
+        &mut self.state
+    }
+
+    fn viewport(&self) -> Rect {
+        Rect {
+            x: 0.0,
+            y: 0.0,
+            w: self.target.width() as Scalar,
+            h: self.target.height() as Scalar,
+        }
+    }
+
+    fn create_surface(&mut self, name: &str, width: usize, height: usize) -> bool {
+        if self.surfaces.contains_key(name) {
+            return false;
+        }
+        self.surfaces
+            .insert(name.to_owned(), RasterImage::new(width, height));
+        true
+    }
+
+    fn destroy_surface(&mut self, name: &str) -> bool {
+        self.surfaces.remove(name).is_some()
+    }
+
+    fn has_surface(&mut self, name: &str) -> bool {
+        self.surfaces.contains_key(name)
+    }
+
+    fn get_surface_size(&self, name: &str) -> Option<(usize, usize)> {
+        self.surfaces
+            .get(name)
+            .map(|surface| (surface.width(), surface.height()))
+    }
+
+    fn update_surface<'a, I>(&mut self, name: &str, commands: I) -> Result<(usize, usize)>
+    where
+        I: IntoIterator<Item = Command<'a>>,
+    {
+        let surface = match self.surfaces.remove(name) {
+            Some(surface) => surface,
+            None => return Ok((0, 0)),
+        };
+        let target = std::mem::replace(&mut self.target, surface);
+        let draw_state = std::mem::take(&mut self.draw_state);
+        let stack = std::mem::take(&mut self.stack);
+        let result = self.execute(commands);
+        let surface = std::mem::replace(&mut self.target, target);
+        self.draw_state = draw_state;
+        self.stack = stack;
+        self.surfaces.insert(name.to_owned(), surface);
+        result
+    }
+}
+
+/// Polygons covering polyline stroked with round joints, all wound clockwise
+/// so non-zero fill merges overlaps.
+fn stroke_polygons(polyline: &[Vec2], half_width: Scalar) -> Vec<Vec<Vec2>> {
+    let mut result = vec![];
+    if half_width <= 0.0 {
+        return result;
+    }
+    for (from, to) in polyline.iter().zip(polyline.iter().skip(1)) {
+        let (dx, dy) = (to.x - from.x, to.y - from.y);
+        let length = (dx * dx + dy * dy).sqrt();
+        if length < std::f32::EPSILON {
+            continue;
+        }
+        let (nx, ny) = (-dy / length * half_width, dx / length * half_width);
+        result.push(oriented(vec![
+            Vec2::new(from.x + nx, from.y + ny),
+            Vec2::new(to.x + nx, to.y + ny),
+            Vec2::new(to.x - nx, to.y - ny),
+            Vec2::new(from.x - nx, from.y - ny),
+        ]));
+    }
+    let joints = if polyline.len() > 2 {
+        &polyline[1..(polyline.len() - 1)]
+    } else {
+        &[]
+    };
+    for point in joints {
+        result.push(
+            (0..JOINT_SEGMENTS)
+                .map(|index| {
+                    let angle = 2.0 * PI * index as Scalar / JOINT_SEGMENTS as Scalar;
+                    Vec2::new(
+                        point.x + angle.cos() * half_width,
+                        point.y + angle.sin() * half_width,
+                    )
+                })
+                .collect(),
+        );
+    }
+    result
+}
+
+fn oriented(mut polygon: Vec<Vec2>) -> Vec<Vec2> {
+    let area = polygon
+        .iter()
+        .zip(polygon.iter().cycle().skip(1))
+        .map(|(a, b)| a.x * b.y - b.x * a.y)
+        .sum::<Scalar>();
+    if area < 0.0 {
+        polygon.reverse();
+    }
+    polygon
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rectangle(color: Color, x: Scalar, y: Scalar, w: Scalar, h: Scalar) -> Command<'static> {
+        Command::Draw(Renderable::Rectangle(Rectangle {
+            color,
+            rect: Rect { x, y, w, h },
+        }))
+    }
+
+    #[test]
+    fn test_rectangle() {
+        let mut renderer = RasterCompositeRenderer::new(8, 8);
+        renderer
+            .execute(vec![rectangle(
+                Color::rgba(255, 0, 0, 255),
+                2.0,
+                2.0,
+                4.0,
+                4.0,
+            )])
+            .unwrap();
+        assert_eq!(renderer.target().pixel(3, 3), Some([255, 0, 0, 255]));
+        assert_eq!(renderer.target().pixel(0, 0), Some([0, 0, 0, 0]));
+        assert_eq!(renderer.target().pixel(6, 6), Some([0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn test_transform_store_restore() {
+        let mut renderer = RasterCompositeRenderer::new(8, 8);
+        renderer
+            .execute(vec![
+                Command::Store,
+                Command::Transform(1.0, 0.0, 0.0, 1.0, 4.0, 4.0),
+                rectangle(Color::rgba(0, 255, 0, 255), 0.0, 0.0, 2.0, 2.0),
+                Command::Restore,
+                rectangle(Color::rgba(0, 0, 255, 255), 0.0, 0.0, 2.0, 2.0),
+            ])
+            .unwrap();
+        assert_eq!(renderer.target().pixel(5, 5), Some([0, 255, 0, 255]));
+        assert_eq!(renderer.target().pixel(1, 1), Some([0, 0, 255, 255]));
+        assert_eq!(renderer.target().pixel(3, 3), Some([0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn test_png() {
+        let mut renderer = RasterCompositeRenderer::new(4, 4);
+        renderer
+            .execute(vec![rectangle(
+                Color::rgba(10, 20, 30, 255),
+                0.0,
+                0.0,
+                4.0,
+                4.0,
+            )])
+            .unwrap();
+        let data = renderer.to_png().unwrap();
+        let image = RasterImage::from_png(&data).unwrap();
+        assert_eq!(&image, renderer.target());
+    }
+
+    #[test]
+    fn test_camera_viewports() {
+        let mut renderer = RasterCompositeRenderer::new(8, 8);
+        let left = Camera {
+            viewport: Some(Rect {
+                x: 0.0,
+                y: 0.0,
+                w: 0.5,
+                h: 1.0,
+            }),
+            clear_color: Some(Color::rgba(255, 0, 0, 255)),
+            ..Default::default()
+        };
+        let right = Camera {
+            order: 1.0,
+            viewport: Some(Rect {
+                x: 0.5,
+                y: 0.0,
+                w: 0.5,
+                h: 1.0,
+            }),
+            clear_color: Some(Color::rgba(0, 0, 255, 255)),
+            ..Default::default()
+        };
+        renderer.render_commands(&RenderCommands::default(), vec![&left, &right]);
+        assert_eq!(renderer.target().pixel(1, 4), Some([255, 0, 0, 255]));
+        assert_eq!(renderer.target().pixel(6, 4), Some([0, 0, 255, 255]));
+    }
+}
@@ -0,0 +1,465 @@
+use crate::{
+    geometry::{matrix, multiply},
+    oxygengine::{
+        component::{CompositeCamera, CompositeTransform},
+        composite_renderer::{Command, Renderable},
+        math::{Mat2d, Vec2},
+    },
+    picking::CommandIds,
+    Camera, RenderCommands, Scalar,
+};
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::collections::HashMap;
+
+/// Tag of renderables without `Tag` component, visible only through cameras without tags.
+pub const DEFAULT_TAG: &str = "default";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PrefabEntity {
+    Data(PrefabEntityData),
+    /// Template references cannot be resolved without game assets and are skipped.
+    Template(String),
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct PrefabEntityData {
+    #[serde(default)]
+    pub components: HashMap<String, Value>,
+}
+
+/// Oxygengine prefab scene, only composite renderer related components are read.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ScenePrefab {
+    #[serde(default)]
+    pub template_name: Option<String>,
+    #[serde(default)]
+    pub entities: Vec<PrefabEntity>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TransformProxy {
+    #[serde(default = "TransformProxy::default_translation")]
+    pub translation: Vec2,
+    #[serde(default)]
+    pub rotation: Scalar,
+    #[serde(default = "TransformProxy::default_scale")]
+    pub scale: Vec2,
+}
+
+impl TransformProxy {
+    fn default_translation() -> Vec2 {
+        Vec2::new(0.0, 0.0)
+    }
+
+    fn default_scale() -> Vec2 {
+        Vec2::new(1.0, 1.0)
+    }
+
+    fn matrix(&self) -> Mat2d {
+        let (sin, cos) = self.rotation.sin_cos();
+        matrix(
+            cos * self.scale.x,
+            sin * self.scale.x,
+            -sin * self.scale.y,
+            cos * self.scale.y,
+            self.translation.x,
+            self.translation.y,
+        )
+    }
+
+    /// Decomposes world matrix, skew from non uniformly scaled rotated parents is lost.
+    fn from_matrix(matrix: &Mat2d) -> Self {
+        let [a, b, c, d, e, f] = matrix.0;
+        let sign = if a * d - b * c < 0.0 { -1.0 } else { 1.0 };
+        Self {
+            translation: Vec2::new(e, f),
+            rotation: b.atan2(a),
+            scale: Vec2::new(a.hypot(b), sign * c.hypot(d)),
+        }
+    }
+
+    fn into_transform(self) -> CompositeTransform {
+        let mut transform = CompositeTransform::default();
+        transform.set_translation(self.translation);
+        transform.set_rotation(self.rotation);
+        transform.set_scale(self.scale);
+        transform
+    }
+}
+
+impl Default for TransformProxy {
+    fn default() -> Self {
+        Self {
+            translation: Self::default_translation(),
+            rotation: 0.0,
+            scale: Self::default_scale(),
+        }
+    }
+}
+
+/// Render data produced from scene prefab. Command ids are entity names.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SceneRender {
+    pub commands: RenderCommands,
+    pub ids: CommandIds,
+    /// Cameras by entity name, unnamed cameras get `#<entity index>` names.
+    pub cameras: HashMap<String, Camera>,
+}
+
+impl SceneRender {
+    pub fn from_yaml(source: &str) -> Result<Self, String> {
+        let prefab = serde_yaml::from_str::<ScenePrefab>(source)
+            .map_err(|error| format!("Could not parse scene prefab: {}", error))?;
+        Self::from_prefab(&prefab)
+    }
+
+    /// Entities with `Parent` component naming another entity get their transform
+    /// composed with transforms of all their ancestors. Parents that cannot be found,
+    /// e.g. ones coming from templates, are treated as scene root.
+    pub fn from_prefab(prefab: &ScenePrefab) -> Result<Self, String> {
+        let mut result = Self::default();
+        let mut entities = vec![];
+        for (index, entity) in prefab.entities.iter().enumerate() {
+            if let PrefabEntity::Data(data) = entity {
+                entities.push(SceneEntity::new(index, &data.components)?);
+            }
+        }
+        let names = entities
+            .iter()
+            .enumerate()
+            .filter_map(|(position, entity)| Some((entity.name.as_deref()?, position)))
+            .collect::<HashMap<_, _>>();
+        // (depth, entity index, tag, name, commands) sorted before grouping by tag.
+        let mut items = vec![];
+        for position in 0..entities.len() {
+            let world = world_matrix(&entities, &names, position)?;
+            let entity = &entities[position];
+            let (index, components) = (entity.index, entity.components);
+            let name = entity.name.clone();
+            if let Some(camera) =
+                component::<CompositeCamera>(components, "CompositeCamera", index)?
+            {
+                let key = name.clone().unwrap_or_else(|| format!("#{}", index));
+                result.cameras.insert(
+                    key,
+                    Camera {
+                        order: index as f32,
+                        camera,
+                        transform: TransformProxy::from_matrix(&world).into_transform(),
+                        ..Default::default()
+                    },
+                );
+            }
+            let visible =
+                component::<bool>(components, "CompositeVisibility", index)?.unwrap_or(true);
+            let renderable =
+                match component::<Renderable<'static>>(components, "CompositeRenderable", index)? {
+                    Some(Renderable::None) | None => continue,
+                    Some(renderable) if visible => renderable,
+                    Some(_) => continue,
+                };
+            let tag = component::<String>(components, "Tag", index)?
+                .unwrap_or_else(|| DEFAULT_TAG.to_owned());
+            let depth =
+                component::<Scalar>(components, "CompositeRenderDepth", index)?.unwrap_or(0.0);
+            let [a, b, c, d, e, f] = world.0;
+            let commands = vec![
+                Command::Store,
+                Command::Transform(a, b, c, d, e, f),
+                Command::Draw(renderable),
+                Command::Restore,
+            ];
+            items.push((depth, index, tag, name, commands));
+        }
+        items.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+        for (_, _, tag, name, commands) in items {
+            let ids = result.ids.entry(tag.clone()).or_default();
+            ids.extend(vec![None, None, name, None]);
+            result.commands.0.entry(tag).or_default().extend(commands);
+        }
+        Ok(result)
+    }
+}
+
+/// Prefab entity data needed to resolve hierarchy.
+struct SceneEntity<'a> {
+    index: usize,
+    name: Option<String>,
+    parent: Option<String>,
+    transform: Mat2d,
+    components: &'a HashMap<String, Value>,
+}
+
+impl<'a> SceneEntity<'a> {
+    fn new(index: usize, components: &'a HashMap<String, Value>) -> Result<Self, String> {
+        Ok(Self {
+            index,
+            name: component::<String>(components, "Name", index)?,
+            parent: component::<String>(components, "Parent", index)?,
+            transform: component::<TransformProxy>(components, "CompositeTransform", index)?
+                .unwrap_or_default()
+                .matrix(),
+            components,
+        })
+    }
+}
+
+/// Transform of entity composed with transforms of its ancestors.
+fn world_matrix(
+    entities: &[SceneEntity],
+    names: &HashMap<&str, usize>,
+    position: usize,
+) -> Result<Mat2d, String> {
+    let mut result = entities[position].transform;
+    let mut current = position;
+    for _ in 0..entities.len() {
+        let parent = match entities[current]
+            .parent
+            .as_deref()
+            .and_then(|parent| names.get(parent))
+        {
+            Some(parent) => *parent,
+            None => return Ok(result),
+        };
+        result = multiply(&entities[parent].transform, &result);
+        current = parent;
+    }
+    Err(format!(
+        "Entity #{} has cyclic parent hierarchy",
+        entities[position].index
+    ))
+}
+
+fn component<T>(
+    components: &HashMap<String, Value>,
+    name: &str,
+    index: usize,
+) -> Result<Option<T>, String>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    match components.get(name) {
+        Some(value) => serde_yaml::from_value(value.clone())
+            .map(Some)
+            .map_err(|error| format!("Invalid {} of entity #{}: {}", name, index, error)),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCENE: &str = r#"
+template_name: scene
+entities:
+  - Data:
+      components:
+        Name: camera
+        CompositeCamera:
+          scaling: CenterAspect
+          tags:
+            - world
+        CompositeTransform:
+          scale:
+            x: 400
+            y: 400
+  - Data:
+      components:
+        Tag: world
+        Name: front
+        CompositeRenderable:
+          Rectangle:
+            color:
+              r: 255
+              g: 0
+              b: 0
+              a: 128
+            rect:
+              x: -10
+              y: -10
+              w: 20
+              h: 20
+        CompositeTransform: {}
+        CompositeRenderDepth: 1
+  - Data:
+      components:
+        Tag: world
+        Name: back
+        CompositeRenderable:
+          Image:
+            image: logo.png
+            alignment:
+              x: 0.5
+              y: 0.5
+        CompositeTransform:
+          translation:
+            x: 10
+            y: 20
+  - Data:
+      components:
+        CompositeRenderable: None
+        CompositeTransform: {}
+  - Template: player
+"#;
+
+    #[test]
+    fn test_scene_render() {
+        let scene = SceneRender::from_yaml(SCENE).unwrap();
+        assert_eq!(scene.cameras.len(), 1);
+        assert_eq!(
+            scene.cameras["camera"].camera.tags,
+            vec!["world".to_owned()]
+        );
+        assert_eq!(scene.commands.0.len(), 1);
+        let commands = &scene.commands.0["world"];
+        assert_eq!(commands.len(), 8);
+        match &commands[1] {
+            Command::Transform(_, _, _, _, e, f) => assert_eq!((*e, *f), (10.0, 20.0)),
+            command => panic!("Unexpected command: {:?}", command),
+        }
+        assert_eq!(
+            scene.ids["world"],
+            vec![
+                None,
+                None,
+                Some("back".to_owned()),
+                None,
+                None,
+                None,
+                Some("front".to_owned()),
+                None,
+            ]
+        );
+    }
+
+    const HIERARCHY: &str = r#"
+entities:
+  - Data:
+      components:
+        Name: root
+        CompositeTransform:
+          translation:
+            x: 100
+            y: 0
+          scale:
+            x: 2
+            y: 2
+  - Data:
+      components:
+        Name: arm
+        Parent: root
+        CompositeTransform:
+          translation:
+            x: 10
+            y: 0
+          rotation: 1.5707964
+  - Data:
+      components:
+        Name: hand
+        Parent: arm
+        Tag: world
+        CompositeRenderable:
+          Rectangle:
+            color:
+              r: 255
+              g: 255
+              b: 255
+              a: 255
+            rect:
+              x: 0
+              y: 0
+              w: 1
+              h: 1
+        CompositeTransform:
+          translation:
+            x: 5
+            y: 0
+  - Data:
+      components:
+        Name: eye
+        Parent: arm
+        CompositeCamera:
+          scaling: CenterAspect
+  - Data:
+      components:
+        Name: orphan
+        Parent: template-entity
+        Tag: world
+        CompositeRenderable:
+          Rectangle:
+            color:
+              r: 255
+              g: 255
+              b: 255
+              a: 255
+            rect:
+              x: 0
+              y: 0
+              w: 1
+              h: 1
+        CompositeTransform:
+          translation:
+            x: 7
+            y: 0
+"#;
+
+    fn assert_near(actual: &[Scalar], expected: &[Scalar]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, b) in actual.iter().zip(expected.iter()) {
+            assert!((a - b).abs() < 1.0e-3, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_hierarchy() {
+        let scene = SceneRender::from_yaml(HIERARCHY).unwrap();
+        let commands = &scene.commands.0["world"];
+        assert_eq!(commands.len(), 8);
+        // hand at 5 along arm rotated by 90 degrees, arm at 10 from root scaled by 2.
+        match &commands[1] {
+            Command::Transform(a, b, c, d, e, f) => assert_near(
+                &[*a, *b, *c, *d, *e, *f],
+                &[0.0, 2.0, -2.0, 0.0, 120.0, 10.0],
+            ),
+            _ => panic!("expected transform"),
+        }
+        // unknown parent, e.g. from template, leaves entity at scene root.
+        match &commands[5] {
+            Command::Transform(a, b, c, d, e, f) => {
+                assert_near(&[*a, *b, *c, *d, *e, *f], &[1.0, 0.0, 0.0, 1.0, 7.0, 0.0])
+            }
+            _ => panic!("expected transform"),
+        }
+        let camera = &scene.cameras["eye"].transform;
+        let translation = camera.get_translation();
+        let scale = camera.get_scale();
+        assert_near(
+            &[
+                translation.x,
+                translation.y,
+                camera.get_rotation(),
+                scale.x,
+                scale.y,
+            ],
+            &[120.0, 0.0, 1.5707964, 2.0, 2.0],
+        );
+    }
+
+    #[test]
+    fn test_cyclic_hierarchy() {
+        let scene = r#"
+entities:
+  - Data:
+      components:
+        Name: a
+        Parent: b
+  - Data:
+      components:
+        Name: b
+        Parent: a
+"#;
+        assert!(SceneRender::from_yaml(scene).is_err());
+    }
+}
@@ -0,0 +1,291 @@
+use crate::{
+    culling::CullingStats,
+    oxygengine::composite_renderer::{Command, Renderable},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct TagStats {
+    /// Number of commands stored for tag.
+    pub commands: usize,
+    /// Render operations and renderables executed this frame, zero when layer was cached.
+    pub render_ops: usize,
+    pub renderables: usize,
+    pub cached: bool,
+    pub culling: CullingStats,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ResourceStats {
+    pub images: usize,
+    pub fontfaces: usize,
+    pub surfaces: usize,
+}
+
+/// Rough memory usage in bytes.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct MemoryStats {
+    pub images: usize,
+    pub surfaces: usize,
+    pub commands: usize,
+}
+
+impl MemoryStats {
+    pub fn total(&self) -> usize {
+        self.images + self.surfaces + self.commands
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct RenderStats {
+    /// Duration of last render in milliseconds.
+    pub frame_time: f64,
+    /// Number of renders since renderer creation.
+    pub frames: usize,
+    /// Per camera and tag statistics of last render.
+    pub cameras: HashMap<String, HashMap<String, TagStats>>,
+    /// Draw calls of last render by renderable type.
+    pub draw_calls: BTreeMap<String, usize>,
+    pub resources: ResourceStats,
+    pub memory: MemoryStats,
+}
+
+impl RenderStats {
+    /// Lines of text describing statistics, used by stats overlay.
+    pub fn summary(&self) -> Vec<String> {
+        let (render_ops, drawn, culled, cached, layers) = self
+            .cameras
+            .values()
+            .flat_map(|tags| tags.values())
+            .fold((0, 0, 0, 0, 0), |(o, d, c, h, l), tag| {
+                (
+                    o + tag.render_ops,
+                    d + tag.culling.drawn,
+                    c + tag.culling.culled,
+                    h + if tag.cached { 1 } else { 0 },
+                    l + 1,
+                )
+            });
+        let draw_calls = self
+            .draw_calls
+            .iter()
+            .map(|(kind, count)| format!("{}: {}", kind, count))
+            .collect::<Vec<_>>()
+            .join(", ");
+        vec![
+            format!("frame: {:.2} ms (#{})", self.frame_time, self.frames),
+            format!("layers: {} ({} cached)", layers, cached),
+            format!(
+                "ops: {} | drawn: {} | culled: {}",
+                render_ops, drawn, culled
+            ),
+            format!("draws: {}", draw_calls),
+            format!(
+                "images: {} | fonts: {} | surfaces: {}",
+                self.resources.images, self.resources.fontfaces, self.resources.surfaces
+            ),
+            format!(
+                "memory: {:.1} MB",
+                self.memory.total() as f64 / 1024.0 / 1024.0
+            ),
+        ]
+    }
+}
+
+pub fn renderable_kind(renderable: &Renderable) -> &'static str {
+    match renderable {
+        Renderable::None => "none",
+        Renderable::FullscreenRectangle(_) => "fullscreen-rectangle",
+        Renderable::Rectangle(_) => "rectangle",
+        Renderable::Text(_) => "text",
+        Renderable::Path(_) => "path",
+        Renderable::Mask(_) => "mask",
+        Renderable::Image(_) => "image",
+        Renderable::Commands(_) => "commands",
+        _ => "other",
+    }
+}
+
+/// Counts draws and strokes by renderable type, descending into nested commands.
+pub fn count_draw_calls(commands: &[Command], counts: &mut BTreeMap<String, usize>) {
+    for command in commands {
+        let renderable = match command {
+            Command::Draw(renderable) | Command::Stroke(_, renderable) => renderable,
+            _ => continue,
+        };
+        if let Renderable::Commands(commands) = renderable {
+            count_draw_calls(commands, counts);
+        } else {
+            *counts
+                .entry(renderable_kind(renderable).to_owned())
+                .or_default() += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        culling::cull_commands,
+        geometry::identity,
+        oxygengine::{
+            composite_renderer::Rectangle,
+            math::{Color, Rect, Vec2},
+        },
+        Scalar,
+    };
+
+    fn rectangle(x: Scalar, y: Scalar) -> Renderable<'static> {
+        Renderable::Rectangle(Rectangle {
+            color: Color::rgba(255, 255, 255, 255),
+            rect: Rect {
+                x,
+                y,
+                w: 10.0,
+                h: 10.0,
+            },
+        })
+    }
+
+    /// Two visible and one culled draw at top level, one of each inside nested list.
+    fn commands() -> Vec<Command<'static>> {
+        vec![
+            Command::Store,
+            Command::Transform(1.0, 0.0, 0.0, 1.0, 10.0, 0.0),
+            Command::Draw(rectangle(0.0, 0.0)),
+            Command::Draw(rectangle(500.0, 500.0)),
+            Command::Draw(Renderable::Commands(vec![
+                Command::Draw(rectangle(20.0, 20.0)),
+                Command::Draw(rectangle(-300.0, 0.0)),
+            ])),
+            Command::Stroke(2.0, rectangle(30.0, 30.0)),
+            Command::Restore,
+            Command::Alpha(0.5),
+        ]
+    }
+
+    fn cull(commands: &[Command<'static>]) -> (Vec<Command<'static>>, CullingStats) {
+        cull_commands(
+            commands,
+            &identity(),
+            Vec2::new(100.0, 100.0),
+            &Default::default(),
+        )
+    }
+
+    #[test]
+    fn test_renderable_kind() {
+        assert_eq!(renderable_kind(&Renderable::None), "none");
+        assert_eq!(renderable_kind(&rectangle(0.0, 0.0)), "rectangle");
+        assert_eq!(renderable_kind(&Renderable::Commands(vec![])), "commands");
+    }
+
+    #[test]
+    fn test_count_draw_calls() {
+        let mut counts = BTreeMap::new();
+        count_draw_calls(&commands(), &mut counts);
+        assert_eq!(counts.len(), 1);
+        assert_eq!(counts["rectangle"], 5);
+        let (culled, _) = cull(&commands());
+        let mut counts = BTreeMap::new();
+        count_draw_calls(&culled, &mut counts);
+        assert_eq!(counts["rectangle"], 3);
+        count_draw_calls(
+            &[Command::Draw(Renderable::FullscreenRectangle(Color::rgba(
+                0, 0, 0, 255,
+            )))],
+            &mut counts,
+        );
+        assert_eq!(counts["fullscreen-rectangle"], 1);
+        assert_eq!(counts["rectangle"], 3);
+    }
+
+    #[test]
+    fn test_culled_counts() {
+        let (culled, stats) = cull(&commands());
+        assert_eq!(culled.len(), 7);
+        assert_eq!(stats.drawn, 3);
+        assert_eq!(stats.culled, 2);
+    }
+
+    #[cfg(feature = "raster")]
+    #[test]
+    fn test_render_counts() {
+        use crate::{
+            oxygengine::composite_renderer::CompositeRenderer, raster::RasterCompositeRenderer,
+        };
+
+        let mut renderer = RasterCompositeRenderer::new(100, 100);
+        // store, transform, restore and alpha are operations without renderables.
+        assert_eq!(renderer.execute(commands()).unwrap(), (9, 5));
+        let (culled, _) = cull(&commands());
+        assert_eq!(renderer.execute(culled).unwrap(), (7, 3));
+    }
+
+    #[test]
+    fn test_summary() {
+        let (_, culling) = cull(&commands());
+        let mut tags = HashMap::new();
+        tags.insert(
+            "world".to_owned(),
+            TagStats {
+                commands: commands().len(),
+                render_ops: 7,
+                renderables: 3,
+                cached: false,
+                culling,
+            },
+        );
+        tags.insert(
+            "ui".to_owned(),
+            TagStats {
+                commands: 2,
+                cached: true,
+                culling: CullingStats {
+                    drawn: 2,
+                    culled: 0,
+                },
+                ..Default::default()
+            },
+        );
+        let mut stats = RenderStats {
+            frame_time: 16.0,
+            frames: 3,
+            ..Default::default()
+        };
+        stats.cameras.insert("main".to_owned(), tags);
+        let mut minimap = HashMap::new();
+        minimap.insert(
+            "world".to_owned(),
+            TagStats {
+                commands: 8,
+                render_ops: 4,
+                renderables: 1,
+                cached: false,
+                culling: CullingStats {
+                    drawn: 1,
+                    culled: 4,
+                },
+            },
+        );
+        stats.cameras.insert("minimap".to_owned(), minimap);
+        stats.draw_calls.insert("rectangle".to_owned(), 4);
+        stats.draw_calls.insert("image".to_owned(), 3);
+        stats.resources.images = 2;
+        stats.memory.images = 1024 * 1024;
+        stats.memory.commands = 1024 * 1024;
+        assert_eq!(
+            stats.summary(),
+            vec![
+                "frame: 16.00 ms (#3)".to_owned(),
+                "layers: 3 (1 cached)".to_owned(),
+                "ops: 11 | drawn: 6 | culled: 6".to_owned(),
+                "draws: image: 3, rectangle: 4".to_owned(),
+                "images: 2 | fonts: 0 | surfaces: 0".to_owned(),
+                "memory: 2.0 MB".to_owned(),
+            ]
+        );
+    }
+}
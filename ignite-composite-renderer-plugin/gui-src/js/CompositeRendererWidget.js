@@ -1,6 +1,7 @@
 import React from 'react';
 import { registerWidget } from 'ignite-gui';
 import * as mod from '../pkg/index_bg';
+import * as resources from './ResourceManager';
 
 mod.main_js();
 
@@ -24,15 +25,23 @@ class CompositeRendererWidget extends React.Component {
       const { storage } = props;
       storage.id = storage.id || null;
       storage.cameraId = storage.cameraId || null;
+      storage.cameraIds = storage.cameraIds || [];
       this.state = {};
     } else {
       this.state = {
         id: null,
         cameraId: null,
+        cameraIds: [],
       };
     }
     this._onFrame = this.onFrame.bind(this);
+    this._onMouseDown = this.onMouseDown.bind(this);
+    this._onMouseMove = this.onMouseMove.bind(this);
+    this._onMouseUp = this.onMouseUp.bind(this);
+    this._onWheel = this.onWheel.bind(this);
     this._handle = null;
+    this._dragPosition = null;
+    this._dragCameraId = null;
     this._canvasRef = React.createRef();
   }
 
@@ -42,7 +51,16 @@ class CompositeRendererWidget extends React.Component {
 
     this._writeStorage(storage => {
       storage.id = mod.renderer_create(_canvasRef.current, defaultRenderState);
-      storage.cameraId = mod.camera_create(storage.id || '');
+      const cameras = Array.isArray(props.cameras) && props.cameras.length > 0
+        ? props.cameras
+        : [null];
+      storage.cameraIds = cameras.map(camera => mod.camera_create(storage.id || '', camera));
+      storage.cameraId = storage.cameraIds[0];
+      if (!!props.cameraControls && typeof props.cameraControls === 'object') {
+        for (const cameraId of storage.cameraIds) {
+          mod.camera_controls_set(storage.id, cameraId, props.cameraControls);
+        }
+      }
     });
 
     this._handle = window.requestAnimationFrame(this._onFrame);
@@ -51,8 +69,10 @@ class CompositeRendererWidget extends React.Component {
   componentWillUnmount() {
     window.cancelAnimationFrame(this._handle);
     this._handle = null;
+    this.onMouseUp();
 
     this._readStorage(storage => {
+      resources.releaseRenderer(storage.id || '');
       mod.renderer_destroy(storage.id || '');
     });
   }
@@ -65,16 +85,214 @@ class CompositeRendererWidget extends React.Component {
     this._handle = window.requestAnimationFrame(this._onFrame);
   }
 
-  renderCommands(data) {
+  onMouseDown(event) {
+    if (!this.props.cameraControls || (event.button !== 0 && event.button !== 1)) {
+      return;
+    }
+    event.preventDefault();
+    this._dragPosition = this._localPosition(event);
+    this._dragCameraId = this.cameraAt(...this._dragPosition);
+    window.addEventListener('mousemove', this._onMouseMove);
+    window.addEventListener('mouseup', this._onMouseUp);
+  }
+
+  onMouseMove(event) {
+    if (!this._dragPosition) {
+      return;
+    }
+    const position = this._localPosition(event);
+    const [fromX, fromY] = this._dragPosition;
+    const [toX, toY] = position;
+    this._dragPosition = position;
+    this.panCamera(fromX, fromY, toX, toY, this._dragCameraId);
+  }
+
+  onMouseUp() {
+    this._dragPosition = null;
+    this._dragCameraId = null;
+    window.removeEventListener('mousemove', this._onMouseMove);
+    window.removeEventListener('mouseup', this._onMouseUp);
+  }
+
+  onWheel(event) {
+    if (!this.props.cameraControls) {
+      return;
+    }
+    const [x, y] = this._localPosition(event);
+    this.zoomCamera(x, y, -Math.sign(event.deltaY), this.cameraAt(x, y));
+  }
+
+  renderCommands(data, ids) {
     this._readStorage(storage => {
       data = Array.isArray(data) ? { default: data } : data;
-      mod.render_commands(storage.id || '', data);
+      ids = Array.isArray(ids) ? { default: ids } : ids;
+      mod.render_commands(storage.id || '', data, ids);
+    });
+  }
+
+  patchCommands(patches) {
+    this._readStorage(storage => {
+      mod.render_commands_patch(storage.id || '', patches);
+    });
+  }
+
+  getStats() {
+    return this._readStorage(storage => {
+      return mod.renderer_stats(storage.id || '');
+    });
+  }
+
+  getCullingStats() {
+    return this._readStorage(storage => {
+      return mod.culling_stats(storage.id || '');
+    });
+  }
+
+  pick(x, y, precise = true) {
+    return this._readStorage(storage => {
+      return mod.pick(storage.id || '', x, y, !!precise);
+    });
+  }
+
+  setOverlays(data) {
+    this._readStorage(storage => {
+      mod.overlays_set(storage.id || '', data);
+    });
+  }
+
+  getOverlays() {
+    return this._readStorage(storage => {
+      return mod.overlays_state(storage.id || '');
+    });
+  }
+
+  enableOverlay(name, enabled) {
+    this._readStorage(storage => {
+      mod.overlay_enable(storage.id || '', name, !!enabled);
+    });
+  }
+
+  setSelection(ids) {
+    this._readStorage(storage => {
+      mod.selection_set(storage.id || '', ids || []);
+    });
+  }
+
+  setCamera(data, cameraId = null) {
+    this._readStorage(storage => {
+      mod.camera_set(storage.id, cameraId || storage.cameraId, data);
+    });
+  }
+
+  createCamera(data) {
+    return this._writeStorage(storage => {
+      const cameraId = mod.camera_create(storage.id || '', data);
+      storage.cameraIds = [...(storage.cameraIds || []), cameraId];
+      storage.cameraId = storage.cameraId || cameraId;
+      if (!!this.props.cameraControls && typeof this.props.cameraControls === 'object') {
+        mod.camera_controls_set(storage.id, cameraId, this.props.cameraControls);
+      }
+      return cameraId;
+    });
+  }
+
+  destroyCamera(cameraId) {
+    this._writeStorage(storage => {
+      mod.camera_destroy(storage.id || '', cameraId);
+      storage.cameraIds = (storage.cameraIds || []).filter(id => id !== cameraId);
+      if (storage.cameraId === cameraId) {
+        storage.cameraId = storage.cameraIds[0] || null;
+      }
+    });
+  }
+
+  getCameras() {
+    return this._readStorage(storage => {
+      return mod.cameras_list(storage.id || '');
+    });
+  }
+
+  cameraAt(x, y) {
+    return this._readStorage(storage => {
+      return mod.camera_at(storage.id || '', x, y) || storage.cameraId;
+    });
+  }
+
+  setCameraViewport(viewport, cameraId = null) {
+    this._readStorage(storage => {
+      mod.camera_viewport_set(storage.id, cameraId || storage.cameraId, viewport);
     });
   }
 
-  setCamera(data) {
+  setCameraClearColor(color, cameraId = null) {
     this._readStorage(storage => {
-      mod.camera_set(storage.id, storage.cameraId, data);
+      mod.camera_clear_color_set(storage.id, cameraId || storage.cameraId, color);
+    });
+  }
+
+  setCameraClip(clip, cameraId = null) {
+    this._readStorage(storage => {
+      mod.camera_clip_set(storage.id, cameraId || storage.cameraId, !!clip);
+    });
+  }
+
+  setCameraOrder(order, cameraId = null) {
+    this._readStorage(storage => {
+      mod.camera_order_set(storage.id, cameraId || storage.cameraId, order);
+    });
+  }
+
+  setCameraControls(data, cameraId = null) {
+    this._readStorage(storage => {
+      mod.camera_controls_set(storage.id, cameraId || storage.cameraId, data);
+    });
+  }
+
+  panCamera(fromX, fromY, toX, toY, cameraId = null) {
+    this._readStorage(storage => {
+      mod.camera_pan(storage.id, cameraId || storage.cameraId, fromX, fromY, toX, toY);
+    });
+  }
+
+  zoomCamera(x, y, steps, cameraId = null) {
+    this._readStorage(storage => {
+      mod.camera_zoom(storage.id, cameraId || storage.cameraId, x, y, steps);
+    });
+  }
+
+  frameAll(cameraId = null) {
+    return this._readStorage(storage => {
+      return mod.camera_frame(storage.id, cameraId || storage.cameraId, false);
+    });
+  }
+
+  frameSelection(cameraId = null) {
+    return this._readStorage(storage => {
+      return mod.camera_frame(storage.id, cameraId || storage.cameraId, true);
+    });
+  }
+
+  saveCameraBookmark(name, cameraId = null) {
+    this._readStorage(storage => {
+      mod.camera_bookmark_save(storage.id, cameraId || storage.cameraId, name);
+    });
+  }
+
+  restoreCameraBookmark(name, cameraId = null) {
+    this._readStorage(storage => {
+      mod.camera_bookmark_restore(storage.id, cameraId || storage.cameraId, name);
+    });
+  }
+
+  getCameraBookmarks(cameraId = null) {
+    return this._readStorage(storage => {
+      return mod.camera_bookmarks(storage.id, cameraId || storage.cameraId);
+    });
+  }
+
+  setCameraBookmarks(bookmarks, cameraId = null) {
+    this._readStorage(storage => {
+      mod.camera_bookmarks_set(storage.id, cameraId || storage.cameraId, bookmarks);
     });
   }
 
@@ -123,18 +341,56 @@ class CompositeRendererWidget extends React.Component {
   loadFontFaceResource(id, url, descriptors) {
     const font = new FontFace(id, url, descriptors);
     return font.load().then(() => {
-      window.fonts.add(font);
+      document.fonts.add(font);
       return this.addFontFaceResource(id, font);
     });
   }
 
   removeFontFaceResource(id) {
     return this._readStorage(storage => {
-      window.fonts.delete(font);
-      return mod.remove_fontface_resource(storage.id, id);
+      const font = mod.remove_fontface_resource(storage.id, id);
+      document.fonts.delete(font);
+      return font;
+    });
+  }
+
+  loadImage(path) {
+    return this._readStorage(storage => {
+      return resources.acquireImage(storage.id, path);
     });
   }
 
+  loadFont(path, family, descriptors) {
+    return this._readStorage(storage => {
+      return resources.acquireFont(storage.id, path, family, descriptors);
+    });
+  }
+
+  loadAtlas(path) {
+    return this._readStorage(storage => {
+      return resources.acquireAtlas(storage.id, path);
+    });
+  }
+
+  releaseResource(path) {
+    this._readStorage(storage => {
+      resources.release(storage.id, path);
+    });
+  }
+
+  getResourceStatus(path) {
+    return resources.status(path);
+  }
+
+  getResourcesStatus() {
+    return resources.statuses();
+  }
+
+  _localPosition(event) {
+    const rect = this._canvasRef.current.getBoundingClientRect();
+    return [event.clientX - rect.left, event.clientY - rect.top];
+  }
+
   _readStorage(cb) {
     if (!!this.props.storage) {
       return cb(this.props.storage());
@@ -157,6 +413,8 @@ class CompositeRendererWidget extends React.Component {
     return (
       <canvas
         ref={this._canvasRef}
+        onMouseDown={this._onMouseDown}
+        onWheel={this._onWheel}
         style={{ ...style.container, ...this.props.style }}
       />
     );
@@ -0,0 +1,170 @@
+import * as mod from '../pkg/index_bg';
+
+const PROJECT_URL = 'http://localhost:19092/';
+
+// Shared resources by kind and project path, loaded once and referenced by renderers.
+const entries = new Map();
+
+function projectUrl(path) {
+  return PROJECT_URL + path.replace(/\\/g, '/').replace(/^\/+/, '');
+}
+
+function key(kind, path) {
+  return `${kind}:${path}`;
+}
+
+function acquire(kind, rendererId, path, load, attach) {
+  const k = key(kind, path);
+  let entry = entries.get(k);
+  // failed load stays reported until resource is acquired again and retried.
+  if (!!entry && entry.status === 'error') {
+    entries.delete(k);
+    entry = null;
+  }
+  if (!entry) {
+    entry = {
+      kind,
+      path,
+      status: 'loading',
+      error: null,
+      value: null,
+      renderers: new Map(),
+      promise: null,
+    };
+    entry.promise = load(path)
+      .then(value => {
+        entry.status = 'ready';
+        entry.value = value;
+        return value;
+      })
+      .catch(error => {
+        entry.status = 'error';
+        entry.error = `${error}`;
+        throw error;
+      });
+    entries.set(k, entry);
+  }
+  const reference = entry.renderers.get(rendererId);
+  if (!!reference) {
+    reference.count += 1;
+    return entry.promise;
+  }
+  const created = { id: null, count: 1 };
+  entry.renderers.set(rendererId, created);
+  return entry.promise.then(value => {
+    // renderer could release resource before it finished loading.
+    if (entry.renderers.get(rendererId) !== created) {
+      return value;
+    }
+    created.id = attach(rendererId, value);
+    mod.renderer_refresh(rendererId, true);
+    return value;
+  });
+}
+
+function detach(entry, rendererId) {
+  const resourceId = entry.renderers.get(rendererId).id;
+  entry.renderers.delete(rendererId);
+  try {
+    if (entry.kind === 'image' && resourceId !== null) {
+      mod.remove_image_resource(rendererId, resourceId);
+    } else if (entry.kind === 'font' && resourceId !== null) {
+      mod.remove_fontface_resource(rendererId, resourceId);
+    } else if (entry.kind === 'atlas' && resourceId !== null) {
+      mod.atlas_remove(rendererId, entry.path);
+      release(rendererId, resourceId, 'image');
+    }
+  } catch (error) {
+    // renderer is already destroyed.
+  }
+  if (entry.renderers.size === 0) {
+    entries.delete(key(entry.kind, entry.path));
+    if (entry.kind === 'font' && !!entry.value) {
+      document.fonts.delete(entry.value);
+    }
+  }
+}
+
+function loadImage(path) {
+  const image = new Image();
+  image.crossOrigin = 'anonymous';
+  image.src = projectUrl(path);
+  return image.decode().then(() => image);
+}
+
+export function acquireImage(rendererId, path) {
+  return acquire('image', rendererId, path, loadImage, (rendererId, image) =>
+    mod.add_image_resource(rendererId, path, image)
+  );
+}
+
+export function acquireFont(rendererId, path, family, descriptors) {
+  const load = path => {
+    const font = new FontFace(family || path, `url(${projectUrl(path)})`, descriptors || {});
+    return font.load().then(() => {
+      document.fonts.add(font);
+      return font;
+    });
+  };
+  return acquire('font', rendererId, path, load, (rendererId, font) =>
+    mod.add_fontface_resource(rendererId, font.family, font)
+  );
+}
+
+export function acquireAtlas(rendererId, path) {
+  const load = path =>
+    fetch(projectUrl(path))
+      .then(response => {
+        if (!response.ok) {
+          throw new Error(`${response.status} ${response.statusText}`);
+        }
+        return response.json();
+      });
+  return acquire('atlas', rendererId, path, load, (rendererId, data) => {
+    const image = mod.atlas_set(rendererId, path, data);
+    acquireImage(rendererId, image).catch(() => {});
+    return image;
+  });
+}
+
+export function release(rendererId, path, kind = null) {
+  for (const entry of Array.from(entries.values())) {
+    const reference = entry.renderers.get(rendererId);
+    if (entry.path === path && (!kind || entry.kind === kind) && !!reference) {
+      reference.count -= 1;
+      if (reference.count <= 0) {
+        detach(entry, rendererId);
+      }
+    }
+  }
+}
+
+export function releaseRenderer(rendererId) {
+  for (const entry of Array.from(entries.values())) {
+    if (entry.renderers.has(rendererId)) {
+      detach(entry, rendererId);
+    }
+  }
+}
+
+function describe(entry) {
+  return {
+    kind: entry.kind,
+    path: entry.path,
+    status: entry.status,
+    error: entry.error,
+    references: Array.from(entry.renderers.values()).reduce(
+      (total, reference) => total + reference.count,
+      0
+    ),
+  };
+}
+
+export function status(path) {
+  const found = Array.from(entries.values()).filter(entry => entry.path === path);
+  return found.length > 0 ? describe(found[0]) : null;
+}
+
+export function statuses() {
+  return Array.from(entries.values()).map(describe);
+}
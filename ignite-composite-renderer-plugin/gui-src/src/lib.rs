@@ -4,13 +4,27 @@ extern crate ignite_composite_renderer_plugin_utils as utils;
 extern crate oxygengine_composite_renderer_backend_web as renderer_web;
 
 use renderer_web::WebCompositeRenderer;
-use std::{collections::HashMap, str::FromStr, sync::RwLock};
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    sync::RwLock,
+};
 use typid::ID;
 use utils::{
-    oxygengine::composite_renderer::{
-        Command, CompositeRenderer, CompositeRendererResources, Renderable,
+    atlas::{frame_sizes, resolve_frames, AtlasFrames, SpriteAtlas, FRAME_SEPARATOR},
+    controls::{CameraController, CameraControls, CameraPose},
+    culling::{cull_commands, CullingStats},
+    geometry::{commands_bounds, commands_item_bounds, identity, matrix, multiply, Bounds},
+    overlays::Overlays,
+    oxygengine::{
+        composite_renderer::{
+            Command, CompositeRenderer, CompositeRendererResources, Image, Rectangle, Renderable,
+        },
+        math::{Color, Rect, Vec2},
     },
-    Camera, RenderCommands, RenderStateProxy,
+    picking::{self, CommandIds, PickMode},
+    stats::{count_draw_calls, RenderStats, TagStats},
+    Camera, RenderCommands, RenderCommandsPatch, RenderStateProxy,
 };
 use wasm_bindgen::prelude::*;
 use web_sys::{FontFace, HtmlCanvasElement, HtmlImageElement};
@@ -19,10 +33,34 @@ lazy_static! {
     static ref RENDERERS: RwLock<HashMap<String, Renderer>> = RwLock::new(Default::default());
 }
 
+#[derive(Default)]
+struct Layer {
+    pub dirty: bool,
+    /// Culling result of last layer redraw.
+    pub culling: CullingStats,
+    /// Surface size in pixels.
+    pub size: (usize, usize),
+}
+
 struct Renderer {
     pub renderer: WebCompositeRenderer,
     pub cameras: HashMap<String, Camera>,
     pub commands: HashMap<String, Vec<Command<'static>>>,
+    pub ids: CommandIds,
+    /// Sizes of image resources by image name.
+    pub image_sizes: HashMap<String, Vec2>,
+    /// Image names by resource id.
+    pub image_names: HashMap<String, String>,
+    /// Sprite atlas frames by frame image name.
+    pub frames: AtlasFrames,
+    pub overlays: Overlays,
+    pub controllers: HashMap<String, CameraController>,
+    /// Timestamp of last refresh in milliseconds.
+    pub last_refresh: Option<f64>,
+    /// Offscreen layers with camera view applied, per camera and tag.
+    /// Tags missing here or marked dirty get redrawn on next render.
+    pub layers: HashMap<String, HashMap<String, Layer>>,
+    pub stats: RenderStats,
 }
 
 impl Renderer {
@@ -31,59 +69,314 @@ impl Renderer {
             renderer,
             cameras: Default::default(),
             commands: Default::default(),
+            ids: Default::default(),
+            image_sizes: Default::default(),
+            image_names: Default::default(),
+            frames: Default::default(),
+            overlays: Default::default(),
+            controllers: Default::default(),
+            last_refresh: None,
+            layers: Default::default(),
+            stats: Default::default(),
+        }
+    }
+
+    fn layer_surface(camera: &str, tag: &str) -> String {
+        format!("@layer/{}/{}", camera, tag)
+    }
+
+    pub fn mark_tag_dirty(&mut self, tag: &str) {
+        for layers in self.layers.values_mut() {
+            if let Some(layer) = layers.get_mut(tag) {
+                layer.dirty = true;
+            }
+        }
+    }
+
+    pub fn mark_camera_dirty(&mut self, camera: &str) {
+        if let Some(layers) = self.layers.get_mut(camera) {
+            for layer in layers.values_mut() {
+                layer.dirty = true;
+            }
+        }
+    }
+
+    pub fn mark_all_dirty(&mut self) {
+        for layers in self.layers.values_mut() {
+            for layer in layers.values_mut() {
+                layer.dirty = true;
+            }
         }
     }
+
+    /// Destroys layers that do not match any camera and tag anymore.
+    fn drop_stale_layers(&mut self) {
+        let cameras = &self.cameras;
+        let commands = &self.commands;
+        let renderer = &mut self.renderer;
+        self.layers.retain(|camera_id, layers| {
+            let camera = cameras.get(camera_id);
+            layers.retain(|tag, _| {
+                let keep = commands.contains_key(tag)
+                    && camera
+                        .map(|camera| {
+                            camera.camera.tags.is_empty()
+                                || camera.camera.tags.iter().any(|item| item == tag)
+                        })
+                        .unwrap_or(false);
+                if !keep {
+                    renderer.destroy_surface(&Self::layer_surface(camera_id, tag));
+                }
+                keep
+            });
+            camera.is_some()
+        });
+    }
+
+    /// Camera and its controller, controller gets created on first use.
+    pub fn controller(&mut self, id: &str) -> Option<(&mut Camera, &mut CameraController)> {
+        let camera = self.cameras.get_mut(id)?;
+        let controller = self.controllers.entry(id.to_owned()).or_default();
+        Some((camera, controller))
+    }
+
+    /// World space bounds of commands visible through camera, optionally limited
+    /// to selected ones.
+    pub fn world_bounds(&self, id: &str, selection: bool) -> Option<Bounds> {
+        let camera = self.cameras.get(id)?;
+        self.commands
+            .iter()
+            .filter(|(tag, _)| {
+                camera.camera.tags.is_empty() || camera.camera.tags.iter().any(|item| item == *tag)
+            })
+            .filter_map(|(tag, commands)| {
+                if !selection {
+                    return commands_bounds(commands, &identity(), &self.image_sizes);
+                }
+                let ids = self.ids.get(tag)?;
+                commands_item_bounds(commands, &identity(), &self.image_sizes)
+                    .into_iter()
+                    .zip(ids.iter())
+                    .filter_map(|(bounds, id)| match id {
+                        Some(id) if self.overlays.selection.ids.contains(id) => bounds,
+                        _ => None,
+                    })
+                    .fold(None, |result: Option<Bounds>, bounds| match result {
+                        Some(result) => Some(result.union(bounds)),
+                        None => Some(bounds),
+                    })
+            })
+            .fold(None, |result: Option<Bounds>, bounds| match result {
+                Some(result) => Some(result.union(bounds)),
+                None => Some(bounds),
+            })
+    }
 }
 
 impl Renderer {
     pub fn refresh(&mut self, forced: bool) {
         let old = self.renderer.view_size();
         self.renderer.update_state();
-        if forced || self.renderer.view_size() != old {
+        let now = now();
+        let delta_time = self
+            .last_refresh
+            .map(|last| ((now - last) * 0.001) as f32)
+            .unwrap_or(0.0);
+        self.last_refresh = Some(now);
+        let mut moved = HashSet::new();
+        for (id, controller) in &mut self.controllers {
+            if let Some(camera) = self.cameras.get_mut(id) {
+                if controller.update(camera, delta_time) {
+                    moved.insert(id.clone());
+                }
+            }
+        }
+        for id in &moved {
+            self.mark_camera_dirty(id);
+        }
+        let resized = self.renderer.view_size() != old;
+        if resized {
+            self.mark_all_dirty();
+        }
+        if forced || resized || !moved.is_empty() {
             self.render();
         }
     }
 
+    /// Redraws dirty layers and composites all of them with overlays on top.
     pub fn render(&mut self) {
+        let started = now();
+        let mut stats = RenderStats::default();
+        self.drop_stale_layers();
         if let Some(color) = self.renderer.state().clear_color {
             drop(self.renderer.execute(std::iter::once(Command::Draw(
                 Renderable::FullscreenRectangle(color),
             ))));
         }
         let view_size = self.renderer.view_size();
-        let mut cameras = self.cameras.values().collect::<Vec<_>>();
-        cameras.sort_by(|a, b| a.order.partial_cmp(&b.order).unwrap());
-        for camera in cameras {
-            let matrix = camera.camera.view_matrix(&camera.transform, view_size);
+        let screen = Rect {
+            x: 0.0,
+            y: 0.0,
+            w: view_size.x,
+            h: view_size.y,
+        };
+        let mut cameras = self.cameras.iter().collect::<Vec<_>>();
+        cameras.sort_by(|a, b| a.1.order.total_cmp(&b.1.order));
+        for (camera_id, camera) in cameras {
+            let viewport = camera.viewport_rect(view_size);
+            // layers of clipped cameras cover only their viewport.
+            let area = if camera.clip { viewport } else { screen };
+            let area_size = Vec2::new(area.w.max(1.0), area.h.max(1.0));
+            let (width, height) = (area_size.x as usize, area_size.y as usize);
+            let view = multiply(
+                &matrix(1.0, 0.0, 0.0, 1.0, -area.x, -area.y),
+                &camera.view_matrix(view_size),
+            );
             let transform = {
-                let [a, b, c, d, e, f] = matrix.0;
+                let [a, b, c, d, e, f] = view.0;
                 Command::Transform(a, b, c, d, e, f)
             };
-            if camera.camera.tags.is_empty() {
+            if let Some(color) = camera.clear_color {
+                let clear = Command::Draw(Renderable::Rectangle(Rectangle {
+                    color,
+                    rect: viewport,
+                }));
+                count_draw_calls(std::slice::from_ref(&clear), &mut stats.draw_calls);
+                drop(self.renderer.execute(std::iter::once(clear)));
+            }
+            let tags = camera.visible_tags(&self.commands);
+            let layers = self.layers.entry(camera_id.clone()).or_default();
+            let camera_stats = stats.cameras.entry(camera_id.clone()).or_default();
+            for tag in tags {
+                let mut tag_stats = TagStats {
+                    commands: self.commands[&tag].len(),
+                    cached: true,
+                    ..Default::default()
+                };
+                let surface = Self::layer_surface(camera_id, &tag);
+                let dirty = layers
+                    .get(&tag)
+                    .map(|layer| layer.dirty || layer.size != (width, height))
+                    .unwrap_or(true);
+                if dirty || !self.renderer.has_surface(&surface) {
+                    self.renderer.destroy_surface(&surface);
+                    self.renderer.create_surface(&surface, width, height);
+                    let (commands, culling) =
+                        cull_commands(&self.commands[&tag], &view, area_size, &self.image_sizes);
+                    count_draw_calls(&commands, &mut stats.draw_calls);
+                    let commands = resolve_frames(commands, &self.frames);
+                    let commands = std::iter::once(Command::Store)
+                        .chain(std::iter::once(transform.clone()))
+                        .chain(commands.into_iter())
+                        .chain(std::iter::once(Command::Restore));
+                    if let Ok((render_ops, renderables)) =
+                        self.renderer.update_surface(&surface, commands)
+                    {
+                        tag_stats.render_ops = render_ops;
+                        tag_stats.renderables = renderables;
+                    }
+                    tag_stats.cached = false;
+                    layers.insert(
+                        tag.clone(),
+                        Layer {
+                            dirty: false,
+                            culling,
+                            size: (width, height),
+                        },
+                    );
+                }
+                if let Some(layer) = layers.get(&tag) {
+                    tag_stats.culling = layer.culling;
+                }
+                let composite = Command::Draw(Renderable::Image(Image {
+                    image: surface.into(),
+                    source: None,
+                    destination: Some(Rect {
+                        x: area.x,
+                        y: area.y,
+                        w: width as f32,
+                        h: height as f32,
+                    }),
+                    alignment: Vec2::new(0.0, 0.0),
+                }));
+                count_draw_calls(std::slice::from_ref(&composite), &mut stats.draw_calls);
+                drop(self.renderer.execute(std::iter::once(composite)));
+                camera_stats.insert(tag, tag_stats);
+            }
+            let overlays = self.overlays.commands(
+                camera,
+                &view,
+                area_size,
+                &self.commands,
+                &self.ids,
+                &self.image_sizes,
+            );
+            if !overlays.is_empty() {
+                count_draw_calls(&overlays, &mut stats.draw_calls);
+                let mask = if camera.clip {
+                    Some(camera.viewport_mask(view_size))
+                } else {
+                    None
+                };
                 let commands = std::iter::once(Command::Store)
-                    .chain(std::iter::once(transform))
-                    .chain(self.commands.values().cloned().flatten())
+                    .chain(mask.into_iter())
+                    .chain(std::iter::once(Command::Transform(
+                        1.0, 0.0, 0.0, 1.0, area.x, area.y,
+                    )))
+                    .chain(overlays.into_iter())
                     .chain(std::iter::once(Command::Restore));
                 drop(self.renderer.execute(commands));
-            } else {
-                for tag in &camera.camera.tags {
-                    if let Some(commands) = self.commands.get(&tag.to_string()) {
-                        let commands = std::iter::once(Command::Store)
-                            .chain(std::iter::once(transform.clone()))
-                            .chain(commands.iter().cloned())
-                            .chain(std::iter::once(Command::Restore));
-                        drop(self.renderer.execute(commands));
-                    }
-                }
             }
         }
+        let overlay = self
+            .overlays
+            .stats
+            .commands(&self.stats.summary(), view_size);
+        if !overlay.is_empty() {
+            count_draw_calls(&overlay, &mut stats.draw_calls);
+            let commands = std::iter::once(Command::Store)
+                .chain(overlay.into_iter())
+                .chain(std::iter::once(Command::Restore));
+            drop(self.renderer.execute(commands));
+        }
         for camera in self.cameras.values_mut() {
-            camera.world_transform = camera.camera.view_matrix(&camera.transform, view_size);
+            camera.world_transform = camera.view_matrix(view_size);
             camera.world_inverse_transform = camera.world_transform.inverse().unwrap_or_default();
         }
+        stats.resources.images = self.renderer.images_count();
+        stats.resources.fontfaces = self.renderer.fontfaces_count();
+        stats.resources.surfaces = self.renderer.surfaces_count();
+        stats.memory.images = self
+            .image_sizes
+            .values()
+            .map(|size| size.x as usize * size.y as usize * 4)
+            .sum();
+        stats.memory.surfaces = self
+            .layers
+            .values()
+            .flat_map(|layers| layers.values())
+            .map(|layer| layer.size.0 * layer.size.1 * 4)
+            .sum();
+        stats.memory.commands = self
+            .commands
+            .values()
+            .map(|commands| commands.len())
+            .sum::<usize>()
+            * std::mem::size_of::<Command>();
+        stats.frames = self.stats.frames + 1;
+        stats.frame_time = now() - started;
+        self.stats = stats;
     }
 }
 
+/// High resolution milliseconds, falls back to wall clock outside of window.
+fn now() -> f64 {
+    web_sys::window()
+        .and_then(|window| window.performance())
+        .map(|performance| performance.now())
+        .unwrap_or_else(js_sys::Date::now)
+}
+
 #[wasm_bindgen(start)]
 pub fn main_js() -> Result<(), JsValue> {
     #[cfg(feature = "console_error_panic_hook")]
@@ -191,6 +484,11 @@ pub fn camera_set(renderer_id: &str, id: &str, camera: JsValue) -> Result<(), Js
         if let Some(renderer) = renderers.get_mut(renderer_id) {
             if let Some(instance) = renderer.cameras.get_mut(id) {
                 *instance = camera;
+                if let Some(controller) = renderer.controllers.get_mut(id) {
+                    controller.stop();
+                }
+                renderer.mark_camera_dirty(id);
+                renderer.render();
                 Ok(())
             } else {
                 Err(format!("Renderer {} does not have camera: {}", renderer_id, id).into())
@@ -208,6 +506,7 @@ pub fn camera_destroy(renderer: &str, id: &str) -> Result<(), JsValue> {
     if let Ok(mut renderers) = RENDERERS.write() {
         if let Some(renderer) = renderers.get_mut(renderer) {
             renderer.cameras.remove(id);
+            renderer.controllers.remove(id);
             renderer.refresh(true);
             Ok(())
         } else {
@@ -218,6 +517,242 @@ pub fn camera_destroy(renderer: &str, id: &str) -> Result<(), JsValue> {
     }
 }
 
+#[wasm_bindgen]
+pub fn cameras_list(renderer: &str) -> Result<JsValue, JsValue> {
+    if let Ok(renderers) = RENDERERS.read() {
+        if let Some(renderer) = renderers.get(renderer) {
+            match JsValue::from_serde(&renderer.cameras) {
+                Ok(cameras) => Ok(cameras),
+                Err(error) => Err(format!("{:#?}", error).into()),
+            }
+        } else {
+            Err(format!("Renderer does not exists: {}", renderer).into())
+        }
+    } else {
+        Err("Could not acquire a read access to renderers".into())
+    }
+}
+
+/// Id of topmost camera drawing at view point.
+#[wasm_bindgen]
+pub fn camera_at(renderer: &str, x: f32, y: f32) -> Result<Option<String>, JsValue> {
+    if let Ok(renderers) = RENDERERS.read() {
+        if let Some(renderer) = renderers.get(renderer) {
+            let view_size = renderer.renderer.view_size();
+            let point = Vec2::new(x, y);
+            Ok(renderer
+                .cameras
+                .iter()
+                .filter(|(_, camera)| {
+                    let viewport = camera.viewport_rect(view_size);
+                    point.x >= viewport.x
+                        && point.y >= viewport.y
+                        && point.x < viewport.x + viewport.w
+                        && point.y < viewport.y + viewport.h
+                })
+                .max_by(|a, b| a.1.order.total_cmp(&b.1.order))
+                .map(|(id, _)| id.to_owned()))
+        } else {
+            Err(format!("Renderer does not exists: {}", renderer).into())
+        }
+    } else {
+        Err("Could not acquire a read access to renderers".into())
+    }
+}
+
+/// Runs operation on camera and redraws renderer.
+fn with_camera<F>(renderer_id: &str, id: &str, f: F) -> Result<(), JsValue>
+where
+    F: FnOnce(&mut Camera),
+{
+    if let Ok(mut renderers) = RENDERERS.write() {
+        if let Some(renderer) = renderers.get_mut(renderer_id) {
+            if let Some(camera) = renderer.cameras.get_mut(id) {
+                f(camera);
+                renderer.mark_camera_dirty(id);
+                renderer.render();
+                Ok(())
+            } else {
+                Err(format!("Renderer {} does not have camera: {}", renderer_id, id).into())
+            }
+        } else {
+            Err(format!("Renderer does not exists: {}", renderer_id).into())
+        }
+    } else {
+        Err("Could not acquire a write access to renderers".into())
+    }
+}
+
+/// Sets camera viewport in fractions of view size, null covers whole view.
+#[wasm_bindgen]
+pub fn camera_viewport_set(renderer_id: &str, id: &str, viewport: JsValue) -> Result<(), JsValue> {
+    let viewport = if viewport.is_null() || viewport.is_undefined() {
+        None
+    } else {
+        match viewport.into_serde::<Rect>() {
+            Ok(viewport) => Some(viewport),
+            Err(error) => return Err(format!("{:#?}", error).into()),
+        }
+    };
+    with_camera(renderer_id, id, |camera| camera.viewport = viewport)
+}
+
+#[wasm_bindgen]
+pub fn camera_clear_color_set(renderer_id: &str, id: &str, color: JsValue) -> Result<(), JsValue> {
+    let color = if color.is_null() || color.is_undefined() {
+        None
+    } else {
+        match color.into_serde::<Color>() {
+            Ok(color) => Some(color),
+            Err(error) => return Err(format!("{:#?}", error).into()),
+        }
+    };
+    with_camera(renderer_id, id, |camera| camera.clear_color = color)
+}
+
+#[wasm_bindgen]
+pub fn camera_clip_set(renderer_id: &str, id: &str, clip: bool) -> Result<(), JsValue> {
+    with_camera(renderer_id, id, |camera| camera.clip = clip)
+}
+
+#[wasm_bindgen]
+pub fn camera_order_set(renderer_id: &str, id: &str, order: f32) -> Result<(), JsValue> {
+    with_camera(renderer_id, id, |camera| camera.order = order)
+}
+
+/// Runs operation on camera controller and redraws renderer.
+fn with_controller<F, R>(renderer_id: &str, id: &str, f: F) -> Result<R, JsValue>
+where
+    F: FnOnce(&mut Camera, &mut CameraController, Vec2) -> R,
+{
+    if let Ok(mut renderers) = RENDERERS.write() {
+        if let Some(renderer) = renderers.get_mut(renderer_id) {
+            let view_size = renderer.renderer.view_size();
+            if let Some((camera, controller)) = renderer.controller(id) {
+                let result = f(camera, controller, view_size);
+                renderer.mark_camera_dirty(id);
+                renderer.render();
+                Ok(result)
+            } else {
+                Err(format!("Renderer {} does not have camera: {}", renderer_id, id).into())
+            }
+        } else {
+            Err(format!("Renderer does not exists: {}", renderer_id).into())
+        }
+    } else {
+        Err("Could not acquire a write access to renderers".into())
+    }
+}
+
+#[wasm_bindgen]
+pub fn camera_controls_set(renderer_id: &str, id: &str, controls: JsValue) -> Result<(), JsValue> {
+    let controls = if controls.is_null() || controls.is_undefined() {
+        Default::default()
+    } else {
+        match controls.into_serde::<CameraControls>() {
+            Ok(controls) => controls,
+            Err(error) => return Err(format!("{:#?}", error).into()),
+        }
+    };
+    with_controller(renderer_id, id, |_, controller, _| {
+        controller.controls = controls;
+    })
+}
+
+#[wasm_bindgen]
+pub fn camera_pan(
+    renderer_id: &str,
+    id: &str,
+    from_x: f32,
+    from_y: f32,
+    to_x: f32,
+    to_y: f32,
+) -> Result<(), JsValue> {
+    with_controller(renderer_id, id, |camera, controller, view_size| {
+        controller.pan(
+            camera,
+            view_size,
+            Vec2::new(from_x, from_y),
+            Vec2::new(to_x, to_y),
+        );
+    })
+}
+
+#[wasm_bindgen]
+pub fn camera_zoom(renderer_id: &str, id: &str, x: f32, y: f32, steps: f32) -> Result<(), JsValue> {
+    with_controller(renderer_id, id, |camera, controller, view_size| {
+        controller.zoom_steps(camera, view_size, Vec2::new(x, y), steps);
+    })
+}
+
+#[wasm_bindgen]
+pub fn camera_frame(renderer_id: &str, id: &str, selection: bool) -> Result<bool, JsValue> {
+    let bounds = if let Ok(renderers) = RENDERERS.read() {
+        if let Some(renderer) = renderers.get(renderer_id) {
+            renderer.world_bounds(id, selection)
+        } else {
+            return Err(format!("Renderer does not exists: {}", renderer_id).into());
+        }
+    } else {
+        return Err("Could not acquire a read access to renderers".into());
+    };
+    match bounds {
+        Some(bounds) => with_controller(renderer_id, id, |camera, controller, view_size| {
+            controller.frame(camera, view_size, bounds);
+            true
+        }),
+        None => Ok(false),
+    }
+}
+
+#[wasm_bindgen]
+pub fn camera_bookmark_save(renderer_id: &str, id: &str, name: &str) -> Result<(), JsValue> {
+    with_controller(renderer_id, id, |camera, controller, _| {
+        controller.save_bookmark(camera, name);
+    })
+}
+
+#[wasm_bindgen]
+pub fn camera_bookmark_restore(renderer_id: &str, id: &str, name: &str) -> Result<(), JsValue> {
+    if with_controller(renderer_id, id, |camera, controller, _| {
+        controller.restore_bookmark(camera, name)
+    })? {
+        Ok(())
+    } else {
+        Err(format!("Camera {} does not have bookmark: {}", id, name).into())
+    }
+}
+
+#[wasm_bindgen]
+pub fn camera_bookmarks(renderer_id: &str, id: &str) -> Result<JsValue, JsValue> {
+    let bookmarks = with_controller(renderer_id, id, |_, controller, _| {
+        controller.bookmarks.clone()
+    })?;
+    match JsValue::from_serde(&bookmarks) {
+        Ok(bookmarks) => Ok(bookmarks),
+        Err(error) => Err(format!("{:#?}", error).into()),
+    }
+}
+
+#[wasm_bindgen]
+pub fn camera_bookmarks_set(
+    renderer_id: &str,
+    id: &str,
+    bookmarks: JsValue,
+) -> Result<(), JsValue> {
+    let bookmarks = if bookmarks.is_null() || bookmarks.is_undefined() {
+        Default::default()
+    } else {
+        match bookmarks.into_serde::<HashMap<String, CameraPose>>() {
+            Ok(bookmarks) => bookmarks,
+            Err(error) => return Err(format!("{:#?}", error).into()),
+        }
+    };
+    with_controller(renderer_id, id, |_, controller, _| {
+        controller.bookmarks = bookmarks;
+    })
+}
+
 #[wasm_bindgen]
 pub fn camera_state(renderer_id: &str, id: &str) -> Result<JsValue, JsValue> {
     if let Ok(renderers) = RENDERERS.read() {
@@ -285,18 +820,32 @@ pub fn camera_world_to_screen_space(
 }
 
 #[wasm_bindgen]
-pub fn render_commands(renderer: &str, commands: JsValue) -> Result<(), JsValue> {
+pub fn render_commands(renderer: &str, commands: JsValue, ids: JsValue) -> Result<(), JsValue> {
     if commands.is_null() || commands.is_undefined() {
         return Ok(());
     }
+    let mut ids = if ids.is_null() || ids.is_undefined() {
+        CommandIds::default()
+    } else {
+        match ids.into_serde::<CommandIds>() {
+            Ok(ids) => ids,
+            Err(error) => return Err(format!("{:#?}", error).into()),
+        }
+    };
     match commands.into_serde::<RenderCommands>() {
         Ok(RenderCommands(commands)) => {
             if let Ok(mut renderers) = RENDERERS.write() {
                 if let Some(renderer) = renderers.get_mut(renderer) {
                     for (tag, commands) in commands {
+                        renderer.mark_tag_dirty(&tag);
                         if commands.is_empty() {
                             renderer.commands.remove(&tag);
+                            renderer.ids.remove(&tag);
                         } else {
+                            match ids.remove(&tag) {
+                                Some(ids) => renderer.ids.insert(tag.clone(), ids),
+                                None => renderer.ids.remove(&tag),
+                            };
                             renderer.commands.insert(tag, commands);
                         }
                     }
@@ -313,6 +862,196 @@ pub fn render_commands(renderer: &str, commands: JsValue) -> Result<(), JsValue>
     }
 }
 
+#[wasm_bindgen]
+pub fn render_commands_patch(renderer: &str, patches: JsValue) -> Result<(), JsValue> {
+    if patches.is_null() || patches.is_undefined() {
+        return Ok(());
+    }
+    match patches.into_serde::<Vec<RenderCommandsPatch>>() {
+        Ok(patches) => {
+            if let Ok(mut renderers) = RENDERERS.write() {
+                if let Some(renderer) = renderers.get_mut(renderer) {
+                    for patch in patches {
+                        let tag = patch.tag.clone();
+                        let (start, remove) = (patch.start, patch.remove);
+                        renderer.mark_tag_dirty(&tag);
+                        if !patch.apply(&mut renderer.commands, &mut renderer.ids) {
+                            renderer.render();
+                            return Err(format!(
+                                "Patch range {}..{} is out of bounds of tag: {}",
+                                start,
+                                start + remove,
+                                tag
+                            )
+                            .into());
+                        }
+                    }
+                    renderer.render();
+                    Ok(())
+                } else {
+                    Err(format!("Renderer does not exists: {}", renderer).into())
+                }
+            } else {
+                Err("Could not acquire a write access to renderers".into())
+            }
+        }
+        Err(error) => Err(format!("{:#?}", error).into()),
+    }
+}
+
+#[wasm_bindgen]
+pub fn pick(renderer: &str, x: f32, y: f32, precise: bool) -> Result<JsValue, JsValue> {
+    if let Ok(renderers) = RENDERERS.read() {
+        if let Some(renderer) = renderers.get(renderer) {
+            let mode = if precise {
+                PickMode::Precise
+            } else {
+                PickMode::Bounds
+            };
+            let result = picking::pick(
+                &renderer.commands,
+                &renderer.ids,
+                renderer.cameras.values(),
+                renderer.renderer.view_size(),
+                Vec2::new(x, y),
+                mode,
+                &renderer.image_sizes,
+            );
+            match JsValue::from_serde(&result) {
+                Ok(result) => Ok(result),
+                Err(error) => Err(format!("{:#?}", error).into()),
+            }
+        } else {
+            Err(format!("Renderer does not exists: {}", renderer).into())
+        }
+    } else {
+        Err("Could not acquire a read access to renderers".into())
+    }
+}
+
+#[wasm_bindgen]
+pub fn overlays_set(renderer: &str, overlays: JsValue) -> Result<(), JsValue> {
+    let overlays = if overlays.is_null() || overlays.is_undefined() {
+        Default::default()
+    } else {
+        match overlays.into_serde::<Overlays>() {
+            Ok(overlays) => overlays,
+            Err(error) => return Err(format!("{:#?}", error).into()),
+        }
+    };
+    if let Ok(mut renderers) = RENDERERS.write() {
+        if let Some(renderer) = renderers.get_mut(renderer) {
+            renderer.overlays = overlays;
+            renderer.render();
+            Ok(())
+        } else {
+            Err(format!("Renderer does not exists: {}", renderer).into())
+        }
+    } else {
+        Err("Could not acquire a write access to renderers".into())
+    }
+}
+
+#[wasm_bindgen]
+pub fn overlays_state(renderer: &str) -> Result<JsValue, JsValue> {
+    if let Ok(renderers) = RENDERERS.read() {
+        if let Some(renderer) = renderers.get(renderer) {
+            match JsValue::from_serde(&renderer.overlays) {
+                Ok(overlays) => Ok(overlays),
+                Err(error) => Err(format!("{:#?}", error).into()),
+            }
+        } else {
+            Err(format!("Renderer does not exists: {}", renderer).into())
+        }
+    } else {
+        Err("Could not acquire a read access to renderers".into())
+    }
+}
+
+#[wasm_bindgen]
+pub fn overlay_enable(renderer: &str, name: &str, enabled: bool) -> Result<(), JsValue> {
+    if let Ok(mut renderers) = RENDERERS.write() {
+        if let Some(renderer) = renderers.get_mut(renderer) {
+            if renderer.overlays.set_enabled(name, enabled) {
+                renderer.render();
+                Ok(())
+            } else {
+                Err(format!("Unknown overlay: {}", name).into())
+            }
+        } else {
+            Err(format!("Renderer does not exists: {}", renderer).into())
+        }
+    } else {
+        Err("Could not acquire a write access to renderers".into())
+    }
+}
+
+#[wasm_bindgen]
+pub fn selection_set(renderer: &str, ids: JsValue) -> Result<(), JsValue> {
+    let ids = if ids.is_null() || ids.is_undefined() {
+        vec![]
+    } else {
+        match ids.into_serde::<Vec<String>>() {
+            Ok(ids) => ids,
+            Err(error) => return Err(format!("{:#?}", error).into()),
+        }
+    };
+    if let Ok(mut renderers) = RENDERERS.write() {
+        if let Some(renderer) = renderers.get_mut(renderer) {
+            renderer.overlays.selection.ids = ids;
+            renderer.render();
+            Ok(())
+        } else {
+            Err(format!("Renderer does not exists: {}", renderer).into())
+        }
+    } else {
+        Err("Could not acquire a write access to renderers".into())
+    }
+}
+
+#[wasm_bindgen]
+pub fn renderer_stats(renderer: &str) -> Result<JsValue, JsValue> {
+    if let Ok(renderers) = RENDERERS.read() {
+        if let Some(renderer) = renderers.get(renderer) {
+            match JsValue::from_serde(&renderer.stats) {
+                Ok(stats) => Ok(stats),
+                Err(error) => Err(format!("{:#?}", error).into()),
+            }
+        } else {
+            Err(format!("Renderer does not exists: {}", renderer).into())
+        }
+    } else {
+        Err("Could not acquire a read access to renderers".into())
+    }
+}
+
+#[wasm_bindgen]
+pub fn culling_stats(renderer: &str) -> Result<JsValue, JsValue> {
+    if let Ok(renderers) = RENDERERS.read() {
+        if let Some(renderer) = renderers.get(renderer) {
+            let stats = renderer
+                .layers
+                .iter()
+                .map(|(camera, layers)| {
+                    let layers = layers
+                        .iter()
+                        .map(|(tag, layer)| (tag.clone(), layer.culling))
+                        .collect::<HashMap<_, _>>();
+                    (camera.clone(), layers)
+                })
+                .collect::<HashMap<_, _>>();
+            match JsValue::from_serde(&stats) {
+                Ok(stats) => Ok(stats),
+                Err(error) => Err(format!("{:#?}", error).into()),
+            }
+        } else {
+            Err(format!("Renderer does not exists: {}", renderer).into())
+        }
+    } else {
+        Err("Could not acquire a read access to renderers".into())
+    }
+}
+
 #[wasm_bindgen]
 pub fn render_state(renderer: &str) -> Result<JsValue, JsValue> {
     if let Ok(renderers) = RENDERERS.read() {
@@ -340,8 +1079,20 @@ pub fn add_image_resource(
     }
     if let Ok(mut renderers) = RENDERERS.write() {
         if let Some(renderer) = renderers.get_mut(renderer) {
+            let size = Vec2::new(
+                resource.natural_width() as f32,
+                resource.natural_height() as f32,
+            );
             match renderer.renderer.add_resource(id.to_owned(), resource) {
-                Ok(id) => Ok(id.to_string()),
+                Ok(resource_id) => {
+                    let resource_id = resource_id.to_string();
+                    renderer.image_sizes.insert(id.to_owned(), size);
+                    renderer.mark_all_dirty();
+                    renderer
+                        .image_names
+                        .insert(resource_id.clone(), id.to_owned());
+                    Ok(resource_id)
+                }
                 Err(error) => Err(format!("{:#?}", error).into()),
             }
         } else {
@@ -356,6 +1107,10 @@ pub fn add_image_resource(
 pub fn remove_image_resource(renderer_id: &str, id: &str) -> Result<HtmlImageElement, JsValue> {
     if let Ok(mut renderers) = RENDERERS.write() {
         if let Some(renderer) = renderers.get_mut(renderer_id) {
+            if let Some(name) = renderer.image_names.remove(id) {
+                renderer.image_sizes.remove(&name);
+            }
+            renderer.mark_all_dirty();
             match ID::from_str(id) {
                 Ok(id) => match renderer.renderer.remove_resource(id) {
                     Ok(resource) => Ok(resource),
@@ -371,6 +1126,60 @@ pub fn remove_image_resource(renderer_id: &str, id: &str) -> Result<HtmlImageEle
     }
 }
 
+/// Registers sprite atlas frames, returns project path of atlas image to load.
+#[wasm_bindgen]
+pub fn atlas_set(renderer: &str, path: &str, atlas: JsValue) -> Result<String, JsValue> {
+    let atlas = match atlas.into_serde::<SpriteAtlas>() {
+        Ok(atlas) => atlas,
+        Err(error) => return Err(format!("{:#?}", error).into()),
+    };
+    let image = match atlas.image_path(path) {
+        Some(image) => image,
+        None => return Err(format!("Atlas does not specify image: {}", path).into()),
+    };
+    if let Ok(mut renderers) = RENDERERS.write() {
+        if let Some(renderer) = renderers.get_mut(renderer) {
+            remove_atlas_frames(renderer, path);
+            let frames = atlas.frames(path, &image);
+            renderer.image_sizes.extend(frame_sizes(&frames));
+            renderer.frames.extend(frames);
+            renderer.mark_all_dirty();
+            Ok(image)
+        } else {
+            Err(format!("Renderer does not exists: {}", renderer).into())
+        }
+    } else {
+        Err("Could not acquire a write access to renderers".into())
+    }
+}
+
+#[wasm_bindgen]
+pub fn atlas_remove(renderer: &str, path: &str) -> Result<(), JsValue> {
+    if let Ok(mut renderers) = RENDERERS.write() {
+        if let Some(renderer) = renderers.get_mut(renderer) {
+            remove_atlas_frames(renderer, path);
+            renderer.mark_all_dirty();
+            Ok(())
+        } else {
+            Err(format!("Renderer does not exists: {}", renderer).into())
+        }
+    } else {
+        Err("Could not acquire a write access to renderers".into())
+    }
+}
+
+fn remove_atlas_frames(renderer: &mut Renderer, path: &str) {
+    let prefix = format!("{}{}", path, FRAME_SEPARATOR);
+    let image_sizes = &mut renderer.image_sizes;
+    renderer.frames.retain(|name, _| {
+        let keep = !name.starts_with(&prefix);
+        if !keep {
+            image_sizes.remove(name);
+        }
+        keep
+    });
+}
+
 #[wasm_bindgen]
 pub fn add_fontface_resource(
     renderer: &str,
@@ -383,7 +1192,10 @@ pub fn add_fontface_resource(
     if let Ok(mut renderers) = RENDERERS.write() {
         if let Some(renderer) = renderers.get_mut(renderer) {
             match renderer.renderer.add_resource(id.to_owned(), resource) {
-                Ok(id) => Ok(id.to_string()),
+                Ok(id) => {
+                    renderer.mark_all_dirty();
+                    Ok(id.to_string())
+                }
                 Err(error) => Err(format!("{:#?}", error).into()),
             }
         } else {
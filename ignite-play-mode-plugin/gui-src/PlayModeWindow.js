@@ -1,5 +1,5 @@
 import React from 'react';
-import { IconButton, Tooltip } from '@material-ui/core';
+import { IconButton, MenuItem, Select, Tooltip } from '@material-ui/core';
 import {
   PlayArrow as PlayIcon,
   OpenInBrowser as PlayExternalIcon,
@@ -14,11 +14,15 @@ import {
   Fullscreen as FullscreenIcon,
   BugReport as DebugIcon,
   Cancel as CancelIcon,
+  Visibility as WatchIcon,
+  VisibilityOff as UnwatchIcon,
+  FiberManualRecord as RecordIcon,
 } from '@material-ui/icons';
 import { registerWindow } from 'ignite-gui';
 import { ignite, on, off } from 'ignite-editor';
 
-const VIEW_URL = 'http://localhost:19100';
+const DEFAULT_SESSION = 'default';
+const DEFAULT_PORT = 19100;
 
 const style = {
   container: {
@@ -47,17 +51,54 @@ const style = {
     alignItems: 'center',
     color: 'white',
   },
+  problems: {
+    overflow: 'auto',
+    color: 'white',
+    fontFamily: 'monospace',
+  },
+  problem: {
+    padding: 4,
+    cursor: 'pointer',
+  },
+  sessions: {
+    marginRight: 16,
+    verticalAlign: 'middle',
+  },
 };
 
+const CONSOLE_LEVELS = ['debug', 'info', 'warning', 'error'];
+
+const PROBLEM_COLORS = {
+  error: 'rgb(255, 100, 100)',
+  warning: 'rgb(255, 200, 100)',
+  note: 'rgb(150, 150, 255)',
+  help: 'rgb(150, 255, 150)',
+};
+
+// Flags of session state as reported by `status` query.
+function sessionFlags(state) {
+  const status = !!state ? state.status : 'idle';
+  return {
+    isRunning: status === 'serving' || status === 'building-while-serving',
+    isBuilding: status === 'building'
+      || status === 'building-while-serving'
+      || (status === 'stopping' && !!state.build),
+  };
+}
+
 class PlayModeWindow extends React.Component {
   constructor(props) {
     super(props);
 
     const storage = props.storage();
-    storage.isRunning = storage.isRunning || false;
+    storage.session = storage.session || DEFAULT_SESSION;
+    storage.sessions = storage.sessions || [{ id: DEFAULT_SESSION, group: null, port: DEFAULT_PORT }];
+    storage.states = storage.states || {};
     storage.isReloading = storage.isReloading || false;
-    storage.isBuilding = storage.isBuilding || false;
     storage.isPaused = storage.isPaused || false;
+    storage.problems = storage.problems || [];
+    storage.isWatching = storage.isWatching || false;
+    storage.isRecording = storage.isRecording || false;
     this.state = {};
     this._onPlay = this.onPlay.bind(this);
     this._onStop = this.onStop.bind(this);
@@ -68,19 +109,39 @@ class PlayModeWindow extends React.Component {
     this._onBuildRelease = this.onBuildRelease.bind(this);
     this._onBuildCancel = this.onBuildCancel.bind(this);
     this._onTakeScreenshot = this.onTakeScreenshot.bind(this);
+    this._onToggleRecording = this.onToggleRecording.bind(this);
+    this._onRecording = this.onRecording.bind(this);
     this._onGoFullscreen = this.onGoFullscreen.bind(this);
     this._onChange = this.onChange.bind(this);
+    this._onStatus = this.onStatus.bind(this);
+    this._onSelectSession = this.onSelectSession.bind(this);
+    this._onProblems = this.onProblems.bind(this);
+    this._onToggleWatch = this.onToggleWatch.bind(this);
+    this._onWatch = this.onWatch.bind(this);
+    this._onConsoleMessage = this.onConsoleMessage.bind(this);
+    this._onViewRef = this.onViewRef.bind(this);
     this._viewRef = React.createRef();
-    this._periodicPreview = null;
+    this._recordingInterval = null;
+  }
+
+  // Selected session with its running and building flags.
+  currentSession() {
+    const { session, sessions, states } = this.props.storage();
+    const found = sessions.find(item => item.id === session)
+      || { id: session, group: null, port: DEFAULT_PORT };
+    return {
+      ...found,
+      isRunning: false,
+      isBuilding: false,
+      ...states[session],
+    };
   }
 
   onPlay() {
     const storage = this.props.storage();
-    const { isRunning, isPaused } = storage;
-    if (!isRunning) {
-      ignite('ignite-play-mode-plugin', 'start');
-      clearInterval(this._periodicPreview);
-      this._periodicPreview = setInterval(() => this.takeScreenshot(true), 60000);
+    const { isPaused } = storage;
+    if (!this.currentSession().isRunning) {
+      ignite('ignite-play-mode-plugin', 'start', storage.session);
     } else {
       storage.isPaused = !isPaused;
       this.setState({});
@@ -88,13 +149,10 @@ class PlayModeWindow extends React.Component {
   }
 
   onStop() {
-    clearInterval(this._periodicPreview);
-    this._periodicPreview = null;
     const storage = this.props.storage();
     storage.isPaused = false;
-    storage.isRunning = false;
     this.setState({});
-    ignite('ignite-play-mode-plugin', 'stop');
+    ignite('ignite-play-mode-plugin', 'stop', storage.session);
   }
 
   onReload() {
@@ -113,36 +171,80 @@ class PlayModeWindow extends React.Component {
     }
   }
 
-  onBuild() {
+  onBuild(event) {
     this.onStop();
-    ignite('ignite-play-mode-plugin', 'build');
+    ignite('ignite-play-mode-plugin', 'build', {
+      session: this.props.storage().session,
+      force: !!event && event.shiftKey,
+    });
   }
 
-  onBuildRelease() {
+  onBuildRelease(event) {
     this.onStop();
-    ignite('ignite-play-mode-plugin', 'build-release');
+    ignite('ignite-play-mode-plugin', 'build-release', {
+      session: this.props.storage().session,
+      force: !!event && event.shiftKey,
+    });
   }
 
   onBuildCancel() {
     this.onStop();
-    ignite('ignite-play-mode-plugin', 'build-cancel');
+    ignite('ignite-play-mode-plugin', 'build-cancel', this.props.storage().session);
   }
 
   onTakeScreenshot() {
     this.takeScreenshot();
   }
 
+  canCapture() {
+    const { isRunning, isBuilding } = this.currentSession();
+    const { isPaused } = this.props.storage();
+    return !!isRunning && !isBuilding && !isPaused && !!this._viewRef.current;
+  }
+
   takeScreenshot(preview) {
-    const { isRunning, isBuilding, isPaused } = this.props.storage();
-    if (!!isRunning && !isBuilding && !isPaused && !!this._viewRef.current) {
+    if (this.canCapture()) {
+      const { session } = this.props.storage();
       this._viewRef.current.capturePage().then(image => {
-        ignite('?', !!preview ? 'screenshot-preview' : 'screenshot', image.toDataURL());
+        ignite('?', !!preview ? 'screenshot-preview' : 'screenshot', {
+          image: image.toDataURL(),
+          session,
+        });
+      });
+    }
+  }
+
+  onToggleRecording() {
+    if (!!this.props.storage().isRecording) {
+      ignite('ignite-screenshots-plugin', 'recording-stop');
+    } else {
+      ignite('ignite-screenshots-plugin', 'recording-start', {
+        session: this.props.storage().session,
       });
     }
   }
 
+  onRecording(status) {
+    if (!!status.session && status.session !== this.props.storage().session) {
+      return;
+    }
+    clearInterval(this._recordingInterval);
+    this._recordingInterval = null;
+    if (!!status.isRecording) {
+      this._recordingInterval = setInterval(() => {
+        if (this.canCapture()) {
+          this._viewRef.current.capturePage().then(image => {
+            ignite('ignite-screenshots-plugin', 'recording-frame', image.toDataURL());
+          });
+        }
+      }, 1000 / Math.max(1, status.fps));
+    }
+    this.props.storage().isRecording = !!status.isRecording;
+    this.setState({});
+  }
+
   onGoFullscreen() {
-    if (!!this.props.storage().isRunning && !!this._viewRef.current) {
+    if (!!this.currentSession().isRunning && !!this._viewRef.current) {
       const { current } = this._viewRef;
       if (!!current && !!current.requestFullscreen) {
         current.requestFullscreen().then(() => current.focus());
@@ -152,24 +254,147 @@ class PlayModeWindow extends React.Component {
 
   onChange(state) {
     const storage = this.props.storage();
-    storage.isRunning = state.isRunning;
-    storage.isBuilding = state.isBuilding;
+    storage.states[state.id || DEFAULT_SESSION] = {
+      isRunning: state.isRunning,
+      isBuilding: state.isBuilding,
+    };
+    this.setState({});
+  }
+
+  onStatus(sessions) {
+    const storage = this.props.storage();
+    storage.sessions = sessions.map(({ id, group, port }) => ({ id, group, port }));
+    storage.states = {};
+    for (const { id, state } of sessions) {
+      storage.states[id] = sessionFlags(state);
+    }
+    if (!sessions.some(({ id }) => id === storage.session)) {
+      storage.session = DEFAULT_SESSION;
+    }
+    this.setState({});
+  }
+
+  onSelectSession(event) {
+    const storage = this.props.storage();
+    if (storage.session === event.target.value) {
+      return;
+    }
+    if (!!storage.isRecording) {
+      ignite('ignite-screenshots-plugin', 'recording-stop');
+    }
+    storage.session = event.target.value;
+    storage.isPaused = false;
+    this.setState({});
+  }
+
+  onViewRef(view) {
+    const { current } = this._viewRef;
+    if (current === view) {
+      return;
+    }
+    if (!!current) {
+      current.removeEventListener('console-message', this._onConsoleMessage);
+    }
+    this._viewRef.current = view;
+    if (!!view) {
+      view.addEventListener('console-message', this._onConsoleMessage);
+    }
+  }
+
+  onConsoleMessage({ level, message }) {
+    ignite('ignite-play-mode-plugin', 'log', {
+      level: CONSOLE_LEVELS[level] || 'info',
+      message,
+      session: this.props.storage().session,
+    });
+  }
+
+  onToggleWatch() {
+    const { isWatching, session } = this.props.storage();
+    ignite('ignite-play-mode-plugin', !isWatching ? 'watch-start' : 'watch-stop', session);
+  }
+
+  onWatch(isWatching) {
+    this.props.storage().isWatching = isWatching;
+    this.setState({});
+  }
+
+  onProblems(problems) {
+    this.props.storage().problems = problems;
     this.setState({});
   }
 
+  onOpenProblem(index) {
+    ignite('ignite-play-mode-plugin', 'problem-open', index);
+  }
+
+  renderProblems(problems) {
+    return (
+      <div style={{ ...style.view, ...style.problems }}>
+        {problems.map((problem, index) => (
+          <div
+            key={index}
+            style={{ ...style.problem, color: PROBLEM_COLORS[problem.level] }}
+            onClick={() => this.onOpenProblem(index)}
+          >
+            {`${problem.level}${!!problem.code ? `[${problem.code}]` : ''}: ${problem.message}`}
+            <br />
+            {`  --> ${problem.file}:${problem.line}:${problem.column}`}
+          </div>
+        ))}
+      </div>
+    );
+  }
+
   componentDidMount() {
     this._onChangeToken = on('gui/ignite-play-mode-plugin/change', this._onChange);
+    this._onStatusToken = on('gui/ignite-play-mode-plugin/status', this._onStatus);
+    this._onProblemsToken = on('gui/ignite-play-mode-plugin/problems', this._onProblems);
+    this._onWatchToken = on('gui/ignite-play-mode-plugin/watch', this._onWatch);
+    this._onReloadToken = on('gui/ignite-play-mode-plugin/reload', id => {
+      if ((id || DEFAULT_SESSION) === this.props.storage().session) {
+        this.onReload();
+      }
+    });
+    this._onPreviewRequestToken = on(
+      'gui/ignite-screenshots-plugin/preview-request',
+      () => this.takeScreenshot(true),
+    );
+    this._onRecordingToken = on('gui/ignite-screenshots-plugin/recording', this._onRecording);
+    ignite('ignite-play-mode-plugin', 'problems');
+    ignite('ignite-play-mode-plugin', 'status');
   }
 
   componentWillUnmount() {
-    clearInterval(this._periodicPreview);
-    this._periodicPreview = null;
-    ignite('ignite-play-mode-plugin', 'stop');
+    for (const { id } of this.props.storage().sessions) {
+      ignite('ignite-play-mode-plugin', 'stop', id);
+    }
     off(this._onChangeToken);
+    off(this._onStatusToken);
+    off(this._onProblemsToken);
+    off(this._onWatchToken);
+    off(this._onReloadToken);
+    off(this._onPreviewRequestToken);
+    off(this._onRecordingToken);
+    clearInterval(this._recordingInterval);
+    this._recordingInterval = null;
+    if (!!this.props.storage().isRecording) {
+      ignite('ignite-screenshots-plugin', 'recording-stop');
+    }
   }
 
   render() {
-    const { isRunning, isReloading, isBuilding, isPaused } = this.props.storage();
+    const {
+      isReloading,
+      isPaused,
+      isWatching,
+      isRecording,
+      problems,
+      session,
+      sessions,
+      states,
+    } = this.props.storage();
+    const { isRunning, isBuilding, port } = this.currentSession();
     if (!!isReloading) {
       setTimeout(() => {
         this.props.storage().isReloading = false;
@@ -180,6 +405,23 @@ class PlayModeWindow extends React.Component {
     return (
       <div style={style.container}>
         <div style={style.toolbar}>
+          {sessions.length > 1 ? (
+            <Select
+              value={session}
+              onChange={this._onSelectSession}
+              style={style.sessions}
+            >
+              {sessions.map(({ id, group }) => {
+                const flags = states[id] || {};
+                const marker = !!flags.isBuilding ? ' (building)' : (!!flags.isRunning ? ' (running)' : '');
+                return (
+                  <MenuItem key={id} value={id}>
+                    {`${!!group ? `${group}/` : ''}${id}${marker}`}
+                  </MenuItem>
+                );
+              })}
+            </Select>
+          ) : null}
           <Tooltip
             title={
               !!isRunning
@@ -244,7 +486,7 @@ class PlayModeWindow extends React.Component {
               </IconButton>
             </span>
           </Tooltip>
-          <Tooltip title="Build game (Debug)">
+          <Tooltip title="Build game (Debug), hold Shift to force rebuild">
             <span>
               <IconButton
                 color="primary"
@@ -255,7 +497,7 @@ class PlayModeWindow extends React.Component {
               </IconButton>
             </span>
           </Tooltip>
-          <Tooltip title="Ship game (Release)">
+          <Tooltip title="Ship game (Release), hold Shift to force rebuild">
             <span>
               <IconButton
                 color="primary"
@@ -277,6 +519,16 @@ class PlayModeWindow extends React.Component {
               </IconButton>
             </span>
           </Tooltip>
+          <Tooltip title={!isWatching ? 'Rebuild on source changes' : 'Stop watching sources'}>
+            <span>
+              <IconButton
+                color="primary"
+                onClick={this._onToggleWatch}
+              >
+                {!isWatching ? <WatchIcon /> : <UnwatchIcon />}
+              </IconButton>
+            </span>
+          </Tooltip>
           <Tooltip title="Take screenshot">
             <span>
               <IconButton
@@ -288,6 +540,17 @@ class PlayModeWindow extends React.Component {
               </IconButton>
             </span>
           </Tooltip>
+          <Tooltip title={!!isRecording ? 'Stop recording' : 'Record animation'}>
+            <span>
+              <IconButton
+                color={!!isRecording ? 'secondary' : 'primary'}
+                disabled={!isRecording && (!isRunning || isBuilding)}
+                onClick={this._onToggleRecording}
+              >
+                <RecordIcon />
+              </IconButton>
+            </span>
+          </Tooltip>
           <Tooltip title="Go fullscreen">
             <span>
               <IconButton
@@ -303,11 +566,11 @@ class PlayModeWindow extends React.Component {
         {isRunning && !isReloading && !isPaused ? (
           <webview
             style={style.view}
-            src="http://localhost:19100"
-            ref={this._viewRef}
+            src={`http://localhost:${port}`}
+            ref={this._onViewRef}
             onError={this._onStop}
           />
-        ) : (
+        ) : (!isPaused && problems.length > 0 ? this.renderProblems(problems) : (
             <div style={{
               ...style.view,
               ...style.noView,
@@ -324,7 +587,7 @@ class PlayModeWindow extends React.Component {
                   </Tooltip>
                 )}
             </div>
-          )}
+          ))}
       </div>
     );
   }
@@ -0,0 +1,325 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticLevel {
+    Error,
+    Warning,
+    Note,
+    Help,
+}
+
+impl DiagnosticLevel {
+    fn parse(level: &str) -> Option<Self> {
+        match level {
+            "error" | "ERROR" => Some(Self::Error),
+            "warning" | "WARNING" => Some(Self::Warning),
+            "note" => Some(Self::Note),
+            "help" => Some(Self::Help),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub level: DiagnosticLevel,
+    pub message: String,
+    #[serde(default)]
+    pub code: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CargoMessage {
+    reason: String,
+    #[serde(default)]
+    message: Option<CompilerMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompilerMessage {
+    message: String,
+    level: String,
+    #[serde(default)]
+    code: Option<CompilerCode>,
+    #[serde(default)]
+    spans: Vec<CompilerSpan>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompilerCode {
+    code: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompilerSpan {
+    file_name: String,
+    line_start: usize,
+    column_start: usize,
+    is_primary: bool,
+}
+
+/// Extracts diagnostics from build output.
+/// Lines produced by cargo `--message-format=json` are parsed first, everything
+/// else goes through plain rustc and webpack text parsers.
+pub fn parse(output: &str) -> Vec<Diagnostic> {
+    let mut result = vec![];
+    let mut text = vec![];
+    for line in output.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('{') {
+            if let Ok(message) = serde_json::from_str::<CargoMessage>(trimmed) {
+                if let Some(diagnostic) = parse_cargo_message(message) {
+                    push_unique(&mut result, diagnostic);
+                }
+                continue;
+            }
+        }
+        text.push(line);
+    }
+    for diagnostic in parse_text(&text) {
+        push_unique(&mut result, diagnostic);
+    }
+    result
+}
+
+fn push_unique(result: &mut Vec<Diagnostic>, diagnostic: Diagnostic) {
+    if !result.contains(&diagnostic) {
+        result.push(diagnostic);
+    }
+}
+
+fn parse_cargo_message(message: CargoMessage) -> Option<Diagnostic> {
+    if message.reason != "compiler-message" {
+        return None;
+    }
+    let message = message.message?;
+    let level = DiagnosticLevel::parse(&message.level)?;
+    let span = message.spans.into_iter().find(|span| span.is_primary)?;
+    Some(Diagnostic {
+        file: span.file_name,
+        line: span.line_start,
+        column: span.column_start,
+        level,
+        message: message.message,
+        code: message.code.map(|code| code.code),
+    })
+}
+
+fn parse_text(lines: &[&str]) -> Vec<Diagnostic> {
+    let mut result = vec![];
+    for (index, line) in lines.iter().enumerate() {
+        if let Some((level, code, message)) = parse_rustc_header(line) {
+            let location = lines[(index + 1)..]
+                .iter()
+                .take_while(|line| !line.trim().is_empty())
+                .find_map(|line| line.trim_start().strip_prefix("--> "));
+            if let Some((file, line, column)) = location.and_then(parse_location) {
+                result.push(Diagnostic {
+                    file,
+                    line,
+                    column,
+                    level,
+                    message,
+                    code,
+                });
+            }
+        } else if let Some((level, location)) = parse_webpack_header(line) {
+            let message = lines[(index + 1)..]
+                .iter()
+                .map(|line| line.trim())
+                .find(|line| !line.is_empty())
+                .unwrap_or_default()
+                .to_owned();
+            let (file, line, column) = parse_webpack_location(location);
+            result.push(Diagnostic {
+                file,
+                line,
+                column,
+                level,
+                message,
+                code: None,
+            });
+        }
+    }
+    result
+}
+
+/// `error[E0425]: cannot find value `x` in this scope`
+fn parse_rustc_header(line: &str) -> Option<(DiagnosticLevel, Option<String>, String)> {
+    let colon = line.find(": ")?;
+    let (head, message) = (&line[..colon], &line[(colon + 2)..]);
+    let (level, code) = match head.find('[') {
+        Some(bracket) if head.ends_with(']') => (
+            &head[..bracket],
+            Some(head[(bracket + 1)..(head.len() - 1)].to_owned()),
+        ),
+        Some(_) => return None,
+        None => (head, None),
+    };
+    let level = DiagnosticLevel::parse(level)?;
+    Some((level, code, message.trim().to_owned()))
+}
+
+/// `src/lib.rs:10:5`
+fn parse_location(location: &str) -> Option<(String, usize, usize)> {
+    let mut parts = location.trim().rsplitn(3, ':');
+    let column = parts.next()?.parse().ok()?;
+    let line = parts.next()?.parse().ok()?;
+    let file = parts.next()?.to_owned();
+    Some((file, line, column))
+}
+
+/// `ERROR in ./src/index.js 10:5-12`
+fn parse_webpack_header(line: &str) -> Option<(DiagnosticLevel, &str)> {
+    let line = line.trim();
+    let (level, location) = if let Some(location) = line.strip_prefix("ERROR in ") {
+        (DiagnosticLevel::Error, location)
+    } else if let Some(location) = line.strip_prefix("WARNING in ") {
+        (DiagnosticLevel::Warning, location)
+    } else {
+        return None;
+    };
+    Some((level, location.trim()))
+}
+
+fn parse_webpack_location(location: &str) -> (String, usize, usize) {
+    let mut parts = location.splitn(2, ' ');
+    let file = parts.next().unwrap_or_default();
+    let file = file.strip_prefix("./").unwrap_or(file).to_owned();
+    let position = parts
+        .next()
+        .map(|position| position.split('-').next().unwrap_or_default())
+        .and_then(|position| {
+            let mut parts = position.splitn(2, ':');
+            let line = parts.next()?.parse().ok()?;
+            let column = parts.next()?.parse().ok()?;
+            Some((line, column))
+        });
+    let (line, column) = position.unwrap_or((1, 1));
+    (file, line, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CARGO_ERROR: &str = r#"{"reason":"compiler-message","package_id":"game 0.1.0","target":{"name":"game"},"message":{"rendered":"error[E0425]: cannot find value `x` in this scope","message":"cannot find value `x` in this scope","level":"error","code":{"code":"E0425","explanation":null},"spans":[{"file_name":"src/macros.rs","line_start":3,"column_start":1,"is_primary":false},{"file_name":"src/lib.rs","line_start":10,"column_start":5,"is_primary":true}],"children":[]}}"#;
+    const CARGO_WARNING: &str = r#"{"reason":"compiler-message","message":{"message":"unused variable: `y`","level":"warning","code":null,"spans":[{"file_name":"src/game.rs","line_start":2,"column_start":9,"is_primary":true}]}}"#;
+    const CARGO_SUMMARY: &str = r#"{"reason":"compiler-message","message":{"message":"aborting due to previous error","level":"error","code":null,"spans":[]}}"#;
+    const CARGO_ARTIFACT: &str =
+        r#"{"reason":"compiler-artifact","package_id":"game 0.1.0","filenames":[]}"#;
+
+    #[test]
+    fn test_cargo_json() {
+        let output = [CARGO_ARTIFACT, CARGO_WARNING, CARGO_ERROR, CARGO_SUMMARY].join("\n");
+        assert_eq!(
+            parse(&output),
+            vec![
+                Diagnostic {
+                    file: "src/game.rs".to_owned(),
+                    line: 2,
+                    column: 9,
+                    level: DiagnosticLevel::Warning,
+                    message: "unused variable: `y`".to_owned(),
+                    code: None,
+                },
+                Diagnostic {
+                    file: "src/lib.rs".to_owned(),
+                    line: 10,
+                    column: 5,
+                    level: DiagnosticLevel::Error,
+                    message: "cannot find value `x` in this scope".to_owned(),
+                    code: Some("E0425".to_owned()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rustc_text() {
+        let output = "\
+   Compiling game v0.1.0
+warning: unused variable: `y`
+ --> src/game.rs:2:9
+  |
+2 |     let y = 1;
+  |         ^ help: prefix it with an underscore: `_y`
+
+error[E0425]: cannot find value `x` in this scope
+  --> src/lib.rs:10:5
+   |
+10 |     x
+   |     ^ not found in this scope
+
+error: aborting due to previous error
+";
+        let result = parse(output);
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].level, DiagnosticLevel::Warning);
+        assert_eq!(
+            (result[0].file.as_str(), result[0].line, result[0].column),
+            ("src/game.rs", 2, 9)
+        );
+        assert_eq!(result[0].code, None);
+        assert_eq!(result[1].level, DiagnosticLevel::Error);
+        assert_eq!(result[1].code.as_deref(), Some("E0425"));
+        assert_eq!(
+            (result[1].file.as_str(), result[1].line, result[1].column),
+            ("src/lib.rs", 10, 5)
+        );
+    }
+
+    #[test]
+    fn test_webpack_text() {
+        let output = "\
+ERROR in ./src/index.js 10:5-12
+Module not found: Error: Can't resolve './missing'
+
+WARNING in ./src/app.js
+export 'foo' was not found in './bar'
+";
+        let result = parse(output);
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].level, DiagnosticLevel::Error);
+        assert_eq!(
+            (result[0].file.as_str(), result[0].line, result[0].column),
+            ("src/index.js", 10, 5)
+        );
+        assert_eq!(
+            result[0].message,
+            "Module not found: Error: Can't resolve './missing'"
+        );
+        assert_eq!(result[1].level, DiagnosticLevel::Warning);
+        assert_eq!(
+            (result[1].file.as_str(), result[1].line, result[1].column),
+            ("src/app.js", 1, 1)
+        );
+    }
+
+    #[test]
+    fn test_duplicates_are_merged() {
+        let output = [CARGO_ERROR, CARGO_ERROR].join("\n");
+        assert_eq!(parse(&output).len(), 1);
+    }
+
+    #[test]
+    fn test_malformed_lines() {
+        let output = "\
+{\"reason\":\"compiler-message\",\"message\":
+{not json at all}
+{\"reason\":\"compiler-message\",\"message\":{\"message\":\"m\",\"level\":\"ice\",\"spans\":[]}}
+error[E0425 missing bracket: message
+ --> src/lib.rs:1:1
+error: location without numbers
+ --> src/lib.rs:x:y
+warning: no location follows
+
+ERROR in
+";
+        assert!(parse(output).is_empty());
+    }
+}
@@ -0,0 +1,100 @@
+use ignite_plugin_utils::{console, file_system::read_string, paths::relative_to_project};
+use std::collections::{BTreeMap, HashMap};
+use wasm_bindgen::prelude::*;
+
+/// Merges environment variables from plugin meta with the ones from local overrides
+/// file, so values that should not be shared (API keys, tokens) can live in a
+/// git-ignored file next to the project.
+pub fn resolve(
+    env: &BTreeMap<String, String>,
+    env_file: Option<&str>,
+) -> Result<HashMap<String, String>, JsValue> {
+    let mut result = env
+        .iter()
+        .map(|(key, value)| (key.to_owned(), value.to_owned()))
+        .collect::<HashMap<_, _>>();
+    if let Some(path) = env_file {
+        match read_string(&relative_to_project(path)?, true) {
+            Ok(contents) => result.extend(parse(&contents)),
+            Err(_) => console::warn(&format!("Could not read environment file: {}", path)),
+        }
+    }
+    Ok(result)
+}
+
+/// Parses `.env` style contents: `KEY=VALUE` lines, `#` comments and optional quotes.
+fn parse(contents: &str) -> Vec<(String, String)> {
+    contents
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next()?.trim();
+            let value = parts.next()?.trim();
+            if key.is_empty() {
+                return None;
+            }
+            let value = if value.len() >= 2
+                && ((value.starts_with('"') && value.ends_with('"'))
+                    || (value.starts_with('\'') && value.ends_with('\'')))
+            {
+                &value[1..(value.len() - 1)]
+            } else {
+                value
+            };
+            Some((key.to_owned(), value.to_owned()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(items: &[(&str, &str)]) -> Vec<(String, String)> {
+        items
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_values() {
+        assert_eq!(
+            parse("A=1\n  B = two words  \nC=\nD=a=b"),
+            pairs(&[("A", "1"), ("B", "two words"), ("C", ""), ("D", "a=b")])
+        );
+    }
+
+    #[test]
+    fn test_parse_quotes() {
+        assert_eq!(
+            parse("A=\"quoted # value\"\nB='single'\nC=\"\"\nD=\"unterminated\nE='\"mixed'"),
+            pairs(&[
+                ("A", "quoted # value"),
+                ("B", "single"),
+                ("C", ""),
+                ("D", "\"unterminated"),
+                ("E", "\"mixed"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_comments_and_export() {
+        assert_eq!(
+            parse("# comment\n\n  # indented comment\nexport TOKEN=secret\r\nKEY=value"),
+            pairs(&[("TOKEN", "secret"), ("KEY", "value")])
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_lines() {
+        assert_eq!(
+            parse("no separator\n=value\n  = spaced\nVALID=1"),
+            pairs(&[("VALID", "1")])
+        );
+    }
+}
@@ -0,0 +1,346 @@
+use glob::{MatchOptions, Pattern};
+use ignite_plugin_utils::file_system::{read_buffer, scan_dir, stat, stat_supported, FileStat};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::RwLock,
+};
+use wasm_bindgen::prelude::*;
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Directories skipped while walking, they only hold build outputs and dependencies.
+const IGNORED_DIRS: &[&str] = &["target", "node_modules", ".git"];
+
+lazy_static! {
+    /// Last known stats and content hashes, keyed by absolute path.
+    static ref HASHES: RwLock<HashMap<String, (FileStat, u64)>> = RwLock::new(Default::default());
+}
+
+/// Content hashes of matched files, keyed by project relative path.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fingerprint(pub BTreeMap<String, u64>);
+
+impl Fingerprint {
+    pub fn scan(root: &str, patterns: &[String]) -> Result<Self, JsValue> {
+        let globs = patterns
+            .iter()
+            .map(|pattern| match Pattern::new(pattern) {
+                Ok(pattern) => Ok(pattern),
+                Err(error) => Err(format!("{:?}", error).into()),
+            })
+            .collect::<Result<Vec<Pattern>, JsValue>>()?;
+        let mut files = vec![];
+        for scan_root in scan_roots(patterns) {
+            match scan_root {
+                ScanRoot::File(path) => files.push(path),
+                ScanRoot::Dir { path, recursive } => walk(root, &path, recursive, &mut files),
+            }
+        }
+        let mut result = BTreeMap::new();
+        for relative in files {
+            if result.contains_key(&relative)
+                || !globs
+                    .iter()
+                    .any(|glob| glob.matches_with(&relative, MATCH_OPTIONS))
+            {
+                continue;
+            }
+            if let Some(hash) = file_hash(&format!("{}/{}", root, relative)) {
+                result.insert(relative, hash);
+            }
+        }
+        Ok(Self(result))
+    }
+
+    /// Paths that were added, removed or modified since `other`.
+    pub fn changes<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = &'a str> {
+        let modified = self
+            .0
+            .iter()
+            .filter(move |(path, hash)| other.0.get(*path) != Some(hash))
+            .map(|(path, _)| path.as_str());
+        let removed = other
+            .0
+            .keys()
+            .filter(move |path| !self.0.contains_key(*path))
+            .map(|path| path.as_str());
+        modified.chain(removed)
+    }
+}
+
+pub fn matches_any(path: &str, patterns: &[String]) -> bool {
+    patterns.iter().any(|pattern| match Pattern::new(pattern) {
+        Ok(pattern) => pattern.matches_with(path, MATCH_OPTIONS),
+        Err(_) => false,
+    })
+}
+
+/// Where files matching pattern can be found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ScanRoot {
+    /// Pattern without wildcards names single file.
+    File(String),
+    /// Directory prefix of pattern, walked into subdirectories only when needed.
+    Dir { path: String, recursive: bool },
+}
+
+fn scan_roots(patterns: &[String]) -> Vec<ScanRoot> {
+    let mut result = vec![];
+    for pattern in patterns {
+        let scan_root = if is_literal(pattern) {
+            ScanRoot::File(pattern.to_owned())
+        } else {
+            let path = base_dir(pattern);
+            let rest = pattern[path.len()..].trim_start_matches('/');
+            ScanRoot::Dir {
+                recursive: rest.contains('/') || rest.contains("**"),
+                path,
+            }
+        };
+        if !result.contains(&scan_root) {
+            result.push(scan_root);
+        }
+    }
+    result
+}
+
+fn is_literal(pattern: &str) -> bool {
+    !pattern.contains(['*', '?', '['])
+}
+
+fn is_ignored(name: &str) -> bool {
+    IGNORED_DIRS.contains(&name)
+}
+
+/// Collects project relative paths of files in directory, skipping ignored ones.
+fn walk(root: &str, relative: &str, recursive: bool, result: &mut Vec<String>) {
+    let path = if relative.is_empty() {
+        root.to_owned()
+    } else {
+        format!("{}/{}", root, relative)
+    };
+    let entries = match scan_dir(&path, false, true) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for (name, file_path, is_directory) in entries {
+        let file_path = relative_path(root, &file_path);
+        if !is_directory {
+            result.push(file_path);
+        } else if recursive && !is_ignored(&name) {
+            walk(root, &file_path, recursive, result);
+        }
+    }
+}
+
+/// Content hash of file, reused while its size and modification time stay the same.
+/// Editors that do not expose file stats get file contents hashed every time.
+fn file_hash(path: &str) -> Option<u64> {
+    let file_stat = if stat_supported() {
+        stat(path, true).ok()
+    } else {
+        None
+    };
+    cached_hash(path, file_stat, || read_buffer(path, true).ok())
+}
+
+fn cached_hash<F>(path: &str, file_stat: Option<FileStat>, read: F) -> Option<u64>
+where
+    F: FnOnce() -> Option<Vec<u8>>,
+{
+    if let (Some(file_stat), Ok(hashes)) = (file_stat, HASHES.read()) {
+        if let Some((cached, hash)) = hashes.get(path) {
+            if *cached == file_stat {
+                return Some(*hash);
+            }
+        }
+    }
+    let hash = hash(&read()?);
+    if let (Some(file_stat), Ok(mut hashes)) = (file_stat, HASHES.write()) {
+        hashes.insert(path.to_owned(), (file_stat, hash));
+    }
+    Some(hash)
+}
+
+/// FNV-1a, stable across runs so fingerprints can be stored on disk.
+fn hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Leading pattern directories without any wildcards, e.g. `src` for `src/**/*.rs`.
+fn base_dir(pattern: &str) -> String {
+    let mut parts = pattern
+        .split('/')
+        .take_while(|part| is_literal(part))
+        .collect::<Vec<_>>();
+    if parts.len() == pattern.split('/').count() {
+        parts.pop();
+    }
+    parts.join("/")
+}
+
+pub fn relative_path(root: &str, path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let root = root.replace('\\', "/");
+    let path = path.strip_prefix(root.as_str()).unwrap_or(&path);
+    path.trim_start_matches('/').to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(items: &[&str]) -> Vec<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    #[test]
+    fn test_base_dir() {
+        assert_eq!(base_dir("src/**/*.rs"), "src");
+        assert_eq!(base_dir("static/assets/**"), "static/assets");
+        assert_eq!(base_dir("static/*/images/*.png"), "static");
+        assert_eq!(base_dir("**/*.rs"), "");
+        assert_eq!(base_dir("*.toml"), "");
+        assert_eq!(base_dir("Cargo.toml"), "");
+        assert_eq!(base_dir("src/main.rs"), "src");
+    }
+
+    #[test]
+    fn test_scan_roots() {
+        let roots = scan_roots(&patterns(&[
+            "Cargo.toml",
+            "src/**/*.rs",
+            "src/**/*",
+            "*.json",
+            "static/assets/**",
+            "Cargo.toml",
+        ]));
+        assert_eq!(
+            roots,
+            vec![
+                ScanRoot::File("Cargo.toml".to_owned()),
+                ScanRoot::Dir {
+                    path: "src".to_owned(),
+                    recursive: true,
+                },
+                ScanRoot::Dir {
+                    path: "".to_owned(),
+                    recursive: false,
+                },
+                ScanRoot::Dir {
+                    path: "static/assets".to_owned(),
+                    recursive: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ignored_dirs() {
+        assert!(is_ignored("target"));
+        assert!(is_ignored("node_modules"));
+        assert!(!is_ignored("src"));
+        assert!(!is_ignored("targets"));
+    }
+
+    #[test]
+    fn test_matches_any() {
+        let sources = patterns(&["src/**/*.rs", "Cargo.toml"]);
+        assert!(matches_any("src/lib.rs", &sources));
+        assert!(matches_any("src/game/player.rs", &sources));
+        assert!(matches_any("Cargo.toml", &sources));
+        assert!(!matches_any("src/lib.js", &sources));
+        assert!(!matches_any("crates/Cargo.toml", &sources));
+        let assets = patterns(&["static/assets/**", "*.json"]);
+        assert!(matches_any("static/assets/images/logo.png", &assets));
+        assert!(matches_any("pipeline.json", &assets));
+        assert!(!matches_any("static/index.html", &assets));
+        assert!(!matches_any("config/pipeline.json", &assets));
+    }
+
+    #[test]
+    fn test_relative_path() {
+        assert_eq!(
+            relative_path("/project", "/project/src/lib.rs"),
+            "src/lib.rs"
+        );
+        assert_eq!(
+            relative_path("C:\\project", "C:\\project\\src\\lib.rs"),
+            "src/lib.rs"
+        );
+    }
+
+    #[test]
+    fn test_changes() {
+        let before = Fingerprint(
+            vec![("a".to_owned(), 1), ("b".to_owned(), 2)]
+                .into_iter()
+                .collect(),
+        );
+        let after = Fingerprint(
+            vec![
+                ("a".to_owned(), 1),
+                ("b".to_owned(), 3),
+                ("c".to_owned(), 4),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        assert_eq!(after.changes(&before).collect::<Vec<_>>(), vec!["b", "c"]);
+        assert_eq!(before.changes(&after).collect::<Vec<_>>(), vec!["b", "c"]);
+        assert_eq!(before.changes(&before).count(), 0);
+    }
+
+    #[test]
+    fn test_hash_is_stable() {
+        assert_eq!(hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_ne!(hash(b"a"), hash(b"b"));
+    }
+
+    #[test]
+    fn test_cached_hash() {
+        let path = "/project/test_cached_hash.rs";
+        let file_stat = FileStat {
+            size: 1,
+            modified: 10.0,
+        };
+        assert_eq!(
+            cached_hash(path, Some(file_stat), || Some(b"a".to_vec())),
+            Some(hash(b"a"))
+        );
+        assert_eq!(
+            cached_hash(path, Some(file_stat), || panic!("file read again")),
+            Some(hash(b"a"))
+        );
+        let modified = FileStat {
+            size: 1,
+            modified: 20.0,
+        };
+        assert_eq!(
+            cached_hash(path, Some(modified), || Some(b"b".to_vec())),
+            Some(hash(b"b"))
+        );
+        assert_eq!(cached_hash(path, Some(modified), || None), Some(hash(b"b")));
+    }
+
+    #[test]
+    fn test_hash_without_stats() {
+        let path = "/project/test_hash_without_stats.rs";
+        assert_eq!(
+            cached_hash(path, None, || Some(b"a".to_vec())),
+            Some(hash(b"a"))
+        );
+        assert_eq!(
+            cached_hash(path, None, || Some(b"b".to_vec())),
+            Some(hash(b"b"))
+        );
+        assert_eq!(cached_hash(path, None, || None), None);
+    }
+}
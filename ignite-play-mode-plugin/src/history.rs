@@ -0,0 +1,404 @@
+use crate::{fingerprint::relative_path, Profile};
+use ignite_plugin_utils::file_system::{file_size, read_string, scan_dir, write_string};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use wasm_bindgen::prelude::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetaHistory {
+    /// Project relative path to the history file.
+    #[serde(default = "MetaHistory::default_path")]
+    pub path: String,
+    /// Number of builds kept in the history file.
+    #[serde(default = "MetaHistory::default_limit")]
+    pub limit: usize,
+    /// Size growth in percents that is reported as regression.
+    #[serde(default = "MetaHistory::default_threshold")]
+    pub threshold: f64,
+}
+
+impl Default for MetaHistory {
+    fn default() -> Self {
+        Self {
+            path: Self::default_path(),
+            limit: Self::default_limit(),
+            threshold: Self::default_threshold(),
+        }
+    }
+}
+
+impl MetaHistory {
+    fn default_path() -> String {
+        "play-mode-history.json".to_owned()
+    }
+
+    fn default_limit() -> usize {
+        100
+    }
+
+    fn default_threshold() -> f64 {
+        5.0
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BuildOutcome {
+    Succeeded,
+    Failed,
+    Cancelled,
+    /// Editor did not report exit status of build node and build reported no errors.
+    /// Counts as success, kept apart only to show that exit status is not known.
+    Unknown,
+}
+
+impl BuildOutcome {
+    pub fn new(cancelled: bool, exit_code: Option<i32>, has_errors: bool) -> Self {
+        if cancelled {
+            Self::Cancelled
+        } else if has_errors {
+            Self::Failed
+        } else {
+            match exit_code {
+                Some(0) => Self::Succeeded,
+                Some(_) => Self::Failed,
+                None => Self::Unknown,
+            }
+        }
+    }
+
+    pub fn is_success(self) -> bool {
+        matches!(self, Self::Succeeded | Self::Unknown)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildRecord {
+    pub session: String,
+    pub profile: Profile,
+    /// Milliseconds since UNIX epoch.
+    pub started: f64,
+    pub ended: f64,
+    pub outcome: BuildOutcome,
+    /// Sizes in bytes of files in `dist` directory, keyed by path relative to it.
+    #[serde(default)]
+    pub files: BTreeMap<String, u64>,
+}
+
+impl BuildRecord {
+    pub fn total_size(&self) -> u64 {
+        self.files.values().sum()
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct History {
+    pub builds: Vec<BuildRecord>,
+}
+
+impl History {
+    pub fn load(path: &str) -> Self {
+        read_string(path, true)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), JsValue> {
+        match serde_json::to_string_pretty(self) {
+            Ok(contents) => write_string(path, &contents, true),
+            Err(error) => Err(format!("{:?}", error).into()),
+        }
+    }
+
+    pub fn push(&mut self, record: BuildRecord, limit: usize) {
+        self.builds.push(record);
+        if self.builds.len() > limit {
+            let count = self.builds.len() - limit;
+            self.builds.drain(0..count);
+        }
+    }
+
+    /// Compares latest successful build of each session profile against the one before it.
+    pub fn report(&self, threshold: f64) -> Vec<ProfileReport> {
+        let mut keys = vec![];
+        for build in &self.builds {
+            let key = (build.session.as_str(), build.profile);
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        keys.into_iter()
+            .filter_map(|(session, profile)| self.profile_report(session, profile, threshold))
+            .collect()
+    }
+
+    fn profile_report(
+        &self,
+        session: &str,
+        profile: Profile,
+        threshold: f64,
+    ) -> Option<ProfileReport> {
+        let builds = self
+            .builds
+            .iter()
+            .filter(|build| {
+                build.session == session && build.profile == profile && build.outcome.is_success()
+            })
+            .collect::<Vec<_>>();
+        let current = *builds.last()?;
+        let previous = if builds.len() > 1 {
+            Some(builds[builds.len() - 2])
+        } else {
+            None
+        };
+        let trend = builds
+            .iter()
+            .map(|build| TrendPoint {
+                started: build.started,
+                duration: build.ended - build.started,
+                total_size: build.total_size(),
+            })
+            .collect();
+        let mut deltas = current
+            .files
+            .iter()
+            .map(|(path, size)| {
+                let before = previous.and_then(|previous| previous.files.get(path).copied());
+                FileDelta::new(path, before, Some(*size), threshold)
+            })
+            .collect::<Vec<_>>();
+        if let Some(previous) = previous {
+            deltas.extend(
+                previous
+                    .files
+                    .iter()
+                    .filter(|(path, _)| !current.files.contains_key(*path))
+                    .map(|(path, size)| FileDelta::new(path, Some(*size), None, threshold)),
+            );
+        }
+        let total = FileDelta::new(
+            "",
+            previous.map(|previous| previous.total_size()),
+            Some(current.total_size()),
+            threshold,
+        );
+        Some(ProfileReport {
+            session: session.to_owned(),
+            profile,
+            builds: builds.len(),
+            trend,
+            total_delta: total.delta,
+            regression: total.regression || deltas.iter().any(|delta| delta.regression),
+            deltas,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileReport {
+    pub session: String,
+    pub profile: Profile,
+    pub builds: usize,
+    pub trend: Vec<TrendPoint>,
+    pub total_delta: i64,
+    pub regression: bool,
+    pub deltas: Vec<FileDelta>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrendPoint {
+    pub started: f64,
+    pub duration: f64,
+    pub total_size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileDelta {
+    pub path: String,
+    pub previous: Option<u64>,
+    pub current: Option<u64>,
+    pub delta: i64,
+    pub regression: bool,
+}
+
+impl FileDelta {
+    fn new(path: &str, previous: Option<u64>, current: Option<u64>, threshold: f64) -> Self {
+        let delta = current.unwrap_or(0) as i64 - previous.unwrap_or(0) as i64;
+        let regression = match previous {
+            Some(previous) if previous > 0 => delta as f64 * 100.0 / previous as f64 > threshold,
+            _ => false,
+        };
+        Self {
+            path: path.to_owned(),
+            previous,
+            current,
+            delta,
+            regression,
+        }
+    }
+}
+
+/// Sizes of all files in given directory, keyed by path relative to it.
+/// Editors that do not expose file stats get file contents read to measure them.
+pub fn file_sizes(directory: &str) -> Result<BTreeMap<String, u64>, JsValue> {
+    let mut result = BTreeMap::new();
+    for (_, path, is_directory) in scan_dir(directory, true, true)? {
+        if is_directory {
+            continue;
+        }
+        let relative = relative_path(directory, &path);
+        let path = format!("{}/{}", directory, relative);
+        result.insert(relative, file_size(&path, true)?);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_outcome() {
+        assert_eq!(
+            BuildOutcome::new(false, Some(0), false),
+            BuildOutcome::Succeeded
+        );
+        assert_eq!(
+            BuildOutcome::new(false, Some(101), false),
+            BuildOutcome::Failed
+        );
+        assert_eq!(
+            BuildOutcome::new(false, Some(0), true),
+            BuildOutcome::Failed
+        );
+        assert_eq!(BuildOutcome::new(false, None, true), BuildOutcome::Failed);
+        assert_eq!(BuildOutcome::new(false, None, false), BuildOutcome::Unknown);
+        assert_eq!(
+            BuildOutcome::new(true, Some(0), false),
+            BuildOutcome::Cancelled
+        );
+        assert_eq!(BuildOutcome::new(true, None, true), BuildOutcome::Cancelled);
+        assert!(BuildOutcome::Succeeded.is_success());
+        assert!(BuildOutcome::Unknown.is_success());
+        assert!(!BuildOutcome::Failed.is_success());
+        assert!(!BuildOutcome::Cancelled.is_success());
+    }
+
+    fn record(session: &str, profile: Profile, started: f64, files: &[(&str, u64)]) -> BuildRecord {
+        BuildRecord {
+            session: session.to_owned(),
+            profile,
+            started,
+            ended: started + 10.0,
+            outcome: BuildOutcome::Succeeded,
+            files: files
+                .iter()
+                .map(|(path, size)| (path.to_string(), *size))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_push_limit() {
+        let mut history = History::default();
+        for index in 0..5 {
+            history.push(record("a", Profile::Debug, index as f64, &[]), 3);
+        }
+        assert_eq!(history.builds.len(), 3);
+        assert_eq!(history.builds[0].started, 2.0);
+        assert_eq!(history.builds[2].started, 4.0);
+    }
+
+    #[test]
+    fn test_file_delta() {
+        let delta = FileDelta::new("a.wasm", Some(100), Some(106), 5.0);
+        assert_eq!(delta.delta, 6);
+        assert!(delta.regression);
+        let delta = FileDelta::new("a.wasm", Some(100), Some(105), 5.0);
+        assert!(!delta.regression);
+        let delta = FileDelta::new("a.wasm", Some(100), Some(50), 5.0);
+        assert_eq!(delta.delta, -50);
+        assert!(!delta.regression);
+        let delta = FileDelta::new("a.wasm", None, Some(50), 5.0);
+        assert_eq!(delta.delta, 50);
+        assert!(!delta.regression);
+        let delta = FileDelta::new("a.wasm", Some(50), None, 5.0);
+        assert_eq!(delta.delta, -50);
+        assert!(!delta.regression);
+    }
+
+    #[test]
+    fn test_report_interleaved_sessions() {
+        let mut history = History::default();
+        history.push(record("a", Profile::Debug, 0.0, &[("a.wasm", 100)]), 10);
+        history.push(record("b", Profile::Debug, 1.0, &[("b.wasm", 1000)]), 10);
+        history.push(record("a", Profile::Debug, 2.0, &[("a.wasm", 200)]), 10);
+        let mut failed = record("b", Profile::Debug, 3.0, &[("b.wasm", 5000)]);
+        failed.outcome = BuildOutcome::Failed;
+        history.push(failed, 10);
+        history.push(record("b", Profile::Release, 4.0, &[("b.wasm", 500)]), 10);
+        history.push(record("b", Profile::Debug, 5.0, &[("b.wasm", 1010)]), 10);
+        let report = history.report(5.0);
+        assert_eq!(report.len(), 3);
+
+        assert_eq!(report[0].session, "a");
+        assert_eq!(report[0].profile, Profile::Debug);
+        assert_eq!(report[0].builds, 2);
+        assert_eq!(report[0].total_delta, 100);
+        assert!(report[0].regression);
+
+        assert_eq!(report[1].session, "b");
+        assert_eq!(report[1].profile, Profile::Debug);
+        assert_eq!(report[1].builds, 2);
+        assert_eq!(report[1].total_delta, 10);
+        assert!(!report[1].regression);
+        assert_eq!(report[1].deltas.len(), 1);
+        assert_eq!(report[1].deltas[0].path, "b.wasm");
+
+        assert_eq!(report[2].session, "b");
+        assert_eq!(report[2].profile, Profile::Release);
+        assert_eq!(report[2].builds, 1);
+        assert_eq!(report[2].total_delta, 500);
+        assert!(!report[2].regression);
+    }
+
+    #[test]
+    fn test_report_removed_files() {
+        let mut history = History::default();
+        history.push(
+            record("a", Profile::Debug, 0.0, &[("a.wasm", 100), ("a.js", 10)]),
+            10,
+        );
+        history.push(record("a", Profile::Debug, 1.0, &[("a.wasm", 100)]), 10);
+        let report = history.report(5.0);
+        assert_eq!(report.len(), 1);
+        let removed = report[0]
+            .deltas
+            .iter()
+            .find(|delta| delta.path == "a.js")
+            .unwrap();
+        assert_eq!(removed.current, None);
+        assert_eq!(removed.delta, -10);
+        assert_eq!(report[0].total_delta, -10);
+    }
+
+    #[test]
+    fn test_report_without_exit_code() {
+        let mut history = History::default();
+        let mut first = record("a", Profile::Debug, 0.0, &[("a.wasm", 100)]);
+        first.outcome = BuildOutcome::Unknown;
+        history.push(first, 10);
+        let mut second = record("a", Profile::Debug, 1.0, &[("a.wasm", 120)]);
+        second.outcome = BuildOutcome::Unknown;
+        history.push(second, 10);
+        let report = history.report(5.0);
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].builds, 2);
+        assert_eq!(report[0].trend.len(), 2);
+        assert_eq!(report[0].total_delta, 20);
+        assert!(report[0].regression);
+        assert_eq!(report[0].deltas[0].previous, Some(100));
+        assert_eq!(report[0].deltas[0].current, Some(120));
+    }
+}
@@ -0,0 +1,91 @@
+use crate::{fingerprint::Fingerprint, Profile};
+use ignite_plugin_utils::file_system::{read_string, write_string};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use wasm_bindgen::prelude::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetaIncremental {
+    #[serde(default = "MetaIncremental::default_enabled")]
+    pub enabled: bool,
+    /// Files that affect build result, build is skipped when none of them changed.
+    #[serde(default = "MetaIncremental::default_inputs")]
+    pub inputs: Vec<String>,
+    /// Project relative path to the file with fingerprints of last successful builds.
+    #[serde(default = "MetaIncremental::default_path")]
+    pub path: String,
+}
+
+impl Default for MetaIncremental {
+    fn default() -> Self {
+        Self {
+            enabled: Self::default_enabled(),
+            inputs: Self::default_inputs(),
+            path: Self::default_path(),
+        }
+    }
+}
+
+impl MetaIncremental {
+    fn default_enabled() -> bool {
+        false
+    }
+
+    fn default_inputs() -> Vec<String> {
+        vec![
+            "src/**/*".to_owned(),
+            "Cargo.toml".to_owned(),
+            "pipeline.json".to_owned(),
+            "static/**/*".to_owned(),
+        ]
+    }
+
+    fn default_path() -> String {
+        "play-mode-fingerprints.json".to_owned()
+    }
+}
+
+/// Input fingerprints of last successful builds, keyed by session and profile.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Fingerprints(pub BTreeMap<String, Fingerprint>);
+
+impl Fingerprints {
+    pub fn load(path: &str) -> Self {
+        read_string(path, true)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), JsValue> {
+        match serde_json::to_string(self) {
+            Ok(contents) => write_string(path, &contents, true),
+            Err(error) => Err(format!("{:?}", error).into()),
+        }
+    }
+
+    pub fn is_up_to_date(
+        &self,
+        session: &str,
+        profile: Profile,
+        fingerprint: &Fingerprint,
+    ) -> bool {
+        self.0.get(&Self::key(session, profile)) == Some(fingerprint)
+    }
+
+    pub fn insert(&mut self, session: &str, profile: Profile, fingerprint: Fingerprint) {
+        self.0.insert(Self::key(session, profile), fingerprint);
+    }
+
+    /// Returns true if there was fingerprint stored for session and profile.
+    pub fn remove(&mut self, session: &str, profile: Profile) -> bool {
+        self.0.remove(&Self::key(session, profile)).is_some()
+    }
+
+    fn key(session: &str, profile: Profile) -> String {
+        match profile {
+            Profile::Debug => format!("{}/debug", session),
+            Profile::Release => format!("{}/release", session),
+        }
+    }
+}
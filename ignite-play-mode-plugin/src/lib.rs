@@ -1,22 +1,74 @@
 #[macro_use]
 extern crate lazy_static;
 
-use ignite_plugin_utils::editor::{
-    emit, get_plugin_meta, ignite, run_node, run_server, terminate_node, terminate_server,
+mod diagnostics;
+mod env;
+mod fingerprint;
+mod history;
+mod incremental;
+mod logs;
+mod proxy;
+mod session;
+mod watch;
+
+use crate::{
+    diagnostics::{Diagnostic, DiagnosticLevel},
+    fingerprint::Fingerprint,
+    history::{BuildOutcome, BuildRecord, History, MetaHistory},
+    incremental::{Fingerprints, MetaIncremental},
+    logs::{LogEntry, LogFilter, LogLevel, LogMessage, LogSource, Logs, MetaLogs},
+    proxy::{Auxiliary, MetaProxy},
+    session::{SessionEvent, SessionState},
+    watch::{MetaWatch, Watch},
+};
+use ignite_plugin_utils::{
+    editor::{
+        emit, get_plugin_meta, ignite, ignite_delayed, run_node_with_env, run_server,
+        terminate_node, terminate_server,
+    },
+    file_system::{read_string, scan_dir, write_string},
+    paths::{is_absolute, project_path, relative_to_project},
 };
 use serde::{Deserialize, Serialize};
-use std::sync::RwLock;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        RwLock,
+    },
+};
 use wasm_bindgen::prelude::*;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct State {
+    pub id: String,
     #[serde(rename(serialize = "isRunning", deserialize = "isRunning"))]
     pub is_running: bool,
     #[serde(rename(serialize = "isBuilding", deserialize = "isBuilding"))]
     pub is_building: bool,
+    pub session: SessionState,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl State {
+    fn new(id: &str, session: &SessionState) -> Self {
+        Self {
+            id: id.to_owned(),
+            is_running: session.is_running() && !session.is_stopping(),
+            is_building: session.is_building(),
+            session: session.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SessionStatus {
+    pub id: String,
+    pub group: Option<String>,
+    pub port: usize,
+    pub state: SessionState,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
 struct Meta {
     #[serde(default)]
     pub dist: Option<String>,
@@ -25,189 +77,1015 @@ struct Meta {
     #[serde(default)]
     pub build_release: Option<MetaBuild>,
     #[serde(default)]
+    pub proxy_debug: Option<MetaProxy>,
+    #[serde(default)]
+    pub proxy_release: Option<MetaProxy>,
+    #[serde(default)]
     pub log_level: u8,
     #[serde(default)]
     pub blocking_task: bool,
+    /// Project relative path to the file that build node writes its output to, e.g. with
+    /// `cargo build --message-format=json 2>&1 | tee build.log`. Editor reports node
+    /// termination with bare token, so this file is where build logs and problems come
+    /// from. It gets cleared when build starts.
+    #[serde(default)]
+    pub diagnostics_log: Option<String>,
+    #[serde(default)]
+    pub watch: Option<MetaWatch>,
+    #[serde(default)]
+    pub sessions: Vec<MetaSession>,
+    #[serde(default)]
+    pub history: MetaHistory,
+    #[serde(default)]
+    pub logs: MetaLogs,
+    #[serde(default)]
+    pub incremental: MetaIncremental,
+}
+
+impl Meta {
+    /// Configured sessions, with implicit default one serving `dist` directory.
+    fn sessions(&self) -> Vec<MetaSession> {
+        let mut result = self.sessions.clone();
+        if !result.iter().any(|session| session.id == DEFAULT_SESSION) {
+            result.insert(
+                0,
+                MetaSession {
+                    id: DEFAULT_SESSION.to_owned(),
+                    group: None,
+                    port: DEFAULT_PORT,
+                    dist: None,
+                    process: None,
+                    build_debug: None,
+                    build_release: None,
+                },
+            );
+        }
+        result
+    }
+
+    fn session(&self, id: &str) -> Option<MetaSession> {
+        self.sessions().into_iter().find(|session| session.id == id)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MetaSession {
+    pub id: String,
+    #[serde(default)]
+    pub group: Option<String>,
+    #[serde(default = "MetaSession::default_port")]
+    pub port: usize,
+    /// Directory served by this session, defaults to `dist`.
+    #[serde(default)]
+    pub dist: Option<String>,
+    /// Node that runs this session (e.g. headless game server) instead of serving files.
+    #[serde(default)]
+    pub process: Option<MetaBuild>,
+    #[serde(default)]
+    pub build_debug: Option<MetaBuild>,
+    #[serde(default)]
+    pub build_release: Option<MetaBuild>,
+}
+
+impl MetaSession {
+    fn default_port() -> usize {
+        DEFAULT_PORT
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PayloadBuildHistory {
+    pub builds: Vec<BuildRecord>,
+    pub report: Vec<history::ProfileReport>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PayloadLogsExport {
+    pub path: String,
+    #[serde(default)]
+    pub filter: LogFilter,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PayloadSessionProfile {
+    pub session: String,
+    pub profile: Profile,
+}
+
+#[derive(Debug, Clone)]
+struct PendingBuild {
+    pub session: String,
+    pub profile: Profile,
+    pub started: f64,
+    pub cancelled: bool,
+    /// Inputs at the time build started, stored when it succeeds.
+    pub fingerprint: Option<Fingerprint>,
+}
+
+/// Payload of `node-terminated`, bare node token as sent by current editor, or token
+/// with exit code and captured output.
+#[derive(Debug, Serialize, Deserialize)]
+struct PayloadNodeTerminated {
+    pub token: String,
+    #[serde(default)]
+    pub code: Option<i32>,
+    /// Combined stdout and stderr of node process.
+    #[serde(default)]
+    pub output: Option<String>,
+}
+
+impl PayloadNodeTerminated {
+    fn new(data: JsValue) -> Option<Self> {
+        match data.as_string() {
+            Some(token) => Some(Self {
+                token,
+                code: None,
+                output: None,
+            }),
+            None => data.into_serde().ok(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+struct PayloadOpenFile {
+    pub path: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Sessions addressed by query, either single one or all in the group.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Target {
+    #[serde(default)]
+    pub session: Option<String>,
+    #[serde(default)]
+    pub group: Option<String>,
+    /// Builds even if inputs did not change since last successful build.
+    #[serde(default)]
+    pub force: bool,
+}
+
+impl Target {
+    fn new(data: &JsValue) -> Self {
+        if let Some(session) = data.as_string() {
+            Self {
+                session: Some(session),
+                group: None,
+                force: false,
+            }
+        } else {
+            data.into_serde().unwrap_or_default()
+        }
+    }
+
+    fn sessions(&self, meta: &Meta) -> Vec<MetaSession> {
+        if let Some(group) = &self.group {
+            meta.sessions()
+                .into_iter()
+                .filter(|session| session.group.as_ref() == Some(group))
+                .collect()
+        } else {
+            let id = self.session.as_deref().unwrap_or(DEFAULT_SESSION);
+            meta.session(id).into_iter().collect()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct MetaBuild {
     pub name: String,
     #[serde(default)]
     pub args: Vec<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Project relative path to git-ignored file with local environment overrides.
+    #[serde(default)]
+    pub env_file: Option<String>,
+}
+
+impl MetaBuild {
+    fn run(&self, blocking_task: bool, log_level: u8) -> Result<String, JsValue> {
+        let env = env::resolve(&self.env, self.env_file.as_deref())?;
+        run_node_with_env(
+            &self.name,
+            self.args.clone(),
+            blocking_task,
+            log_level,
+            &env,
+        )
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Profile {
+    #[default]
+    Debug,
+    Release,
+}
+
+impl Profile {
+    fn meta_build(self, meta: &Meta, session: &MetaSession) -> MetaBuild {
+        let build = match self {
+            Self::Debug => session.build_debug.as_ref().or(meta.build_debug.as_ref()),
+            Self::Release => session
+                .build_release
+                .as_ref()
+                .or(meta.build_release.as_ref()),
+        };
+        build.cloned().unwrap_or_else(|| MetaBuild {
+            name: self.default_node_name().to_owned(),
+            args: vec![],
+            env: Default::default(),
+            env_file: None,
+        })
+    }
+
+    /// Reverse proxy that web sessions serve games built with this profile behind.
+    fn meta_proxy(self, meta: &Meta) -> Option<&MetaProxy> {
+        match self {
+            Self::Debug => meta.proxy_debug.as_ref(),
+            Self::Release => meta.proxy_release.as_ref(),
+        }
+    }
+
+    fn default_node_name(self) -> &'static str {
+        match self {
+            Self::Debug => "@build",
+            Self::Release => "@build-release",
+        }
+    }
 }
 
 lazy_static! {
-    static ref PLAY_TOKEN: RwLock<Option<String>> = RwLock::new(None);
-    static ref BUILD_TOKEN: RwLock<Option<String>> = RwLock::new(None);
+    static ref SESSIONS: RwLock<HashMap<String, SessionState>> = RwLock::new(Default::default());
+    static ref PROBLEMS: RwLock<Vec<Diagnostic>> = RwLock::new(vec![]);
+    static ref PROFILE: RwLock<Profile> = RwLock::new(Profile::default());
+    static ref WATCH: RwLock<Option<Watch>> = RwLock::new(None);
+    static ref LOGS: RwLock<Logs> = RwLock::new(Default::default());
+    static ref PENDING_BUILDS: RwLock<HashMap<String, PendingBuild>> =
+        RwLock::new(Default::default());
+    static ref AUXILIARY: RwLock<HashMap<String, Vec<Auxiliary>>> = RwLock::new(Default::default());
 }
 
+static WATCH_RUN: AtomicUsize = AtomicUsize::new(0);
+
+const PLUGIN_NAME: &str = "ignite-play-mode-plugin";
+const DEFAULT_SESSION: &str = "default";
+const DEFAULT_PORT: usize = 19100;
+
 #[wasm_bindgen]
 pub fn query(query: &str, data: JsValue) -> Result<(), JsValue> {
     match query {
         "start" => {
-            if let (Ok(mut play), Ok(build)) = (PLAY_TOKEN.write(), BUILD_TOKEN.read()) {
-                if play.is_some() {
-                    return Ok(());
+            let meta = meta()?;
+            for session in Target::new(&data).sessions(&meta) {
+                start_session(&meta, &session)?;
+            }
+        }
+        "stop" => {
+            let meta = meta()?;
+            for session in Target::new(&data).sessions(&meta) {
+                stop_session(&session)?;
+            }
+        }
+        "status" => {
+            let meta = meta()?;
+            let status = meta
+                .sessions()
+                .into_iter()
+                .map(|session| SessionStatus {
+                    state: session_state(&session.id),
+                    id: session.id,
+                    group: session.group,
+                    port: session.port,
+                })
+                .collect::<Vec<_>>();
+            if let Ok(status) = JsValue::from_serde(&status) {
+                emit("status", status)?;
+            }
+        }
+        "build" => {
+            let meta = meta()?;
+            let target = Target::new(&data);
+            for session in target.sessions(&meta) {
+                start_build(&session.id, Profile::Debug, false, target.force)?;
+            }
+        }
+        "build-release" => {
+            let meta = meta()?;
+            let target = Target::new(&data);
+            for session in target.sessions(&meta) {
+                start_build(&session.id, Profile::Release, false, target.force)?;
+            }
+        }
+        "build-cancel" => {
+            let meta = meta()?;
+            for session in Target::new(&data).sessions(&meta) {
+                if let Some(build) = session_state(&session.id).build() {
+                    cancel_build(build)?;
                 }
-                let meta = get_plugin_meta()?;
-                if let Ok(meta) = meta.into_serde::<Meta>() {
-                    if let Some(directory) = meta.dist {
-                        let token = run_server(&directory, 19100, "Play Mode")?;
-                        *play = Some(token);
-                        let state = State {
-                            is_running: true,
-                            is_building: build.is_some(),
-                        };
-                        if let Ok(state) = JsValue::from_serde(&state) {
-                            emit("change", state)?;
+            }
+        }
+        "server-terminated" => {
+            if let Some(token) = data.as_string() {
+                if let Some(id) = find_session(|state| state.server() == Some(&token)) {
+                    server_terminated(&id, token)?;
+                }
+            }
+        }
+        "node-terminated" => {
+            if let Some(PayloadNodeTerminated {
+                token,
+                code,
+                output,
+            }) = PayloadNodeTerminated::new(data)
+            {
+                if let Some(id) = find_session(|state| state.build() == Some(&token)) {
+                    if transition(&id, SessionEvent::NodeTerminated(token.clone()))? {
+                        let has_errors = collect_problems(&id, output)?;
+                        record_build(&token, code, has_errors)?;
+                        if session_state(&id).is_running() {
+                            reload_after_watch_build(&id)?;
                         }
-                        drop(ignite("?", "play-mode-start", JsValue::UNDEFINED));
                     }
+                } else if let Some(id) = find_session(|state| state.server() == Some(&token)) {
+                    server_terminated(&id, token)?;
                 }
             }
         }
-        "stop" => {
-            if let (Ok(mut play), Ok(build)) = (PLAY_TOKEN.write(), BUILD_TOKEN.read()) {
-                if play.is_none() {
-                    return Ok(());
-                }
-                terminate_server(play.as_ref().unwrap().as_str())?;
-                *play = None;
-                let state = State {
-                    is_running: false,
-                    is_building: build.is_some(),
-                };
-                if let Ok(state) = JsValue::from_serde(&state) {
-                    emit("change", state)?;
+        "build-history" => {
+            let meta = meta()?;
+            let history = History::load(&relative_to_project(&meta.history.path)?);
+            let payload = PayloadBuildHistory {
+                report: history.report(meta.history.threshold),
+                builds: history.builds,
+            };
+            if let Ok(payload) = JsValue::from_serde(&payload) {
+                emit("build-history", payload)?;
+            }
+        }
+        "log" => {
+            let messages = if let Ok(message) = data.into_serde::<LogMessage>() {
+                vec![message]
+            } else {
+                data.into_serde::<Vec<LogMessage>>().unwrap_or_default()
+            };
+            let capacity = meta()?.logs.capacity;
+            if let Ok(mut logs) = LOGS.write() {
+                let timestamp = js_sys::Date::now();
+                for message in messages {
+                    logs.push(
+                        LogEntry {
+                            level: message.level,
+                            timestamp,
+                            source: LogSource::Game,
+                            session: message
+                                .session
+                                .unwrap_or_else(|| DEFAULT_SESSION.to_owned()),
+                            message: message.message,
+                        },
+                        capacity,
+                    );
                 }
             }
         }
-        // "launch" => {
-        //     if let (Ok(play), Ok(build)) = (PLAY_TOKEN.write(), BUILD_TOKEN.write()) {
-        //         if play.is_some() || build.is_some() {
-        //             return Ok(());
-        //         }
-        //     }
-        // }
-        "build" => {
-            if let (Ok(play), Ok(mut build)) = (PLAY_TOKEN.read(), BUILD_TOKEN.write()) {
-                if build.is_some() {
-                    return Ok(());
+        "logs" => {
+            let filter = data.into_serde::<LogFilter>().unwrap_or_default();
+            if let Ok(logs) = LOGS.read() {
+                if let Ok(entries) = JsValue::from_serde(&logs.filter(&filter)) {
+                    emit("logs", entries)?;
                 }
-                let meta = get_plugin_meta()?;
-                let (node_name, node_args, blocking_task, log_level) =
-                    if let Ok(meta) = meta.into_serde::<Meta>() {
-                        if let Some(info) = meta.build_debug {
-                            (info.name, info.args, meta.blocking_task, meta.log_level)
-                        } else {
-                            (
-                                "@build".to_owned(),
-                                vec![],
-                                meta.blocking_task,
-                                meta.log_level,
-                            )
-                        }
-                    } else {
-                        ("@build".to_owned(), vec![], false, 0)
-                    };
-                let token = run_node(&node_name, node_args, blocking_task, log_level)?;
-                *build = Some(token);
-                let state = State {
-                    is_running: play.is_some(),
-                    is_building: true,
-                };
-                if let Ok(state) = JsValue::from_serde(&state) {
-                    emit("change", state)?;
+            }
+        }
+        "logs-export" => {
+            if let Ok(payload) = data.into_serde::<PayloadLogsExport>() {
+                if let Ok(logs) = LOGS.read() {
+                    let contents = logs::export(&logs.filter(&payload.filter));
+                    write_string(&relative_to_project(&payload.path)?, &contents, true)?;
                 }
-                drop(ignite("?", "play-mode-build-start", JsValue::UNDEFINED));
             }
         }
-        "build-release" => {
-            if let (Ok(play), Ok(mut build)) = (PLAY_TOKEN.read(), BUILD_TOKEN.write()) {
-                if build.is_some() {
-                    return Ok(());
+        "logs-clear" => {
+            if let Ok(mut logs) = LOGS.write() {
+                logs.clear();
+            }
+        }
+        "problems" => {
+            if let Ok(problems) = PROBLEMS.read() {
+                if let Ok(problems) = JsValue::from_serde(&*problems) {
+                    emit("problems", problems)?;
                 }
-                let meta = get_plugin_meta()?;
-                let (node_name, node_args, blocking_task, log_level) =
-                    if let Ok(meta) = meta.into_serde::<Meta>() {
-                        if let Some(info) = meta.build_release {
-                            (info.name, info.args, meta.blocking_task, meta.log_level)
-                        } else {
-                            (
-                                "@build-release".to_owned(),
-                                vec![],
-                                meta.blocking_task,
-                                meta.log_level,
-                            )
-                        }
-                    } else {
-                        ("@build-release".to_owned(), vec![], false, 0)
+            }
+        }
+        "problem-open" => {
+            if let (Ok(problems), Ok(index)) = (PROBLEMS.read(), data.into_serde::<usize>()) {
+                if let Some(problem) = problems.get(index) {
+                    let payload = PayloadOpenFile {
+                        path: problem.file.clone(),
+                        line: problem.line,
+                        column: problem.column,
                     };
-                let token = run_node(&node_name, node_args, blocking_task, log_level)?;
-                *build = Some(token);
-                let state = State {
-                    is_running: play.is_some(),
-                    is_building: true,
-                };
-                if let Ok(state) = JsValue::from_serde(&state) {
-                    emit("change", state)?;
+                    if let Ok(payload) = JsValue::from_serde(&payload) {
+                        ignite("ignite-code-plugin", "open-file", payload)?;
+                    }
                 }
-                drop(ignite("?", "play-mode-build-start", JsValue::UNDEFINED));
             }
         }
-        "build-cancel" => {
-            if let Ok(build) = BUILD_TOKEN.read() {
-                if let Some(token) = &*build {
-                    terminate_node(&token)?;
+        "watch-start" => {
+            if let Ok(mut watch) = WATCH.write() {
+                if watch.is_some() {
+                    return Ok(());
                 }
+                let meta = meta()?;
+                let session = Target::new(&data)
+                    .session
+                    .unwrap_or_else(|| DEFAULT_SESSION.to_owned());
+                let run = WATCH_RUN.fetch_add(1, Ordering::Relaxed) + 1;
+                let meta = meta.watch.unwrap_or_default();
+                let interval = meta.interval;
+                *watch = Some(Watch::new(run, session, meta, &project_path()?)?);
+                emit("watch", JsValue::from_bool(true))?;
+                ignite_delayed(
+                    PLUGIN_NAME,
+                    "watch-tick",
+                    JsValue::from(run as u32),
+                    interval,
+                );
             }
         }
-        "server-terminated" => {
-            if let (Ok(mut play), Ok(build)) = (PLAY_TOKEN.write(), BUILD_TOKEN.read()) {
-                if play.is_none() {
+        "watch-stop" => {
+            if let Ok(mut watch) = WATCH.write() {
+                if watch.is_none() {
                     return Ok(());
                 }
-                if let Some(token) = data.as_string() {
-                    if let Some(value) = play.clone() {
-                        if value == token {
-                            *play = None;
-                            drop(ignite("?", "play-mode-stop", JsValue::UNDEFINED));
-                        }
+                *watch = None;
+                emit("watch", JsValue::from_bool(false))?;
+            }
+        }
+        "watch-tick" => {
+            if let Ok(mut watch) = WATCH.write() {
+                if let Some(watch) = watch.as_mut() {
+                    if data.as_f64() != Some(watch.run as f64) {
+                        return Ok(());
                     }
-                    let state = State {
-                        is_running: play.is_some(),
-                        is_building: build.is_some(),
-                    };
-                    if let Ok(state) = JsValue::from_serde(&state) {
-                        emit("change", state)?;
+                    if watch.scan(&project_path()?)? {
+                        ignite_delayed(
+                            PLUGIN_NAME,
+                            "watch-debounce",
+                            JsValue::from(watch.generation as u32),
+                            watch.meta.debounce,
+                        );
                     }
+                    ignite_delayed(
+                        PLUGIN_NAME,
+                        "watch-tick",
+                        JsValue::from(watch.run as u32),
+                        watch.meta.interval,
+                    );
                 }
             }
         }
-        "node-terminated" => {
-            if let (Ok(play), Ok(mut build)) = (PLAY_TOKEN.read(), BUILD_TOKEN.write()) {
-                if build.is_none() {
-                    return Ok(());
-                }
-                if let Some(token) = data.as_string() {
-                    if let Some(value) = build.clone() {
-                        if value == token {
-                            *build = None;
-                            drop(ignite("?", "play-mode-build-stop", JsValue::UNDEFINED));
-                        }
-                    }
-                    let state = State {
-                        is_running: play.is_some(),
-                        is_building: build.is_some(),
-                    };
-                    if let Ok(state) = JsValue::from_serde(&state) {
-                        emit("change", state)?;
+        "watch-debounce" => {
+            let (session, changes, pack) = if let Ok(mut watch) = WATCH.write() {
+                match watch.as_mut() {
+                    Some(watch) if data.as_f64() == Some(watch.generation as f64) => {
+                        watch.reload = true;
+                        (
+                            watch.session.clone(),
+                            std::mem::take(&mut watch.pending),
+                            watch.meta.pack.clone(),
+                        )
                     }
+                    _ => return Ok(()),
                 }
+            } else {
+                return Ok(());
+            };
+            if changes.sources || session_state(&session).is_building() {
+                let profile = PROFILE.read().map(|profile| *profile).unwrap_or_default();
+                start_build(&session, profile, true, false)?;
+            } else if let Some(pack) = pack {
+                let profile = PROFILE.read().map(|profile| *profile).unwrap_or_default();
+                start_node(&session, profile, pack, None)?;
+            } else {
+                reload_after_watch_build(&session)?;
             }
         }
         _ => {}
     }
     Ok(())
 }
+
+fn meta() -> Result<Meta, JsValue> {
+    Ok(get_plugin_meta()?.into_serde::<Meta>().unwrap_or_default())
+}
+
+fn session_state(id: &str) -> SessionState {
+    SESSIONS
+        .read()
+        .ok()
+        .and_then(|sessions| sessions.get(id).cloned())
+        .unwrap_or_default()
+}
+
+fn find_session(f: impl Fn(&SessionState) -> bool) -> Option<String> {
+    SESSIONS.read().ok().and_then(|sessions| {
+        sessions
+            .iter()
+            .find(|(_, state)| f(state))
+            .map(|(id, _)| id.to_owned())
+    })
+}
+
+/// Validates and applies session event, then notifies GUI and other plugins
+/// about what has changed. Returns false if event was rejected in current state.
+fn transition(id: &str, event: SessionEvent) -> Result<bool, JsValue> {
+    let (prev, next) = match SESSIONS.write() {
+        Ok(mut sessions) => {
+            let session = sessions.entry(id.to_owned()).or_default();
+            match session.apply(event) {
+                Ok(next) => (std::mem::replace(session, next.clone()), next),
+                Err(_) => return Ok(false),
+            }
+        }
+        Err(_) => return Ok(false),
+    };
+    if let Ok(state) = JsValue::from_serde(&State::new(id, &next)) {
+        emit("change", state)?;
+    }
+    let id = JsValue::from_str(id);
+    if !prev.is_running() && next.is_running() {
+        drop(ignite("?", "play-mode-start", id.clone()));
+    }
+    if prev.is_running() && !next.is_running() {
+        drop(ignite("?", "play-mode-stop", id.clone()));
+    }
+    if next.is_building() && prev.build() != next.build() {
+        drop(ignite("?", "play-mode-build-start", id.clone()));
+    }
+    if prev.is_building() && !next.is_building() {
+        drop(ignite("?", "play-mode-build-stop", id));
+    }
+    Ok(true)
+}
+
+fn fail(id: &str, error: JsValue) -> Result<(), JsValue> {
+    let message = error.as_string().unwrap_or_else(|| format!("{:?}", error));
+    transition(id, SessionEvent::Failed(message))?;
+    Err(error)
+}
+
+fn start_session(meta: &Meta, session: &MetaSession) -> Result<(), JsValue> {
+    if !session_state(&session.id).accepts(&SessionEvent::ServerStarted(Default::default())) {
+        return Ok(());
+    }
+    let result = if let Some(process) = &session.process {
+        process.run(meta.blocking_task, meta.log_level)
+    } else if let Some(directory) = session.dist.as_ref().or(meta.dist.as_ref()) {
+        let name = if session.id == DEFAULT_SESSION {
+            "Play Mode".to_owned()
+        } else {
+            format!("Play Mode: {}", session.id)
+        };
+        let profile = PROFILE.read().map(|profile| *profile).unwrap_or_default();
+        match profile.meta_proxy(meta) {
+            Some(proxy) => proxy::start(
+                proxy,
+                directory,
+                session.port,
+                &name,
+                meta.blocking_task,
+                meta.log_level,
+            )
+            .map(|(token, auxiliary)| {
+                if let Ok(mut items) = AUXILIARY.write() {
+                    items.insert(session.id.clone(), auxiliary);
+                }
+                token
+            }),
+            None => run_server(directory, session.port, &name),
+        }
+    } else {
+        return Ok(());
+    };
+    match result {
+        Ok(token) => {
+            transition(&session.id, SessionEvent::ServerStarted(token))?;
+            Ok(())
+        }
+        Err(error) => fail(&session.id, error),
+    }
+}
+
+fn stop_session(session: &MetaSession) -> Result<(), JsValue> {
+    let state = session_state(&session.id);
+    if transition(&session.id, SessionEvent::StopRequested)? && !state.is_stopping() {
+        if let Some(server) = state.server() {
+            if session.process.is_some() {
+                terminate_node(server)?;
+            } else {
+                terminate_server(server)?;
+            }
+        }
+        terminate_auxiliary(&session.id)?;
+    }
+    Ok(())
+}
+
+fn server_terminated(id: &str, token: String) -> Result<(), JsValue> {
+    if transition(id, SessionEvent::ServerTerminated(token))? {
+        terminate_auxiliary(id)?;
+    }
+    Ok(())
+}
+
+fn terminate_auxiliary(id: &str) -> Result<(), JsValue> {
+    let auxiliary = match AUXILIARY.write() {
+        Ok(mut items) => items.remove(id).unwrap_or_default(),
+        Err(_) => return Ok(()),
+    };
+    for item in auxiliary {
+        item.terminate()?;
+    }
+    Ok(())
+}
+
+/// Starts build of given profile, `restart` cancels the one that is already running
+/// and `force` builds even if inputs did not change since last successful build.
+fn start_build(id: &str, profile: Profile, restart: bool, force: bool) -> Result<(), JsValue> {
+    if let Ok(mut current) = PROFILE.write() {
+        *current = profile;
+    }
+    let payload = PayloadSessionProfile {
+        session: id.to_owned(),
+        profile,
+    };
+    if let Ok(payload) = JsValue::from_serde(&payload) {
+        drop(ignite("?", "play-mode-build-profile", payload));
+    }
+    if let Some(build) = session_state(id).build() {
+        if !restart {
+            return Ok(());
+        }
+        cancel_build(build)?;
+    }
+    let meta = meta()?;
+    let session = match meta.session(id) {
+        Some(session) => session,
+        None => return Ok(()),
+    };
+    let fingerprint = if meta.incremental.enabled {
+        Some(Fingerprint::scan(
+            &project_path()?,
+            &meta.incremental.inputs,
+        )?)
+    } else {
+        None
+    };
+    if let (false, Some(fingerprint)) = (force, &fingerprint) {
+        let fingerprints = Fingerprints::load(&relative_to_project(&meta.incremental.path)?);
+        let dist = session.dist.as_ref().or(meta.dist.as_ref());
+        let has_dist = match dist {
+            Some(dist) => scan_dir(&project_directory(dist)?, false, true)
+                .map(|entries| !entries.is_empty())
+                .unwrap_or(false),
+            None => true,
+        };
+        if has_dist && fingerprints.is_up_to_date(id, profile, fingerprint) {
+            return build_up_to_date(id, profile, &meta);
+        }
+    }
+    let node = profile.meta_build(&meta, &session);
+    start_node(id, profile, node, fingerprint)
+}
+
+fn build_up_to_date(id: &str, profile: Profile, meta: &Meta) -> Result<(), JsValue> {
+    if let Ok(mut logs) = LOGS.write() {
+        logs.push(
+            LogEntry {
+                level: LogLevel::Info,
+                timestamp: js_sys::Date::now(),
+                source: LogSource::Build,
+                session: id.to_owned(),
+                message: "Build is up to date".to_owned(),
+            },
+            meta.logs.capacity,
+        );
+    }
+    let payload = PayloadSessionProfile {
+        session: id.to_owned(),
+        profile,
+    };
+    if let Ok(payload) = JsValue::from_serde(&payload) {
+        emit("up-to-date", payload)?;
+    }
+    reload_after_watch_build(id)
+}
+
+fn start_node(
+    id: &str,
+    profile: Profile,
+    node: MetaBuild,
+    fingerprint: Option<Fingerprint>,
+) -> Result<(), JsValue> {
+    if !session_state(id).accepts(&SessionEvent::BuildStarted(Default::default())) {
+        return Ok(());
+    }
+    let meta = meta()?;
+    if let Some(path) = &meta.diagnostics_log {
+        // problems of previous build must not show up when this one writes no output.
+        drop(write_string(&relative_to_project(path)?, "", true));
+    }
+    match node.run(meta.blocking_task, meta.log_level) {
+        Ok(token) => {
+            if let Ok(mut pending) = PENDING_BUILDS.write() {
+                pending.insert(
+                    token.clone(),
+                    PendingBuild {
+                        session: id.to_owned(),
+                        profile,
+                        started: js_sys::Date::now(),
+                        cancelled: false,
+                        fingerprint,
+                    },
+                );
+            }
+            transition(id, SessionEvent::BuildStarted(token))?;
+            Ok(())
+        }
+        Err(error) => {
+            // e.g. environment variables that editor cannot pass to build node.
+            if let Ok(mut logs) = LOGS.write() {
+                logs.push(
+                    LogEntry {
+                        level: LogLevel::Error,
+                        timestamp: js_sys::Date::now(),
+                        source: LogSource::Build,
+                        session: id.to_owned(),
+                        message: error.as_string().unwrap_or_else(|| format!("{:?}", error)),
+                    },
+                    meta.logs.capacity,
+                );
+            }
+            fail(id, error)
+        }
+    }
+}
+
+/// Terminates build node and records it as cancelled right away, superseded build
+/// no longer belongs to its session once it terminates.
+fn cancel_build(token: &str) -> Result<(), JsValue> {
+    if let Ok(mut pending) = PENDING_BUILDS.write() {
+        if let Some(build) = pending.get_mut(token) {
+            build.cancelled = true;
+        }
+    }
+    let result = terminate_node(token);
+    record_build(token, None, false)?;
+    result
+}
+
+/// Stores finished build with sizes of produced files in the project build history.
+fn record_build(token: &str, exit_code: Option<i32>, has_errors: bool) -> Result<(), JsValue> {
+    let pending = match PENDING_BUILDS.write() {
+        Ok(mut pending) => match pending.remove(token) {
+            Some(build) => build,
+            None => return Ok(()),
+        },
+        Err(_) => return Ok(()),
+    };
+    let meta = meta()?;
+    let dist = meta
+        .session(&pending.session)
+        .and_then(|session| session.dist)
+        .or_else(|| meta.dist.clone());
+    let (record, fingerprint) = finish_build(
+        pending,
+        exit_code,
+        has_errors,
+        js_sys::Date::now(),
+        || match &dist {
+            Some(dist) => history::file_sizes(&project_directory(dist)?),
+            None => Ok(Default::default()),
+        },
+    )?;
+    if meta.incremental.enabled {
+        let path = relative_to_project(&meta.incremental.path)?;
+        let mut fingerprints = Fingerprints::load(&path);
+        match fingerprint {
+            Some(fingerprint) => {
+                fingerprints.insert(&record.session, record.profile, fingerprint);
+                fingerprints.save(&path)?;
+            }
+            // dist may be left in broken state, next build must not be skipped.
+            None => {
+                if fingerprints.remove(&record.session, record.profile) {
+                    fingerprints.save(&path)?;
+                }
+            }
+        }
+    }
+    let path = relative_to_project(&meta.history.path)?;
+    let mut history = History::load(&path);
+    history.push(record, meta.history.limit);
+    history.save(&path)
+}
+
+/// History record of finished build and input fingerprint to store for it, `None` when
+/// build did not succeed. Builds without reported exit code and without errors succeed,
+/// sizes of produced files are measured only for successful builds.
+fn finish_build<F>(
+    pending: PendingBuild,
+    exit_code: Option<i32>,
+    has_errors: bool,
+    ended: f64,
+    file_sizes: F,
+) -> Result<(BuildRecord, Option<Fingerprint>), JsValue>
+where
+    F: FnOnce() -> Result<BTreeMap<String, u64>, JsValue>,
+{
+    let outcome = BuildOutcome::new(pending.cancelled, exit_code, has_errors);
+    let (files, fingerprint) = if outcome.is_success() {
+        (file_sizes()?, pending.fingerprint)
+    } else {
+        (Default::default(), None)
+    };
+    let record = BuildRecord {
+        session: pending.session,
+        profile: pending.profile,
+        started: pending.started,
+        ended,
+        outcome,
+        files,
+    };
+    Ok((record, fingerprint))
+}
+
+fn project_directory(path: &str) -> Result<String, JsValue> {
+    if is_absolute(path)? {
+        Ok(path.to_owned())
+    } else {
+        relative_to_project(path)
+    }
+}
+
+fn reload_after_watch_build(id: &str) -> Result<(), JsValue> {
+    if let Ok(mut watch) = WATCH.write() {
+        if let Some(watch) = watch.as_mut() {
+            if watch.reload && watch.session == id {
+                watch.reload = false;
+                emit("reload", JsValue::from_str(id))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parses build output into session logs and problems list,
+/// returns true if build reported any error.
+fn collect_problems(id: &str, output: Option<String>) -> Result<bool, JsValue> {
+    let meta = meta()?;
+    let output = match build_output(output, meta.diagnostics_log.as_deref(), |path| {
+        read_string(&relative_to_project(path).ok()?, true).ok()
+    }) {
+        Some(output) => output,
+        None => return Ok(false),
+    };
+    if let Ok(mut logs) = LOGS.write() {
+        let timestamp = js_sys::Date::now();
+        for (level, message) in logs::build_output(&output) {
+            logs.push(
+                LogEntry {
+                    level,
+                    timestamp,
+                    source: LogSource::Build,
+                    session: id.to_owned(),
+                    message,
+                },
+                meta.logs.capacity,
+            );
+        }
+    }
+    let result = diagnostics::parse(&output);
+    let failed = result
+        .iter()
+        .any(|problem| problem.level == DiagnosticLevel::Error);
+    if let Ok(mut problems) = PROBLEMS.write() {
+        *problems = result;
+        if let Ok(problems) = JsValue::from_serde(&*problems) {
+            emit("problems", problems)?;
+        }
+    }
+    Ok(failed)
+}
+
+/// Output of finished build, reported by editor or read from `diagnostics_log` file.
+/// Missing log file counts as empty output, so problems of previous build get cleared.
+fn build_output<F>(
+    output: Option<String>,
+    diagnostics_log: Option<&str>,
+    read_log: F,
+) -> Option<String>
+where
+    F: FnOnce(&str) -> Option<String>,
+{
+    match (output, diagnostics_log) {
+        (Some(output), _) => Some(output),
+        (None, Some(path)) => Some(read_log(path).unwrap_or_default()),
+        (None, None) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending(cancelled: bool) -> PendingBuild {
+        let mut fingerprint = Fingerprint::default();
+        fingerprint.0.insert("src/lib.rs".to_owned(), 42);
+        PendingBuild {
+            session: "game".to_owned(),
+            profile: Profile::Debug,
+            started: 10.0,
+            cancelled,
+            fingerprint: Some(fingerprint),
+        }
+    }
+
+    fn sizes() -> Result<BTreeMap<String, u64>, JsValue> {
+        let mut result = BTreeMap::new();
+        result.insert("game.wasm".to_owned(), 100);
+        Ok(result)
+    }
+
+    #[test]
+    fn test_finish_build_without_exit_code() {
+        let (record, fingerprint) = finish_build(pending(false), None, false, 20.0, sizes).unwrap();
+        assert_eq!(record.outcome, BuildOutcome::Unknown);
+        assert_eq!(record.session, "game");
+        assert_eq!(record.ended, 20.0);
+        assert_eq!(record.files.get("game.wasm"), Some(&100));
+        assert_eq!(fingerprint, pending(false).fingerprint);
+    }
+
+    #[test]
+    fn test_finish_build_failed() {
+        let measured = std::cell::Cell::new(false);
+        let files = || {
+            measured.set(true);
+            sizes()
+        };
+        let (record, fingerprint) = finish_build(pending(false), None, true, 20.0, files).unwrap();
+        assert_eq!(record.outcome, BuildOutcome::Failed);
+        assert!(record.files.is_empty());
+        assert!(fingerprint.is_none());
+        assert!(!measured.get());
+        let (record, fingerprint) =
+            finish_build(pending(true), Some(0), false, 20.0, sizes).unwrap();
+        assert_eq!(record.outcome, BuildOutcome::Cancelled);
+        assert!(record.files.is_empty());
+        assert!(fingerprint.is_none());
+        let (record, fingerprint) =
+            finish_build(pending(false), Some(0), false, 20.0, sizes).unwrap();
+        assert_eq!(record.outcome, BuildOutcome::Succeeded);
+        assert!(fingerprint.is_some());
+    }
+
+    #[test]
+    fn test_build_output_from_log() {
+        let log = concat!(
+            "   Compiling game v0.1.0 (/project)\n",
+            "error[E0425]: cannot find value `x` in this scope\n",
+            "  --> src/lib.rs:10:5\n",
+            "   |\n",
+            "error: could not compile `game`\n",
+        );
+        let output = build_output(None, Some("build.log"), |path| {
+            assert_eq!(path, "build.log");
+            Some(log.to_owned())
+        })
+        .unwrap();
+        let problems = diagnostics::parse(&output);
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].file, "src/lib.rs");
+        assert_eq!(problems[0].line, 10);
+        assert_eq!(problems[0].level, DiagnosticLevel::Error);
+        assert!(logs::build_output(&output)
+            .iter()
+            .any(|(level, _)| *level == LogLevel::Error));
+    }
+
+    #[test]
+    fn test_build_output_sources() {
+        let reported = build_output(Some("reported".to_owned()), Some("build.log"), |_| {
+            panic!("log must not be read when editor reports output")
+        });
+        assert_eq!(reported.as_deref(), Some("reported"));
+        let missing = build_output(None, Some("build.log"), |_| None);
+        assert_eq!(missing.as_deref(), Some(""));
+        assert!(diagnostics::parse(&missing.unwrap()).is_empty());
+        assert!(build_output(None, None, |_| panic!("no log configured")).is_none());
+    }
+}
@@ -0,0 +1,299 @@
+use crate::diagnostics;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use wasm_bindgen::prelude::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetaLogs {
+    /// Maximum number of entries kept, oldest ones are dropped first.
+    #[serde(default = "MetaLogs::default_capacity")]
+    pub capacity: usize,
+}
+
+impl Default for MetaLogs {
+    fn default() -> Self {
+        Self {
+            capacity: Self::default_capacity(),
+        }
+    }
+}
+
+impl MetaLogs {
+    fn default_capacity() -> usize {
+        1000
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogSource {
+    Game,
+    Build,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub level: LogLevel,
+    /// Milliseconds since UNIX epoch.
+    pub timestamp: f64,
+    pub source: LogSource,
+    pub session: String,
+    pub message: String,
+}
+
+/// Log message forwarded by the play view.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogMessage {
+    #[serde(default = "LogMessage::default_level")]
+    pub level: LogLevel,
+    pub message: String,
+    #[serde(default)]
+    pub session: Option<String>,
+}
+
+impl LogMessage {
+    fn default_level() -> LogLevel {
+        LogLevel::Info
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct LogFilter {
+    /// Minimal level of reported entries.
+    #[serde(default)]
+    pub level: Option<LogLevel>,
+    #[serde(default)]
+    pub source: Option<LogSource>,
+    #[serde(default)]
+    pub session: Option<String>,
+    /// Case insensitive phrase that message has to contain.
+    #[serde(default)]
+    pub search: Option<String>,
+    #[serde(default)]
+    pub since: Option<f64>,
+    /// Maximum number of latest entries reported.
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+impl LogFilter {
+    fn matches(&self, entry: &LogEntry, search: Option<&str>) -> bool {
+        self.level.map(|level| entry.level >= level).unwrap_or(true)
+            && self
+                .source
+                .map(|source| entry.source == source)
+                .unwrap_or(true)
+            && self
+                .session
+                .as_ref()
+                .map(|session| &entry.session == session)
+                .unwrap_or(true)
+            && self
+                .since
+                .map(|since| entry.timestamp >= since)
+                .unwrap_or(true)
+            && search
+                .map(|search| entry.message.to_lowercase().contains(search))
+                .unwrap_or(true)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Logs {
+    entries: VecDeque<LogEntry>,
+}
+
+impl Logs {
+    pub fn push(&mut self, entry: LogEntry, capacity: usize) {
+        while self.entries.len() >= capacity.max(1) {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn filter(&self, filter: &LogFilter) -> Vec<&LogEntry> {
+        let search = filter.search.as_ref().map(|search| search.to_lowercase());
+        let mut result = self
+            .entries
+            .iter()
+            .rev()
+            .filter(|entry| filter.matches(entry, search.as_deref()))
+            .take(filter.limit.unwrap_or(usize::MAX))
+            .collect::<Vec<_>>();
+        result.reverse();
+        result
+    }
+}
+
+pub fn export(entries: &[&LogEntry]) -> String {
+    entries
+        .iter()
+        .map(|entry| {
+            let timestamp = js_sys::Date::new(&JsValue::from_f64(entry.timestamp));
+            format!(
+                "[{}] [{:?}] [{:?}] [{}] {}\n",
+                String::from(timestamp.to_iso_string()),
+                entry.source,
+                entry.level,
+                entry.session,
+                entry.message
+            )
+        })
+        .collect()
+}
+
+/// Turns build node output into log lines, cargo JSON messages get reduced to diagnostics.
+pub fn build_output(output: &str) -> Vec<(LogLevel, String)> {
+    output
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| {
+            if line.trim_start().starts_with('{') {
+                let diagnostic = diagnostics::parse(line).into_iter().next()?;
+                let level = match diagnostic.level {
+                    diagnostics::DiagnosticLevel::Error => LogLevel::Error,
+                    diagnostics::DiagnosticLevel::Warning => LogLevel::Warning,
+                    _ => LogLevel::Info,
+                };
+                let message = format!(
+                    "{}:{}:{}: {}",
+                    diagnostic.file, diagnostic.line, diagnostic.column, diagnostic.message
+                );
+                Some((level, message))
+            } else {
+                let lower = line.trim_start().to_lowercase();
+                let level = if lower.starts_with("error") {
+                    LogLevel::Error
+                } else if lower.starts_with("warning") {
+                    LogLevel::Warning
+                } else {
+                    LogLevel::Info
+                };
+                Some((level, line.to_owned()))
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(level: LogLevel, source: LogSource, session: &str, timestamp: f64) -> LogEntry {
+        LogEntry {
+            level,
+            timestamp,
+            source,
+            session: session.to_owned(),
+            message: format!("{:?} message at {}", level, timestamp),
+        }
+    }
+
+    fn logs() -> Logs {
+        let mut logs = Logs::default();
+        logs.push(entry(LogLevel::Debug, LogSource::Game, "a", 1.0), 10);
+        logs.push(entry(LogLevel::Info, LogSource::Build, "a", 2.0), 10);
+        logs.push(entry(LogLevel::Warning, LogSource::Game, "b", 3.0), 10);
+        logs.push(entry(LogLevel::Error, LogSource::Game, "a", 4.0), 10);
+        logs
+    }
+
+    fn timestamps(entries: Vec<&LogEntry>) -> Vec<f64> {
+        entries.iter().map(|entry| entry.timestamp).collect()
+    }
+
+    #[test]
+    fn test_capacity() {
+        let mut logs = logs();
+        logs.push(entry(LogLevel::Info, LogSource::Game, "a", 5.0), 3);
+        assert_eq!(
+            timestamps(logs.filter(&Default::default())),
+            vec![3.0, 4.0, 5.0]
+        );
+        logs.push(entry(LogLevel::Info, LogSource::Game, "a", 6.0), 0);
+        assert_eq!(timestamps(logs.filter(&Default::default())), vec![6.0]);
+        logs.clear();
+        assert!(logs.filter(&Default::default()).is_empty());
+    }
+
+    #[test]
+    fn test_filter() {
+        let logs = logs();
+        let filter = |filter: LogFilter| timestamps(logs.filter(&filter));
+        assert_eq!(
+            filter(LogFilter {
+                level: Some(LogLevel::Warning),
+                ..Default::default()
+            }),
+            vec![3.0, 4.0]
+        );
+        assert_eq!(
+            filter(LogFilter {
+                source: Some(LogSource::Build),
+                ..Default::default()
+            }),
+            vec![2.0]
+        );
+        assert_eq!(
+            filter(LogFilter {
+                session: Some("a".to_owned()),
+                since: Some(2.0),
+                ..Default::default()
+            }),
+            vec![2.0, 4.0]
+        );
+        assert_eq!(
+            filter(LogFilter {
+                search: Some("ERROR MESSAGE".to_owned()),
+                ..Default::default()
+            }),
+            vec![4.0]
+        );
+        assert_eq!(
+            filter(LogFilter {
+                limit: Some(2),
+                ..Default::default()
+            }),
+            vec![3.0, 4.0]
+        );
+    }
+
+    #[test]
+    fn test_build_output() {
+        let output = concat!(
+            "   Compiling game v0.1.0\n",
+            "\n",
+            "warning: unused variable\n",
+            "error[E0425]: cannot find value\n",
+            r#"{"reason":"compiler-message","message":{"message":"oops","level":"error","spans":[{"file_name":"src/lib.rs","line_start":1,"column_start":2,"is_primary":true}]}}"#,
+            "\n",
+            r#"{"reason":"compiler-artifact"}"#,
+            "\n",
+        );
+        assert_eq!(
+            build_output(output),
+            vec![
+                (LogLevel::Info, "   Compiling game v0.1.0".to_owned()),
+                (LogLevel::Warning, "warning: unused variable".to_owned()),
+                (
+                    LogLevel::Error,
+                    "error[E0425]: cannot find value".to_owned()
+                ),
+                (LogLevel::Error, "src/lib.rs:1:2: oops".to_owned()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use crate::MetaBuild;
+use ignite_plugin_utils::editor::{run_proxy_server, run_server, terminate_node, terminate_server};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use wasm_bindgen::prelude::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetaProxy {
+    /// Port of the server with built game files, defaults to session port + 1.
+    #[serde(default)]
+    pub static_port: Option<usize>,
+    /// Path prefixes forwarded to backend addresses, e.g. `/api` -> `http://127.0.0.1:9009`.
+    /// Requires editor with routing proxy, see `ProxyOptions`.
+    #[serde(default)]
+    pub routes: BTreeMap<String, String>,
+    /// Whether websocket upgrades are proxied too.
+    #[serde(default = "MetaProxy::default_websockets")]
+    pub websockets: bool,
+    /// Node that launches local backend before proxy starts.
+    #[serde(default)]
+    pub backend: Option<MetaBuild>,
+}
+
+impl MetaProxy {
+    fn default_websockets() -> bool {
+        true
+    }
+
+    fn static_port(&self, port: usize) -> usize {
+        self.static_port.unwrap_or(port + 1)
+    }
+
+    /// Checks routes up front, so misconfigured proxy fails before anything starts.
+    fn validate(&self) -> Result<(), String> {
+        for (prefix, target) in &self.routes {
+            if !prefix.starts_with('/') {
+                return Err(format!(
+                    "Proxy route prefix must start with `/`: {:?}",
+                    prefix
+                ));
+            }
+            if !ROUTE_SCHEMES
+                .iter()
+                .any(|scheme| target.starts_with(scheme) && target.len() > scheme.len())
+            {
+                return Err(format!(
+                    "Proxy route {:?} target must be an absolute http or ws address: {:?}",
+                    prefix, target
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn options(&self) -> ProxyOptions<'_> {
+        ProxyOptions {
+            routes: &self.routes,
+            ws: self.websockets,
+        }
+    }
+}
+
+const ROUTE_SCHEMES: &[&str] = &["http://", "https://", "ws://", "wss://"];
+
+/// Options object passed as `opts` of `editor.run_proxy_server`: requests whose
+/// path starts with one of `routes` keys go to its target instead of `address`,
+/// `ws` enables websocket proxying. Editor versions without routing proxy ignore
+/// this object and forward every request to the static files server.
+#[derive(Debug, Serialize)]
+struct ProxyOptions<'a> {
+    pub routes: &'a BTreeMap<String, String>,
+    pub ws: bool,
+}
+
+/// Processes running next to the proxy that have to be terminated together with it.
+#[derive(Debug, Clone)]
+pub enum Auxiliary {
+    Server(String),
+    Node(String),
+}
+
+impl Auxiliary {
+    pub fn terminate(&self) -> Result<(), JsValue> {
+        match self {
+            Self::Server(token) => terminate_server(token),
+            Self::Node(token) => terminate_node(token),
+        }
+    }
+}
+
+/// Serves `directory` behind proxy listening on `port`.
+/// Returns proxy server token and auxiliary processes started for it.
+pub fn start(
+    proxy: &MetaProxy,
+    directory: &str,
+    port: usize,
+    name: &str,
+    blocking_task: bool,
+    log_level: u8,
+) -> Result<(String, Vec<Auxiliary>), JsValue> {
+    let mut auxiliary = vec![];
+    let result = start_inner(
+        proxy,
+        directory,
+        port,
+        name,
+        blocking_task,
+        log_level,
+        &mut auxiliary,
+    );
+    match result {
+        Ok(token) => Ok((token, auxiliary)),
+        Err(error) => {
+            for item in &auxiliary {
+                drop(item.terminate());
+            }
+            Err(error)
+        }
+    }
+}
+
+fn start_inner(
+    proxy: &MetaProxy,
+    directory: &str,
+    port: usize,
+    name: &str,
+    blocking_task: bool,
+    log_level: u8,
+    auxiliary: &mut Vec<Auxiliary>,
+) -> Result<String, JsValue> {
+    proxy.validate()?;
+    if let Some(backend) = &proxy.backend {
+        let token = backend.run(blocking_task, log_level)?;
+        auxiliary.push(Auxiliary::Node(token));
+    }
+    let static_port = proxy.static_port(port);
+    let token = run_server(directory, static_port, &format!("{} (files)", name))?;
+    auxiliary.push(Auxiliary::Server(token));
+    let options = match JsValue::from_serde(&proxy.options()) {
+        Ok(options) => options,
+        Err(error) => return Err(format!("{:?}", error).into()),
+    };
+    run_proxy_server(
+        &format!("http://127.0.0.1:{}", static_port),
+        port,
+        name,
+        options,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proxy(routes: &[(&str, &str)]) -> MetaProxy {
+        MetaProxy {
+            static_port: None,
+            routes: routes
+                .iter()
+                .map(|(prefix, target)| (prefix.to_string(), target.to_string()))
+                .collect(),
+            websockets: MetaProxy::default_websockets(),
+            backend: None,
+        }
+    }
+
+    #[test]
+    fn test_static_port() {
+        let mut proxy = proxy(&[]);
+        assert_eq!(proxy.static_port(8080), 8081);
+        proxy.static_port = Some(9000);
+        assert_eq!(proxy.static_port(8080), 9000);
+    }
+
+    #[test]
+    fn test_options() {
+        let mut proxy = proxy(&[
+            ("/api", "http://127.0.0.1:9009"),
+            ("/socket", "ws://127.0.0.1:9010"),
+        ]);
+        assert_eq!(
+            serde_json::to_string(&proxy.options()).unwrap(),
+            r#"{"routes":{"/api":"http://127.0.0.1:9009","/socket":"ws://127.0.0.1:9010"},"ws":true}"#
+        );
+        proxy.websockets = false;
+        proxy.routes.clear();
+        assert_eq!(
+            serde_json::to_string(&proxy.options()).unwrap(),
+            r#"{"routes":{},"ws":false}"#
+        );
+    }
+
+    #[test]
+    fn test_meta_defaults() {
+        let proxy = serde_json::from_str::<MetaProxy>("{}").unwrap();
+        assert_eq!(proxy.static_port, None);
+        assert!(proxy.routes.is_empty());
+        assert!(proxy.websockets);
+        assert!(proxy.backend.is_none());
+    }
+
+    #[test]
+    fn test_validate_routes() {
+        assert!(proxy(&[]).validate().is_ok());
+        assert!(proxy(&[("/api", "https://example.com/v1")])
+            .validate()
+            .is_ok());
+        assert!(proxy(&[("/live", "wss://example.com")]).validate().is_ok());
+        assert!(proxy(&[("api", "http://127.0.0.1:9009")])
+            .validate()
+            .is_err());
+        assert!(proxy(&[("/api", "127.0.0.1:9009")]).validate().is_err());
+        assert!(proxy(&[("/api", "http://")]).validate().is_err());
+    }
+}
@@ -0,0 +1,444 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Play session lifecycle, tokens are the ones returned by the editor when
+/// server or build node gets started.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "kebab-case")]
+pub enum SessionState {
+    #[default]
+    Idle,
+    Building {
+        build: String,
+    },
+    Serving {
+        server: String,
+    },
+    BuildingWhileServing {
+        server: String,
+        build: String,
+    },
+    Stopping {
+        server: String,
+        #[serde(default)]
+        build: Option<String>,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionEvent {
+    ServerStarted(String),
+    StopRequested,
+    BuildStarted(String),
+    ServerTerminated(String),
+    NodeTerminated(String),
+    Failed(String),
+}
+
+impl SessionEvent {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::ServerStarted(_) => "server-started",
+            Self::StopRequested => "stop-requested",
+            Self::BuildStarted(_) => "build-started",
+            Self::ServerTerminated(_) => "server-terminated",
+            Self::NodeTerminated(_) => "node-terminated",
+            Self::Failed(_) => "failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidTransition {
+    pub state: SessionState,
+    pub event: SessionEvent,
+}
+
+impl fmt::Display for InvalidTransition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Play session cannot handle {} event while in state: {:?}",
+            self.event.name(),
+            self.state
+        )
+    }
+}
+
+impl SessionState {
+    pub fn is_running(&self) -> bool {
+        self.server().is_some()
+    }
+
+    pub fn is_building(&self) -> bool {
+        self.build().is_some()
+    }
+
+    pub fn is_stopping(&self) -> bool {
+        matches!(self, Self::Stopping { .. })
+    }
+
+    pub fn server(&self) -> Option<&str> {
+        match self {
+            Self::Serving { server }
+            | Self::BuildingWhileServing { server, .. }
+            | Self::Stopping { server, .. } => Some(server),
+            _ => None,
+        }
+    }
+
+    pub fn build(&self) -> Option<&str> {
+        match self {
+            Self::Building { build } | Self::BuildingWhileServing { build, .. } => Some(build),
+            Self::Stopping { build, .. } => build.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Checks if event kind can be handled in this state, regardless of tokens it carries.
+    pub fn accepts(&self, event: &SessionEvent) -> bool {
+        self.apply(event.clone()).is_ok()
+    }
+
+    pub fn apply(&self, event: SessionEvent) -> Result<Self, InvalidTransition> {
+        use SessionEvent as E;
+        use SessionState as S;
+
+        let next = match (self, &event) {
+            (state, E::Failed(error)) if !state.is_running() && !state.is_building() => {
+                Some(S::Failed {
+                    error: error.clone(),
+                })
+            }
+            (S::Idle, E::ServerStarted(server)) | (S::Failed { .. }, E::ServerStarted(server)) => {
+                Some(S::Serving {
+                    server: server.clone(),
+                })
+            }
+            (S::Building { build }, E::ServerStarted(server)) => Some(S::BuildingWhileServing {
+                server: server.clone(),
+                build: build.clone(),
+            }),
+            (S::Idle, E::BuildStarted(build))
+            | (S::Failed { .. }, E::BuildStarted(build))
+            | (S::Building { .. }, E::BuildStarted(build)) => Some(S::Building {
+                build: build.clone(),
+            }),
+            (S::Serving { server }, E::BuildStarted(build))
+            | (S::BuildingWhileServing { server, .. }, E::BuildStarted(build)) => {
+                Some(S::BuildingWhileServing {
+                    server: server.clone(),
+                    build: build.clone(),
+                })
+            }
+            (S::Stopping { server, .. }, E::BuildStarted(build)) => Some(S::Stopping {
+                server: server.clone(),
+                build: Some(build.clone()),
+            }),
+            (S::Serving { server }, E::StopRequested) => Some(S::Stopping {
+                server: server.clone(),
+                build: None,
+            }),
+            (S::BuildingWhileServing { server, build }, E::StopRequested) => Some(S::Stopping {
+                server: server.clone(),
+                build: Some(build.clone()),
+            }),
+            (S::Stopping { .. }, E::StopRequested) => Some(self.clone()),
+            (S::Failed { .. }, E::StopRequested) => Some(S::Idle),
+            (S::Serving { server }, E::ServerTerminated(token)) if server == token => Some(S::Idle),
+            (S::BuildingWhileServing { server, build }, E::ServerTerminated(token))
+            | (
+                S::Stopping {
+                    server,
+                    build: Some(build),
+                },
+                E::ServerTerminated(token),
+            ) if server == token => Some(S::Building {
+                build: build.clone(),
+            }),
+            (
+                S::Stopping {
+                    server,
+                    build: None,
+                },
+                E::ServerTerminated(token),
+            ) if server == token => Some(S::Idle),
+            (S::Building { build }, E::NodeTerminated(token)) if build == token => Some(S::Idle),
+            (S::BuildingWhileServing { server, build }, E::NodeTerminated(token))
+                if build == token =>
+            {
+                Some(S::Serving {
+                    server: server.clone(),
+                })
+            }
+            (
+                S::Stopping {
+                    server,
+                    build: Some(build),
+                },
+                E::NodeTerminated(token),
+            ) if build == token => Some(S::Stopping {
+                server: server.clone(),
+                build: None,
+            }),
+            _ => None,
+        };
+        next.ok_or_else(|| InvalidTransition {
+            state: self.clone(),
+            event,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SessionEvent as E, SessionState as S};
+
+    fn run(events: Vec<E>) -> Vec<Result<S, S>> {
+        let mut state = S::Idle;
+        events
+            .into_iter()
+            .map(|event| match state.apply(event) {
+                Ok(next) => {
+                    state = next;
+                    Ok(state.clone())
+                }
+                Err(error) => Err(error.state),
+            })
+            .collect()
+    }
+
+    fn serving() -> S {
+        S::Serving {
+            server: "s".to_owned(),
+        }
+    }
+
+    fn building() -> S {
+        S::Building {
+            build: "b".to_owned(),
+        }
+    }
+
+    fn both() -> S {
+        S::BuildingWhileServing {
+            server: "s".to_owned(),
+            build: "b".to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_serve_and_stop() {
+        assert_eq!(
+            run(vec![
+                E::ServerStarted("s".to_owned()),
+                E::StopRequested,
+                E::ServerTerminated("s".to_owned()),
+            ]),
+            vec![
+                Ok(serving()),
+                Ok(S::Stopping {
+                    server: "s".to_owned(),
+                    build: None,
+                }),
+                Ok(S::Idle),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_server_terminated_before_stop() {
+        assert_eq!(
+            run(vec![
+                E::ServerStarted("s".to_owned()),
+                E::ServerTerminated("s".to_owned()),
+                E::StopRequested,
+            ]),
+            vec![Ok(serving()), Ok(S::Idle), Err(S::Idle)]
+        );
+    }
+
+    #[test]
+    fn test_start_while_stopping_is_rejected() {
+        let stopping = S::Stopping {
+            server: "s".to_owned(),
+            build: None,
+        };
+        assert_eq!(
+            run(vec![
+                E::ServerStarted("s".to_owned()),
+                E::StopRequested,
+                E::ServerStarted("s2".to_owned()),
+                E::StopRequested,
+                E::ServerTerminated("s".to_owned()),
+                E::ServerStarted("s2".to_owned()),
+            ]),
+            vec![
+                Ok(serving()),
+                Ok(stopping.clone()),
+                Err(stopping.clone()),
+                Ok(stopping),
+                Ok(S::Idle),
+                Ok(S::Serving {
+                    server: "s2".to_owned(),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_then_serve() {
+        assert_eq!(
+            run(vec![
+                E::BuildStarted("b".to_owned()),
+                E::ServerStarted("s".to_owned()),
+                E::NodeTerminated("b".to_owned()),
+                E::StopRequested,
+                E::ServerTerminated("s".to_owned()),
+            ]),
+            vec![
+                Ok(building()),
+                Ok(both()),
+                Ok(serving()),
+                Ok(S::Stopping {
+                    server: "s".to_owned(),
+                    build: None,
+                }),
+                Ok(S::Idle),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_serve_then_build_finishing_after_server() {
+        assert_eq!(
+            run(vec![
+                E::ServerStarted("s".to_owned()),
+                E::BuildStarted("b".to_owned()),
+                E::ServerTerminated("s".to_owned()),
+                E::NodeTerminated("b".to_owned()),
+            ]),
+            vec![Ok(serving()), Ok(both()), Ok(building()), Ok(S::Idle)]
+        );
+    }
+
+    #[test]
+    fn test_stop_while_building() {
+        let stopping = S::Stopping {
+            server: "s".to_owned(),
+            build: Some("b".to_owned()),
+        };
+        assert_eq!(
+            run(vec![
+                E::ServerStarted("s".to_owned()),
+                E::BuildStarted("b".to_owned()),
+                E::StopRequested,
+                E::NodeTerminated("b".to_owned()),
+                E::ServerTerminated("s".to_owned()),
+            ]),
+            vec![
+                Ok(serving()),
+                Ok(both()),
+                Ok(stopping),
+                Ok(S::Stopping {
+                    server: "s".to_owned(),
+                    build: None,
+                }),
+                Ok(S::Idle),
+            ]
+        );
+        assert_eq!(
+            run(vec![
+                E::ServerStarted("s".to_owned()),
+                E::BuildStarted("b".to_owned()),
+                E::StopRequested,
+                E::ServerTerminated("s".to_owned()),
+                E::NodeTerminated("b".to_owned()),
+            ])
+            .pop(),
+            Some(Ok(S::Idle))
+        );
+    }
+
+    #[test]
+    fn test_restarted_build_ignores_stale_token() {
+        assert_eq!(
+            run(vec![
+                E::BuildStarted("a".to_owned()),
+                E::BuildStarted("b".to_owned()),
+                E::NodeTerminated("a".to_owned()),
+                E::NodeTerminated("b".to_owned()),
+            ]),
+            vec![
+                Ok(S::Building {
+                    build: "a".to_owned(),
+                }),
+                Ok(building()),
+                Err(building()),
+                Ok(S::Idle),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stale_server_token_is_ignored() {
+        assert_eq!(
+            run(vec![
+                E::ServerStarted("s".to_owned()),
+                E::ServerTerminated("other".to_owned()),
+            ]),
+            vec![Ok(serving()), Err(serving())]
+        );
+    }
+
+    #[test]
+    fn test_failure_recovery() {
+        let failed = S::Failed {
+            error: "port taken".to_owned(),
+        };
+        assert_eq!(
+            run(vec![
+                E::Failed("port taken".to_owned()),
+                E::StopRequested,
+                E::Failed("port taken".to_owned()),
+                E::BuildStarted("b".to_owned()),
+            ]),
+            vec![Ok(failed.clone()), Ok(S::Idle), Ok(failed), Ok(building()),]
+        );
+    }
+
+    #[test]
+    fn test_terminations_while_idle_are_rejected() {
+        assert_eq!(
+            run(vec![
+                E::ServerTerminated("s".to_owned()),
+                E::NodeTerminated("b".to_owned()),
+                E::StopRequested,
+            ]),
+            vec![Err(S::Idle), Err(S::Idle), Err(S::Idle)]
+        );
+    }
+
+    #[test]
+    fn test_failure_keeps_live_processes() {
+        assert_eq!(
+            run(vec![
+                E::ServerStarted("s".to_owned()),
+                E::Failed("build node missing".to_owned()),
+            ]),
+            vec![Ok(serving()), Err(serving())]
+        );
+    }
+
+    #[test]
+    fn test_accepts() {
+        assert!(S::Idle.accepts(&E::ServerStarted(Default::default())));
+        assert!(!serving().accepts(&E::ServerStarted(Default::default())));
+        assert!(!both().accepts(&E::ServerStarted(Default::default())));
+        assert!(building().accepts(&E::BuildStarted(Default::default())));
+        assert!(S::Idle.accepts(&E::BuildStarted(Default::default())));
+    }
+}
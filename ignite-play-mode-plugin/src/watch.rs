@@ -0,0 +1,126 @@
+use crate::{
+    fingerprint::{matches_any, Fingerprint},
+    MetaBuild,
+};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetaWatch {
+    /// Changes to these files trigger full rebuild.
+    #[serde(default = "MetaWatch::default_sources")]
+    pub sources: Vec<String>,
+    /// Changes to these files only trigger the pack step.
+    #[serde(default = "MetaWatch::default_assets")]
+    pub assets: Vec<String>,
+    /// Milliseconds between file system scans.
+    #[serde(default = "MetaWatch::default_interval")]
+    pub interval: usize,
+    /// Milliseconds without new changes before rebuild starts.
+    #[serde(default = "MetaWatch::default_debounce")]
+    pub debounce: usize,
+    /// Node that packs assets - when not set asset changes only reload the view.
+    #[serde(default)]
+    pub pack: Option<MetaBuild>,
+}
+
+impl Default for MetaWatch {
+    fn default() -> Self {
+        Self {
+            sources: Self::default_sources(),
+            assets: Self::default_assets(),
+            interval: Self::default_interval(),
+            debounce: Self::default_debounce(),
+            pack: None,
+        }
+    }
+}
+
+impl MetaWatch {
+    fn default_sources() -> Vec<String> {
+        vec!["src/**/*.rs".to_owned(), "Cargo.toml".to_owned()]
+    }
+
+    fn default_assets() -> Vec<String> {
+        vec!["static/assets/**".to_owned()]
+    }
+
+    fn default_interval() -> usize {
+        1000
+    }
+
+    fn default_debounce() -> usize {
+        500
+    }
+
+    pub fn patterns(&self) -> Vec<String> {
+        self.sources
+            .iter()
+            .chain(self.assets.iter())
+            .cloned()
+            .collect()
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Changes {
+    pub sources: bool,
+    pub assets: bool,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        !self.sources && !self.assets
+    }
+}
+
+#[derive(Debug)]
+pub struct Watch {
+    /// Identifies scan loop so ticks scheduled by previous watch session stop.
+    pub run: usize,
+    /// Play session that gets rebuilt and reloaded.
+    pub session: String,
+    pub meta: MetaWatch,
+    pub fingerprint: Fingerprint,
+    pub pending: Changes,
+    /// Bumped on every detected change, debounce only fires for the latest one.
+    pub generation: usize,
+    /// View should be reloaded once currently running build finishes.
+    pub reload: bool,
+}
+
+impl Watch {
+    pub fn new(run: usize, session: String, meta: MetaWatch, root: &str) -> Result<Self, JsValue> {
+        let fingerprint = Fingerprint::scan(root, &meta.patterns())?;
+        Ok(Self {
+            run,
+            session,
+            meta,
+            fingerprint,
+            pending: Default::default(),
+            generation: 0,
+            reload: false,
+        })
+    }
+
+    /// Rescans watched files and returns true if anything changed since last scan.
+    pub fn scan(&mut self, root: &str) -> Result<bool, JsValue> {
+        let fingerprint = Fingerprint::scan(root, &self.meta.patterns())?;
+        let mut changes = Changes::default();
+        for path in fingerprint.changes(&self.fingerprint) {
+            if matches_any(path, &self.meta.sources) {
+                changes.sources = true;
+            } else {
+                changes.assets = true;
+            }
+        }
+        self.fingerprint = fingerprint;
+        if changes.is_empty() {
+            return Ok(false);
+        }
+        self.pending.sources |= changes.sources;
+        self.pending.assets |= changes.assets;
+        self.generation += 1;
+        Ok(true)
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use wasm_bindgen::prelude::*;
 
 pub mod editor {
@@ -19,6 +20,15 @@ pub mod editor {
             log_level: u8,
         ) -> Result<String, JsValue>;
 
+        #[wasm_bindgen(js_namespace = editor, js_name = run_node_with_env, catch)]
+        fn run_node_with_env_inner(
+            name: &str,
+            args: Box<[JsValue]>,
+            blocking: bool,
+            log_level: u8,
+            env: JsValue,
+        ) -> Result<String, JsValue>;
+
         #[wasm_bindgen(js_namespace = editor, catch)]
         pub fn terminate_node(token: &str) -> Result<(), JsValue>;
 
@@ -28,6 +38,8 @@ pub mod editor {
         #[wasm_bindgen(js_namespace = editor, catch)]
         pub fn run_server(directory: &str, port: usize, name: &str) -> Result<String, JsValue>;
 
+        /// `opts` is handed to editor proxy as is, editors without routing
+        /// support ignore it and forward every request to `address`.
         #[wasm_bindgen(js_namespace = editor, catch)]
         pub fn run_proxy_server(
             address: &str,
@@ -68,6 +80,48 @@ pub mod editor {
             .into_boxed_slice();
         run_node_inner(name, args, blocking, log_level)
     }
+
+    /// Tells if editor exposes `run_node_with_env`, able to pass environment variables
+    /// to node process.
+    pub fn run_node_supports_env() -> bool {
+        js_sys::Reflect::get(&js_sys::global(), &"editor".into())
+            .and_then(|editor| js_sys::Reflect::get(&editor, &"run_node_with_env".into()))
+            .map(|run_node_with_env| run_node_with_env.is_function())
+            .unwrap_or(false)
+    }
+
+    /// Same as `run_node` but spawned node process gets additional environment variables.
+    /// Fails when there are any variables and editor does not expose `run_node_with_env`,
+    /// so variables never get silently dropped.
+    pub fn run_node_with_env(
+        name: &str,
+        args: Vec<String>,
+        blocking: bool,
+        log_level: u8,
+        env: &HashMap<String, String>,
+    ) -> Result<String, JsValue> {
+        if env.is_empty() {
+            return run_node(name, args, blocking, log_level);
+        }
+        if !run_node_supports_env() {
+            return Err(format!(
+                "Node `{}` defines environment variables, but this editor version cannot pass them \
+                (missing `editor.run_node_with_env`)",
+                name
+            )
+            .into());
+        }
+        let args = args
+            .into_iter()
+            .map(|arg| arg.into())
+            .collect::<Vec<JsValue>>()
+            .into_boxed_slice();
+        let env = match JsValue::from_serde(env) {
+            Ok(env) => env,
+            Err(error) => return Err(format!("{:?}", error).into()),
+        };
+        run_node_with_env_inner(name, args, blocking, log_level, env)
+    }
 }
 
 pub mod console {
@@ -94,6 +148,7 @@ pub mod console {
 
 pub mod file_system {
     use super::*;
+    use serde::{Deserialize, Serialize};
 
     #[wasm_bindgen]
     extern "C" {
@@ -117,6 +172,9 @@ pub mod file_system {
             project_only: bool,
         ) -> Result<JsValue, JsValue>;
 
+        #[wasm_bindgen(js_namespace = file_system, js_name = stat, catch)]
+        fn stat_inner(path: &str, project_only: bool) -> Result<JsValue, JsValue>;
+
         #[wasm_bindgen(js_namespace = file_system, catch)]
         pub fn request_save(buffer: &[u8], title: &str, extension: &str) -> Result<(), JsValue>;
 
@@ -161,6 +219,44 @@ pub mod file_system {
         }
     }
 
+    /// File size and modification time, as in Node `fs.Stats`.
+    #[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct FileStat {
+        pub size: u64,
+        #[serde(rename = "mtimeMs")]
+        pub modified: f64,
+    }
+
+    /// Tells if editor exposes `file_system.stat`, older versions do not.
+    pub fn stat_supported() -> bool {
+        js_sys::Reflect::get(&js_sys::global(), &"file_system".into())
+            .and_then(|file_system| js_sys::Reflect::get(&file_system, &"stat".into()))
+            .map(|stat| stat.is_function())
+            .unwrap_or(false)
+    }
+
+    /// Fails when file does not exist or when editor does not expose file stats,
+    /// see `stat_supported`.
+    pub fn stat(path: &str, project_only: bool) -> Result<FileStat, JsValue> {
+        if !stat_supported() {
+            return Err("This editor version does not expose `file_system.stat`".into());
+        }
+        match stat_inner(path, project_only)?.into_serde::<FileStat>() {
+            Ok(result) => Ok(result),
+            Err(error) => Err(format!("{:?}", error).into()),
+        }
+    }
+
+    /// File size from its stats, editors without `file_system.stat` get file
+    /// contents read to measure it.
+    pub fn file_size(path: &str, project_only: bool) -> Result<u64, JsValue> {
+        if stat_supported() {
+            stat(path, project_only).map(|file_stat| file_stat.size)
+        } else {
+            read_buffer(path, project_only).map(|buffer| buffer.len() as u64)
+        }
+    }
+
     pub fn request_import(
         title: &str,
         extensions: Vec<String>,
@@ -0,0 +1,448 @@
+use embedded_graphics::{
+    mono_font::{ascii::FONT_10X20, MonoTextStyle},
+    pixelcolor::Rgb888,
+    prelude::*,
+    primitives::{Line, PrimitiveStyle, Rectangle, Triangle},
+    text::{Baseline, Text},
+};
+use ignite_plugin_utils::{
+    file_system::read_string,
+    paths::{basename, project_path, relative_to_project},
+};
+use image::{Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, convert::Infallible};
+use wasm_bindgen::prelude::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetaAnnotate {
+    /// Store project, session, profile, time and git revision in PNG text chunks.
+    #[serde(default = "MetaAnnotate::default_embed_metadata")]
+    pub embed_metadata: bool,
+    /// Text burned into bottom right corner of every saved screenshot.
+    #[serde(default)]
+    pub watermark: Option<String>,
+}
+
+impl Default for MetaAnnotate {
+    fn default() -> Self {
+        Self {
+            embed_metadata: Self::default_embed_metadata(),
+            watermark: None,
+        }
+    }
+}
+
+impl MetaAnnotate {
+    fn default_embed_metadata() -> bool {
+        true
+    }
+}
+
+/// Shape drawn over captured frame, coordinates are in captured frame pixels.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Annotation {
+    Rectangle {
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+        #[serde(default = "default_color")]
+        color: [u8; 3],
+        #[serde(default = "default_thickness")]
+        thickness: u32,
+    },
+    Arrow {
+        from: [i32; 2],
+        to: [i32; 2],
+        #[serde(default = "default_color")]
+        color: [u8; 3],
+        #[serde(default = "default_thickness")]
+        thickness: u32,
+    },
+    Text {
+        x: i32,
+        y: i32,
+        text: String,
+        #[serde(default = "default_color")]
+        color: [u8; 3],
+        #[serde(default)]
+        background: Option<[u8; 3]>,
+    },
+}
+
+fn default_color() -> [u8; 3] {
+    [255, 0, 0]
+}
+
+fn default_thickness() -> u32 {
+    3
+}
+
+const CAPTION_PADDING: i32 = 6;
+
+struct Canvas<'a>(&'a mut RgbaImage);
+
+impl OriginDimensions for Canvas<'_> {
+    fn size(&self) -> Size {
+        Size::new(self.0.width(), self.0.height())
+    }
+}
+
+impl DrawTarget for Canvas<'_> {
+    type Color = Rgb888;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let (width, height) = self.0.dimensions();
+        for Pixel(point, color) in pixels {
+            if point.x >= 0 && point.y >= 0 && (point.x as u32) < width && (point.y as u32) < height
+            {
+                self.0.put_pixel(
+                    point.x as u32,
+                    point.y as u32,
+                    Rgba([color.r(), color.g(), color.b(), 255]),
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+fn color([r, g, b]: [u8; 3]) -> Rgb888 {
+    Rgb888::new(r, g, b)
+}
+
+/// Draws annotations, then caption bar at the bottom and watermark over it.
+pub fn apply(
+    image: &mut RgbaImage,
+    annotations: &[Annotation],
+    caption: Option<&str>,
+    watermark: Option<&str>,
+) {
+    let mut canvas = Canvas(image);
+    for annotation in annotations {
+        draw_annotation(&mut canvas, annotation);
+    }
+    let size = canvas.size();
+    let bar_height = FONT_10X20.character_size.height + CAPTION_PADDING as u32 * 2;
+    let bar_top = size.height as i32 - bar_height as i32;
+    if let Some(caption) = caption {
+        let _ = Rectangle::new(Point::new(0, bar_top), Size::new(size.width, bar_height))
+            .into_styled(PrimitiveStyle::with_fill(Rgb888::BLACK))
+            .draw(&mut canvas);
+        let _ = Text::with_baseline(
+            caption,
+            Point::new(CAPTION_PADDING, bar_top + CAPTION_PADDING),
+            MonoTextStyle::new(&FONT_10X20, Rgb888::WHITE),
+            Baseline::Top,
+        )
+        .draw(&mut canvas);
+    }
+    if let Some(watermark) = watermark {
+        let width = FONT_10X20.character_size.width * watermark.chars().count() as u32;
+        let position = Point::new(
+            size.width as i32 - width as i32 - CAPTION_PADDING,
+            bar_top + CAPTION_PADDING,
+        );
+        let _ = Text::with_baseline(
+            watermark,
+            position + Point::new(1, 1),
+            MonoTextStyle::new(&FONT_10X20, Rgb888::BLACK),
+            Baseline::Top,
+        )
+        .draw(&mut canvas);
+        let _ = Text::with_baseline(
+            watermark,
+            position,
+            MonoTextStyle::new(&FONT_10X20, Rgb888::new(200, 200, 200)),
+            Baseline::Top,
+        )
+        .draw(&mut canvas);
+    }
+}
+
+fn draw_annotation(canvas: &mut Canvas, annotation: &Annotation) {
+    match annotation {
+        Annotation::Rectangle {
+            x,
+            y,
+            width,
+            height,
+            color: c,
+            thickness,
+        } => {
+            let _ = Rectangle::new(Point::new(*x, *y), Size::new(*width, *height))
+                .into_styled(PrimitiveStyle::with_stroke(color(*c), *thickness))
+                .draw(canvas);
+        }
+        Annotation::Arrow {
+            from,
+            to,
+            color: c,
+            thickness,
+        } => {
+            let start = Point::new(from[0], from[1]);
+            let end = Point::new(to[0], to[1]);
+            let _ = Line::new(start, end)
+                .into_styled(PrimitiveStyle::with_stroke(color(*c), *thickness))
+                .draw(canvas);
+            let dx = (end.x - start.x) as f32;
+            let dy = (end.y - start.y) as f32;
+            let length = (dx * dx + dy * dy).sqrt();
+            if length > 0.0 {
+                let (dx, dy) = (dx / length, dy / length);
+                let head = (*thickness as f32 * 3.0 + 8.0).min(length);
+                let base = (end.x as f32 - dx * head, end.y as f32 - dy * head);
+                let side = head * 0.5;
+                let left = Point::new((base.0 - dy * side) as i32, (base.1 + dx * side) as i32);
+                let right = Point::new((base.0 + dy * side) as i32, (base.1 - dx * side) as i32);
+                let _ = Triangle::new(end, left, right)
+                    .into_styled(PrimitiveStyle::with_fill(color(*c)))
+                    .draw(canvas);
+            }
+        }
+        Annotation::Text {
+            x,
+            y,
+            text,
+            color: c,
+            background,
+        } => {
+            let text = Text::with_baseline(
+                text,
+                Point::new(*x, *y),
+                MonoTextStyle::new(&FONT_10X20, color(*c)),
+                Baseline::Top,
+            );
+            if let Some(background) = background {
+                let _ = text
+                    .bounding_box()
+                    .into_styled(PrimitiveStyle::with_fill(color(*background)))
+                    .draw(canvas);
+            }
+            let _ = text.draw(canvas);
+        }
+    }
+}
+
+/// Collects metadata describing capture context, `extra` entries override collected ones.
+pub fn metadata(
+    session: &str,
+    profile: Option<&str>,
+    extra: &BTreeMap<String, String>,
+) -> Result<BTreeMap<String, String>, JsValue> {
+    let mut result = BTreeMap::new();
+    result.insert("Software".to_owned(), "Ignite Editor".to_owned());
+    result.insert("Project".to_owned(), basename(&project_path()?)?);
+    result.insert("Session".to_owned(), session.to_owned());
+    if let Some(profile) = profile {
+        result.insert("Profile".to_owned(), profile.to_owned());
+    }
+    result.insert(
+        "Creation Time".to_owned(),
+        String::from(js_sys::Date::new_0().to_iso_string()),
+    );
+    if let Some(revision) = git_revision() {
+        result.insert("Revision".to_owned(), revision);
+    }
+    result.extend(
+        extra
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone())),
+    );
+    Ok(result)
+}
+
+/// Reads commit hash of checked out revision straight from `.git` directory.
+fn git_revision() -> Option<String> {
+    read_git_revision(|path| read_string(&relative_to_project(path).ok()?, true).ok())
+}
+
+/// Resolves checked out revision, `read` gives contents of project relative file.
+/// Projects without git repository have no revision.
+fn read_git_revision<F>(read: F) -> Option<String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let head = read(".git/HEAD")?;
+    let head = head.trim();
+    let reference = match head.strip_prefix("ref:") {
+        Some(reference) => reference.trim(),
+        None => return Some(head.to_owned()),
+    };
+    if let Some(revision) = read(&format!(".git/{}", reference)) {
+        return Some(revision.trim().to_owned());
+    }
+    let packed = read(".git/packed-refs")?;
+    packed.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        let revision = parts.next()?;
+        if parts.next()? == reference {
+            Some(revision.to_owned())
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    const BACKGROUND: Rgba<u8> = Rgba([10, 10, 10, 255]);
+
+    /// Bounding box (min x, min y, max x, max y) of pixels that differ from background.
+    fn changed(image: &RgbaImage) -> Option<(u32, u32, u32, u32)> {
+        image
+            .enumerate_pixels()
+            .filter(|(_, _, pixel)| **pixel != BACKGROUND)
+            .fold(None, |result, (x, y, _)| match result {
+                Some((a, b, c, d)) => Some((x.min(a), y.min(b), x.max(c), y.max(d))),
+                None => Some((x, y, x, y)),
+            })
+    }
+
+    fn annotated(
+        width: u32,
+        height: u32,
+        annotations: &[Annotation],
+        caption: Option<&str>,
+        watermark: Option<&str>,
+    ) -> RgbaImage {
+        let mut image = RgbaImage::from_pixel(width, height, BACKGROUND);
+        apply(&mut image, annotations, caption, watermark);
+        image
+    }
+
+    #[test]
+    fn test_nothing_to_apply() {
+        assert_eq!(changed(&annotated(50, 50, &[], None, None)), None);
+    }
+
+    #[test]
+    fn test_rectangle() {
+        let image = annotated(
+            100,
+            100,
+            &[Annotation::Rectangle {
+                x: 10,
+                y: 10,
+                width: 30,
+                height: 20,
+                color: [255, 0, 0],
+                thickness: 3,
+            }],
+            None,
+            None,
+        );
+        let (min_x, min_y, max_x, max_y) = changed(&image).unwrap();
+        assert!(min_x >= 8 && min_y >= 8 && max_x <= 41 && max_y <= 31);
+        assert_eq!(image.get_pixel(10, 20).0, [255, 0, 0, 255]);
+        assert_eq!(*image.get_pixel(25, 20), BACKGROUND);
+    }
+
+    #[test]
+    fn test_arrow() {
+        let image = annotated(
+            100,
+            100,
+            &[Annotation::Arrow {
+                from: [10, 60],
+                to: [60, 60],
+                color: [0, 255, 0],
+                thickness: 1,
+            }],
+            None,
+            None,
+        );
+        let (min_x, min_y, max_x, max_y) = changed(&image).unwrap();
+        assert_eq!((min_x, max_x), (10, 60));
+        // head is wider than the line.
+        assert!(min_y < 60 && max_y > 60 && min_y >= 50 && max_y <= 70);
+        assert_eq!(image.get_pixel(30, 60).0, [0, 255, 0, 255]);
+        assert_eq!(*image.get_pixel(30, 55), BACKGROUND);
+    }
+
+    #[test]
+    fn test_text_background() {
+        let image = annotated(
+            100,
+            100,
+            &[Annotation::Text {
+                x: 50,
+                y: 5,
+                text: "AB".to_owned(),
+                color: [255, 255, 255],
+                background: Some([0, 0, 255]),
+            }],
+            None,
+            None,
+        );
+        assert_eq!(changed(&image), Some((50, 5, 69, 24)));
+        assert!(image.pixels().any(|pixel| pixel.0 == [0, 0, 255, 255]));
+        assert!(image.pixels().any(|pixel| pixel.0 == [255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn test_caption_and_watermark() {
+        let image = annotated(200, 60, &[], Some("Level 1"), None);
+        // 20 pixels font with padding of 6 above and below.
+        assert_eq!(changed(&image), Some((0, 28, 199, 59)));
+        assert_eq!(image.get_pixel(199, 59).0, [0, 0, 0, 255]);
+        let image = annotated(200, 60, &[], None, Some("W"));
+        let (min_x, min_y, max_x, max_y) = changed(&image).unwrap();
+        assert!(min_x >= 184 && max_x <= 194, "{} {}", min_x, max_x);
+        assert!(min_y >= 34 && max_y <= 54, "{} {}", min_y, max_y);
+    }
+
+    fn files(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(path, contents)| (path.to_string(), contents.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_git_revision() {
+        let repository = files(&[
+            (".git/HEAD", "ref: refs/heads/master\n"),
+            (".git/refs/heads/master", "0123abcd\n"),
+        ]);
+        assert_eq!(
+            read_git_revision(|path| repository.get(path).cloned()).as_deref(),
+            Some("0123abcd")
+        );
+        let detached = files(&[(".git/HEAD", "4567ef01\n")]);
+        assert_eq!(
+            read_git_revision(|path| detached.get(path).cloned()).as_deref(),
+            Some("4567ef01")
+        );
+        let packed = files(&[
+            (".git/HEAD", "ref: refs/heads/main"),
+            (
+                ".git/packed-refs",
+                "# pack-refs with: peeled fully-peeled sorted\n\
+                 89ab0000 refs/heads/feature\n\
+                 cdef1111 refs/heads/main\n",
+            ),
+        ]);
+        assert_eq!(
+            read_git_revision(|path| packed.get(path).cloned()).as_deref(),
+            Some("cdef1111")
+        );
+    }
+
+    #[test]
+    fn test_git_revision_without_git() {
+        assert_eq!(read_git_revision(|_| None), None);
+        // branch without any commits yet.
+        let empty = files(&[(".git/HEAD", "ref: refs/heads/master")]);
+        assert_eq!(read_git_revision(|path| empty.get(path).cloned()), None);
+    }
+}
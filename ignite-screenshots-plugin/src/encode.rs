@@ -0,0 +1,390 @@
+use crate::webp;
+use image::{
+    codecs::jpeg::JpegEncoder, imageops::FilterType, ColorType, DynamicImage, GenericImageView,
+    RgbaImage,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Output image format. PNG is lossless, JPEG is lossy and WebP is lossless
+/// unless quality is set.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    #[default]
+    Png,
+    Jpeg,
+    Webp,
+}
+
+impl ImageFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+            Self::Webp => "webp",
+        }
+    }
+
+    pub fn supports_alpha(self) -> bool {
+        !matches!(self, Self::Jpeg)
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlphaMode {
+    /// Keep alpha channel when format supports it, otherwise flatten.
+    #[default]
+    Keep,
+    /// Blend image over `background` color.
+    Flatten,
+    /// Make every pixel fully opaque without blending.
+    Discard,
+}
+
+/// Target size of saved image, either one of named store page presets or
+/// explicit width with optional height.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Resize {
+    Preset(String),
+    Size {
+        width: u32,
+        /// When set image gets cropped to exact size, otherwise aspect ratio is kept.
+        #[serde(default)]
+        height: Option<u32>,
+    },
+}
+
+impl Resize {
+    /// (name, width, height)
+    pub const PRESETS: &'static [(&'static str, u32, u32)] = &[
+        ("itch-cover", 630, 500),
+        ("steam-header", 460, 215),
+        ("steam-capsule", 616, 353),
+        ("steam-screenshot", 1920, 1080),
+        ("social-card", 1200, 630),
+    ];
+
+    fn size(&self) -> Option<(u32, Option<u32>)> {
+        match self {
+            Self::Preset(name) => Self::PRESETS
+                .iter()
+                .find(|(preset, _, _)| preset == name)
+                .map(|(_, width, height)| (*width, Some(*height))),
+            Self::Size { width, height } => Some((*width, *height)),
+        }
+    }
+
+    pub fn apply(&self, image: &DynamicImage) -> Result<DynamicImage, String> {
+        match self.size() {
+            Some((width, Some(height))) => {
+                Ok(image.resize_to_fill(width.max(1), height.max(1), FilterType::Lanczos3))
+            }
+            Some((width, None)) => {
+                let height = (image.height() as u64 * width as u64 / image.width().max(1) as u64)
+                    .max(1) as u32;
+                Ok(image.resize_exact(width.max(1), height, FilterType::Lanczos3))
+            }
+            None => Err(format!("Unknown resize preset: {:?}", self)),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct MetaOutput {
+    #[serde(default)]
+    pub format: ImageFormat,
+    /// Quality in range 1-100. JPEG uses 90 when not set, WebP switches from
+    /// lossless to lossy when set and PNG rejects it.
+    #[serde(default)]
+    pub quality: Option<u8>,
+    #[serde(default)]
+    pub resize: Option<Resize>,
+    #[serde(default)]
+    pub alpha: AlphaMode,
+    /// RGB color used when flattening alpha.
+    #[serde(default)]
+    pub background: [u8; 3],
+}
+
+impl MetaOutput {
+    const DEFAULT_QUALITY: u8 = 90;
+
+    /// Resizes, handles alpha and encodes image into output format. Metadata is
+    /// stored only in PNG text chunks, other formats ignore it.
+    pub fn encode(
+        &self,
+        image: &DynamicImage,
+        metadata: &BTreeMap<String, String>,
+    ) -> Result<Vec<u8>, String> {
+        if self.quality.is_some() && self.format == ImageFormat::Png {
+            return Err("Quality is not supported by PNG output, it is always lossless".to_owned());
+        }
+        let resized;
+        let image = match &self.resize {
+            Some(resize) => {
+                resized = resize.apply(image)?;
+                &resized
+            }
+            None => image,
+        };
+        let alpha = if self.format.supports_alpha() {
+            self.alpha
+        } else if self.alpha == AlphaMode::Discard {
+            AlphaMode::Discard
+        } else {
+            AlphaMode::Flatten
+        };
+        let mut image = image.to_rgba8();
+        match alpha {
+            AlphaMode::Keep => {}
+            AlphaMode::Flatten => flatten(&mut image, self.background),
+            AlphaMode::Discard => image.pixels_mut().for_each(|pixel| pixel.0[3] = 255),
+        }
+        let mut result = vec![];
+        match self.format {
+            ImageFormat::Png => encode_png(&mut result, &image, metadata)?,
+            ImageFormat::Jpeg => {
+                let image = DynamicImage::ImageRgba8(image).to_rgb8();
+                let quality = self.quality.unwrap_or(Self::DEFAULT_QUALITY);
+                JpegEncoder::new_with_quality(&mut result, quality.clamp(1, 100))
+                    .encode(
+                        image.as_raw(),
+                        image.width(),
+                        image.height(),
+                        ColorType::Rgb8,
+                    )
+                    .map_err(|error| format!("{:?}", error))?
+            }
+            ImageFormat::Webp => match self.quality {
+                Some(quality) => {
+                    result =
+                        webp::encode_lossy(image.as_raw(), image.width(), image.height(), quality)?
+                }
+                None => image_webp::WebPEncoder::new(&mut result)
+                    .encode(
+                        image.as_raw(),
+                        image.width(),
+                        image.height(),
+                        image_webp::ColorType::Rgba8,
+                    )
+                    .map_err(|error| format!("{:?}", error))?,
+            },
+        }
+        Ok(result)
+    }
+}
+
+fn encode_png(
+    output: &mut Vec<u8>,
+    image: &RgbaImage,
+    metadata: &BTreeMap<String, String>,
+) -> Result<(), String> {
+    let mut encoder = png::Encoder::new(output, image.width(), image.height());
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    for (key, value) in metadata {
+        encoder
+            .add_itxt_chunk(key.to_owned(), value.to_owned())
+            .map_err(|error| format!("{:?}", error))?;
+    }
+    let mut writer = encoder
+        .write_header()
+        .map_err(|error| format!("{:?}", error))?;
+    writer
+        .write_image_data(image.as_raw())
+        .map_err(|error| format!("{:?}", error))?;
+    writer.finish().map_err(|error| format!("{:?}", error))
+}
+
+fn flatten(image: &mut RgbaImage, background: [u8; 3]) {
+    for pixel in image.pixels_mut() {
+        let alpha = pixel.0[3] as u32;
+        for (channel, background) in pixel.0.iter_mut().take(3).zip(background.iter()) {
+            *channel = ((*channel as u32 * alpha + *background as u32 * (255 - alpha)) / 255) as u8;
+        }
+        pixel.0[3] = 255;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn image() -> DynamicImage {
+        let mut image = RgbaImage::new(16, 8);
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            *pixel = Rgba([
+                (x * 16) as u8,
+                (y * 32) as u8,
+                128,
+                if x < 8 { 255 } else { 64 },
+            ]);
+        }
+        DynamicImage::ImageRgba8(image)
+    }
+
+    fn output(format: ImageFormat) -> MetaOutput {
+        MetaOutput {
+            format,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_png_round_trip() {
+        let source = image();
+        let data = output(ImageFormat::Png)
+            .encode(&source, &BTreeMap::new())
+            .unwrap();
+        let decoded = image::load_from_memory(&data).unwrap().to_rgba8();
+        assert_eq!(&decoded, source.as_rgba8().unwrap());
+    }
+
+    #[test]
+    fn test_jpeg_round_trip() {
+        let source = image();
+        let mut meta = output(ImageFormat::Jpeg);
+        meta.alpha = AlphaMode::Discard;
+        let data = meta.encode(&source, &BTreeMap::new()).unwrap();
+        let decoded = image::load_from_memory(&data).unwrap().to_rgba8();
+        assert_eq!(decoded.dimensions(), (16, 8));
+        for (a, b) in decoded.pixels().zip(source.to_rgba8().pixels()) {
+            assert_eq!(a.0[3], 255);
+            for channel in 0..3 {
+                assert!((a.0[channel] as i32 - b.0[channel] as i32).abs() <= 24);
+            }
+        }
+        meta.quality = Some(10);
+        let low = meta.encode(&source, &BTreeMap::new()).unwrap();
+        assert!(low.len() < data.len());
+    }
+
+    #[test]
+    fn test_webp_round_trip() {
+        let source = image();
+        let data = output(ImageFormat::Webp)
+            .encode(&source, &BTreeMap::new())
+            .unwrap();
+        let mut decoder = image_webp::WebPDecoder::new(std::io::Cursor::new(data)).unwrap();
+        assert_eq!(decoder.dimensions(), (16, 8));
+        let mut pixels = vec![0; decoder.output_buffer_size().unwrap()];
+        decoder.read_image(&mut pixels).unwrap();
+        assert_eq!(&pixels, source.as_rgba8().unwrap().as_raw());
+    }
+
+    /// Several macroblocks with smooth areas and sharp edges, last ones partial.
+    fn lossy_image() -> DynamicImage {
+        let mut image = RgbaImage::new(40, 36);
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            let edge = if (x / 10 + y / 9) % 2 == 0 { 0 } else { 64 };
+            *pixel = Rgba([
+                (x * 4) as u8 + edge,
+                (y * 4) as u8 + edge,
+                191 - (x * 3) as u8 + edge,
+                if y < 18 { 255 } else { (x * 6) as u8 },
+            ]);
+        }
+        DynamicImage::ImageRgba8(image)
+    }
+
+    fn decode_webp(data: Vec<u8>) -> (bool, Vec<u8>) {
+        let mut decoder = image_webp::WebPDecoder::new(std::io::Cursor::new(data)).unwrap();
+        assert_eq!(decoder.dimensions(), (40, 36));
+        let mut pixels = vec![0; decoder.output_buffer_size().unwrap()];
+        decoder.read_image(&mut pixels).unwrap();
+        (decoder.is_lossy(), pixels)
+    }
+
+    #[test]
+    fn test_lossy_webp_round_trip() {
+        let source = lossy_image();
+        let mut meta = output(ImageFormat::Webp);
+        meta.quality = Some(90);
+        let data = meta.encode(&source, &BTreeMap::new()).unwrap();
+        let (lossy, pixels) = decode_webp(data.clone());
+        assert!(lossy);
+        let mut error = 0;
+        for (a, b) in pixels.chunks(4).zip(source.as_rgba8().unwrap().pixels()) {
+            error += a
+                .iter()
+                .zip(b.0.iter())
+                .take(3)
+                .map(|(a, b)| (*a as i32 - *b as i32).abs())
+                .sum::<i32>();
+            assert_eq!(a[3], b.0[3]);
+        }
+        assert!(error / (40 * 36 * 3) <= 6);
+        meta.quality = Some(10);
+        let low = meta.encode(&source, &BTreeMap::new()).unwrap();
+        assert!(low.len() < data.len());
+        decode_webp(low);
+    }
+
+    #[test]
+    fn test_lossy_webp_without_alpha() {
+        let mut meta = output(ImageFormat::Webp);
+        meta.quality = Some(75);
+        meta.alpha = AlphaMode::Discard;
+        let data = meta.encode(&lossy_image(), &BTreeMap::new()).unwrap();
+        assert_eq!(&data[12..16], b"VP8 ");
+        let (_, pixels) = decode_webp(data);
+        assert_eq!(pixels.len(), 40 * 36 * 3);
+    }
+
+    #[test]
+    fn test_quality_is_rejected_for_png() {
+        let meta = MetaOutput {
+            format: ImageFormat::Png,
+            quality: Some(80),
+            ..Default::default()
+        };
+        assert!(meta.encode(&image(), &BTreeMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_resize_presets() {
+        let source = image();
+        let resized = Resize::Preset("social-card".to_owned())
+            .apply(&source)
+            .unwrap();
+        assert_eq!(resized.dimensions(), (1200, 630));
+        let resized = Resize::Size {
+            width: 8,
+            height: None,
+        }
+        .apply(&source)
+        .unwrap();
+        assert_eq!(resized.dimensions(), (8, 4));
+        assert!(Resize::Preset("unknown".to_owned()).apply(&source).is_err());
+    }
+
+    #[test]
+    fn test_flatten() {
+        let mut image = RgbaImage::from_pixel(1, 1, Rgba([255, 0, 0, 0]));
+        flatten(&mut image, [0, 0, 255]);
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn test_png_metadata_round_trip() {
+        let mut metadata = BTreeMap::new();
+        metadata.insert("Session".to_owned(), "default".to_owned());
+        metadata.insert("Description".to_owned(), "Zażółć gęślą jaźń".to_owned());
+        let data = output(ImageFormat::Png)
+            .encode(&image(), &metadata)
+            .unwrap();
+        let decoder = png::Decoder::new(std::io::Cursor::new(data));
+        let reader = decoder.read_info().unwrap();
+        let decoded = reader
+            .info()
+            .utf8_text
+            .iter()
+            .map(|chunk| (chunk.keyword.clone(), chunk.get_text().unwrap()))
+            .collect::<BTreeMap<_, _>>();
+        assert_eq!(decoded, metadata);
+    }
+}
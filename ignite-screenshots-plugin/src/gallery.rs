@@ -0,0 +1,274 @@
+use ignite_plugin_utils::{
+    file_system::{read_buffer, scan_dir},
+    paths::{basename, project_path, relative},
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, io::Cursor};
+use wasm_bindgen::prelude::*;
+
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp", "gif"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetaGallery {
+    /// Write screenshots straight into `directory` instead of asking where to save them.
+    #[serde(default)]
+    pub auto_save: bool,
+    /// Project relative directory with captured screenshots.
+    #[serde(default = "MetaGallery::default_directory")]
+    pub directory: String,
+    /// Supports `{project}`, `{session}`, `{date}`, `{time}` and `{counter}` placeholders.
+    #[serde(default = "MetaGallery::default_template")]
+    pub template: String,
+    /// Put screenshots of each play session into its own subfolder.
+    #[serde(default)]
+    pub session_folders: bool,
+}
+
+impl Default for MetaGallery {
+    fn default() -> Self {
+        Self {
+            auto_save: false,
+            directory: Self::default_directory(),
+            template: Self::default_template(),
+            session_folders: false,
+        }
+    }
+}
+
+impl MetaGallery {
+    fn default_directory() -> String {
+        "screenshots".to_owned()
+    }
+
+    fn default_template() -> String {
+        "{project}_{date}_{time}_{counter}.png".to_owned()
+    }
+
+    /// Project relative directory that screenshots of given session go to.
+    pub fn session_directory(&self, session: &str) -> String {
+        if self.session_folders {
+            format!("{}/{}", self.directory.trim_end_matches('/'), session)
+        } else {
+            self.directory.trim_end_matches('/').to_owned()
+        }
+    }
+
+    /// Makes file name out of template, counter is bumped until name is not taken
+    /// by any of `existing` files. Extension from template is replaced with `extension`.
+    pub fn file_name(
+        &self,
+        session: &str,
+        extension: &str,
+        existing: &HashSet<String>,
+    ) -> Result<String, JsValue> {
+        let project = basename(&project_path()?)?;
+        let now = js_sys::Date::new_0();
+        let date = format!(
+            "{:04}-{:02}-{:02}",
+            now.get_full_year(),
+            now.get_month() + 1,
+            now.get_date()
+        );
+        let time = format!(
+            "{:02}-{:02}-{:02}",
+            now.get_hours(),
+            now.get_minutes(),
+            now.get_seconds()
+        );
+        Ok(self.format_name(
+            [
+                ("{project}", project.as_str()),
+                ("{session}", session),
+                ("{date}", &date),
+                ("{time}", &time),
+            ],
+            extension,
+            existing,
+        ))
+    }
+
+    /// (placeholder, value)
+    fn format_name(
+        &self,
+        values: [(&str, &str); 4],
+        extension: &str,
+        existing: &HashSet<String>,
+    ) -> String {
+        let template = values
+            .iter()
+            .fold(self.template.clone(), |template, (placeholder, value)| {
+                template.replace(placeholder, value)
+            });
+        let template = match template.rfind('.') {
+            Some(index) if IMAGE_EXTENSIONS.contains(&&template[(index + 1)..]) => {
+                format!("{}.{}", &template[..index], extension)
+            }
+            _ => format!("{}.{}", template, extension),
+        };
+        if !template.contains("{counter}") {
+            return template;
+        }
+        let mut counter = 1;
+        loop {
+            let name = template.replace("{counter}", &format!("{:04}", counter));
+            if !existing.contains(&name) {
+                return name;
+            }
+            counter += 1;
+        }
+    }
+}
+
+/// Names of files in given directory, empty when it does not exist yet.
+pub fn existing_files(directory: &str) -> HashSet<String> {
+    scan_dir(directory, false, true)
+        .map(|files| {
+            files
+                .into_iter()
+                .filter(|(_, _, is_directory)| !is_directory)
+                .map(|(name, _, _)| name)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GalleryEntry {
+    pub name: String,
+    /// Project relative path.
+    pub path: String,
+    /// Session subfolder the screenshot was found in.
+    pub session: Option<String>,
+    pub size: usize,
+    /// Missing for formats that could not be decoded.
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+impl GalleryEntry {
+    pub fn new(name: String, path: String, session: Option<String>, data: &[u8]) -> Self {
+        let dimensions = image::io::Reader::new(Cursor::new(data))
+            .with_guessed_format()
+            .ok()
+            .and_then(|reader| reader.into_dimensions().ok());
+        Self {
+            name,
+            path,
+            session,
+            size: data.len(),
+            width: dimensions.map(|(width, _)| width),
+            height: dimensions.map(|(_, height)| height),
+        }
+    }
+}
+
+/// Lists images captured into gallery directory, including session subfolders.
+pub fn list(meta: &MetaGallery) -> Result<Vec<GalleryEntry>, JsValue> {
+    let project = project_path()?;
+    let directory = format!("{}/{}", project, meta.directory.trim_end_matches('/'));
+    let files = scan_dir(&directory, true, true).unwrap_or_default();
+    let mut result = files
+        .into_iter()
+        .filter(|(name, _, is_directory)| {
+            !is_directory
+                && name
+                    .rsplit('.')
+                    .next()
+                    .map(|extension| IMAGE_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+                    .unwrap_or(false)
+        })
+        .filter_map(|(name, path, _)| {
+            let inner = relative(&directory, &path).ok()?.replace('\\', "/");
+            let session = inner.rfind('/').map(|index| inner[..index].to_owned());
+            let data = read_buffer(&path, true).ok()?;
+            let path = relative(&project, &path).ok()?.replace('\\', "/");
+            Some(GalleryEntry::new(name, path, session, &data))
+        })
+        .collect::<Vec<_>>();
+    result.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALUES: [(&str, &str); 4] = [
+        ("{project}", "game"),
+        ("{session}", "default"),
+        ("{date}", "2020-01-02"),
+        ("{time}", "03-04-05"),
+    ];
+
+    fn gallery(template: &str) -> MetaGallery {
+        MetaGallery {
+            template: template.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_placeholders() {
+        let name = MetaGallery::default().format_name(VALUES, "png", &HashSet::new());
+        assert_eq!(name, "game_2020-01-02_03-04-05_0001.png");
+        let name = gallery("{session}/{project}").format_name(VALUES, "jpg", &HashSet::new());
+        assert_eq!(name, "default/game.jpg");
+    }
+
+    #[test]
+    fn test_extension_is_replaced() {
+        let existing = HashSet::new();
+        assert_eq!(
+            gallery("shot.png").format_name(VALUES, "webp", &existing),
+            "shot.webp"
+        );
+        assert_eq!(
+            gallery("shot.v1").format_name(VALUES, "png", &existing),
+            "shot.v1.png"
+        );
+    }
+
+    #[test]
+    fn test_counter_skips_existing_files() {
+        let existing = vec!["shot_0001.png".to_owned(), "shot_0002.png".to_owned()]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            gallery("shot_{counter}.png").format_name(VALUES, "png", &existing),
+            "shot_0003.png"
+        );
+        assert_eq!(
+            gallery("shot_{counter}.png").format_name(VALUES, "jpg", &existing),
+            "shot_0001.jpg"
+        );
+        // without counter existing files get overwritten.
+        assert_eq!(
+            gallery("shot_0001.png").format_name(VALUES, "png", &existing),
+            "shot_0001.png"
+        );
+    }
+
+    #[test]
+    fn test_session_directory() {
+        let mut meta = MetaGallery {
+            directory: "screenshots/".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(meta.session_directory("default"), "screenshots");
+        meta.session_folders = true;
+        assert_eq!(meta.session_directory("default"), "screenshots/default");
+    }
+
+    #[test]
+    fn test_entry_dimensions() {
+        let mut data = vec![];
+        image::DynamicImage::new_rgba8(3, 2)
+            .write_to(&mut data, image::ImageOutputFormat::Png)
+            .unwrap();
+        let entry = GalleryEntry::new("a.png".to_owned(), "a.png".to_owned(), None, &data);
+        assert_eq!((entry.width, entry.height), (Some(3), Some(2)));
+        assert_eq!(entry.size, data.len());
+        let entry = GalleryEntry::new("b.png".to_owned(), "b.png".to_owned(), None, b"broken");
+        assert_eq!((entry.width, entry.height), (None, None));
+    }
+}
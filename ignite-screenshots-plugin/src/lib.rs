@@ -1,34 +1,424 @@
+#[macro_use]
+extern crate lazy_static;
+
+mod annotate;
+mod encode;
+mod gallery;
+mod preview;
+mod recording;
+mod regression;
+mod webp;
+
+use crate::{
+    annotate::{Annotation, MetaAnnotate},
+    encode::MetaOutput,
+    gallery::{GalleryEntry, MetaGallery},
+    preview::MetaPreview,
+    recording::{MetaRecording, Recording},
+    regression::MetaRegression,
+};
 use ignite_plugin_utils::{
-    file_system::{request_save, write_buffer},
-    paths::relative_to_project,
+    editor::{emit, get_plugin_meta, ignite_delayed},
+    file_system::{read_buffer, request_save, write_buffer},
+    paths::{project_path, relative, relative_to_project},
+};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        RwLock,
+    },
 };
 use wasm_bindgen::prelude::*;
 
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct Meta {
+    #[serde(default)]
+    pub preview: MetaPreview,
+    #[serde(default)]
+    pub gallery: MetaGallery,
+    /// Default encoding of saved screenshots.
+    #[serde(default)]
+    pub output: MetaOutput,
+    #[serde(default)]
+    pub recording: MetaRecording,
+    #[serde(default)]
+    pub regression: MetaRegression,
+    #[serde(default)]
+    pub annotate: MetaAnnotate,
+}
+
+/// Captured frame compared against baseline of given name.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PayloadRegression {
+    pub image: String,
+    pub name: String,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct PayloadRecordingStart {
+    #[serde(default)]
+    pub session: Option<String>,
+    /// Overrides recording settings from plugin meta.
+    #[serde(default)]
+    pub recording: Option<MetaRecording>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordingStatus {
+    #[serde(rename(serialize = "isRecording", deserialize = "isRecording"))]
+    pub is_recording: bool,
+    pub session: String,
+    pub fps: u32,
+    pub frames: usize,
+}
+
+/// Captured frame as PNG data URL, or capture request with extra options.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum PayloadScreenshot {
+    Image(String),
+    Capture(Capture),
+}
+
+impl PayloadScreenshot {
+    fn into_capture(self) -> Capture {
+        match self {
+            Self::Image(image) => Capture {
+                image,
+                ..Default::default()
+            },
+            Self::Capture(capture) => capture,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct Capture {
+    pub image: String,
+    #[serde(default)]
+    pub session: Option<String>,
+    /// Overrides encoding from plugin meta.
+    #[serde(default)]
+    pub output: Option<MetaOutput>,
+    #[serde(default)]
+    pub caption: Option<String>,
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+    /// Extra entries embedded into PNG text chunks.
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl Capture {
+    fn session(&self) -> &str {
+        self.session.as_deref().unwrap_or(DEFAULT_SESSION)
+    }
+}
+
+/// Build profile of play mode session, broadcasted by play mode plugin.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PayloadBuildProfile {
+    pub session: String,
+    pub profile: String,
+}
+
+lazy_static! {
+    /// Last written preview with the path it was written to.
+    static ref LAST_PREVIEW: RwLock<Option<(String, DynamicImage)>> = RwLock::new(None);
+    static ref SESSIONS: RwLock<HashSet<String>> = RwLock::new(Default::default());
+    static ref RECORDING: RwLock<Option<Recording>> = RwLock::new(None);
+    static ref PROFILES: RwLock<HashMap<String, String>> = RwLock::new(Default::default());
+}
+
+static PREVIEW_RUN: AtomicUsize = AtomicUsize::new(0);
+
+const PLUGIN_NAME: &str = "ignite-screenshots-plugin";
+const DEFAULT_SESSION: &str = "default";
+
 #[wasm_bindgen]
 pub fn query(query: &str, data: JsValue) -> Result<(), JsValue> {
     match query {
         "screenshot" | "screenshot-preview" => {
-            if let Ok(data) = data.into_serde::<String>() {
-                if data.starts_with("data:image/png;base64,") {
-                    if let Ok(data) = base64::decode(&data[22..]) {
+            if let Ok(data) = data.into_serde::<PayloadScreenshot>() {
+                let capture = data.into_capture();
+                if let Some(data) = capture.image.strip_prefix("data:image/png;base64,") {
+                    if let Ok(data) = base64::decode(data) {
+                        if let Ok(image) = image::load_from_memory(&data) {
+                            let meta = meta()?;
+                            update_preview(&image, &meta.preview)?;
+                            if query == "screenshot" {
+                                save_screenshot(image, &capture, meta)?;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        "recording-start" => {
+            stop_recording()?;
+            let payload = data
+                .into_serde::<PayloadRecordingStart>()
+                .unwrap_or_default();
+            let meta = payload.recording.unwrap_or(meta()?.recording);
+            let session = payload
+                .session
+                .unwrap_or_else(|| DEFAULT_SESSION.to_owned());
+            let recording = Recording::new(session, meta, js_sys::Date::now());
+            emit_recording_status(Some(&recording))?;
+            if let Ok(mut current) = RECORDING.write() {
+                *current = Some(recording);
+            }
+        }
+        "recording-frame" => {
+            if let Ok(data) = data.into_serde::<PayloadScreenshot>() {
+                let capture = data.into_capture();
+                if let Some(data) = capture.image.strip_prefix("data:image/png;base64,") {
+                    if let Ok(data) = base64::decode(data) {
                         if let Ok(image) = image::load_from_memory(&data) {
-                            let mut result = vec![];
-                            if image
-                                .thumbnail(512, 512)
-                                .write_to(&mut result, image::ImageOutputFormat::Png)
-                                .is_ok()
-                            {
-                                write_buffer(&relative_to_project("preview.png")?, &result, true)?;
+                            let now = js_sys::Date::now();
+                            let over = if let Ok(mut recording) = RECORDING.write() {
+                                if let Some(recording) = recording.as_mut() {
+                                    recording.push(now, &image).map(|_| recording.is_over(now))
+                                } else {
+                                    Ok(false)
+                                }
+                            } else {
+                                Ok(false)
+                            };
+                            match over {
+                                Ok(true) => stop_recording()?,
+                                Ok(false) => {}
+                                Err(error) => {
+                                    // keep frames recorded so far.
+                                    stop_recording()?;
+                                    return Err(error.into());
+                                }
                             }
                         }
-                        if query == "screenshot" {
-                            request_save(&data, "Save screenshot", "png")?;
+                    }
+                }
+            }
+        }
+        "recording-stop" => stop_recording()?,
+        "regression-compare" => {
+            if let Ok(data) = data.into_serde::<PayloadRegression>() {
+                if let Some(image) = data.image.strip_prefix("data:image/png;base64,") {
+                    if let Ok(image) = base64::decode(image) {
+                        if let Ok(image) = image::load_from_memory(&image) {
+                            let result = regression::run(&data.name, &image, &meta()?.regression)?;
+                            if let Ok(result) = JsValue::from_serde(&result) {
+                                emit("regression", result)?;
+                            }
                         }
                     }
                 }
             }
         }
+        "regression-accept" => {
+            if let Ok(name) = data.into_serde::<String>() {
+                regression::accept(&name, &meta()?.regression)?;
+                emit("regression-accepted", JsValue::from_str(&name))?;
+            }
+        }
+        "play-mode-build-profile" => {
+            if let Ok(data) = data.into_serde::<PayloadBuildProfile>() {
+                if let Ok(mut profiles) = PROFILES.write() {
+                    profiles.insert(data.session, data.profile);
+                }
+            }
+        }
+        "gallery" => {
+            let entries = gallery::list(&meta()?.gallery)?;
+            if let Ok(entries) = JsValue::from_serde(&entries) {
+                emit("gallery", entries)?;
+            }
+        }
+        "play-mode-start" => {
+            if let Ok(id) = data.into_serde::<String>() {
+                if let Ok(mut sessions) = SESSIONS.write() {
+                    sessions.insert(id);
+                    if sessions.len() == 1 {
+                        let run = PREVIEW_RUN.fetch_add(1, Ordering::Relaxed) + 1;
+                        schedule_preview(run, &meta()?.preview);
+                    }
+                }
+            }
+        }
+        "play-mode-stop" => {
+            if let Ok(id) = data.into_serde::<String>() {
+                if let Ok(mut sessions) = SESSIONS.write() {
+                    sessions.remove(&id);
+                    if sessions.is_empty() {
+                        PREVIEW_RUN.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            }
+        }
+        "preview-tick" => {
+            if data.as_f64() != Some(PREVIEW_RUN.load(Ordering::Relaxed) as f64) {
+                return Ok(());
+            }
+            let meta = meta()?.preview;
+            if meta.enabled {
+                emit("preview-request", JsValue::NULL)?;
+            }
+            schedule_preview(PREVIEW_RUN.load(Ordering::Relaxed), &meta);
+        }
         _ => {}
     }
     Ok(())
 }
+
+fn meta() -> Result<Meta, JsValue> {
+    Ok(get_plugin_meta()?.into_serde::<Meta>().unwrap_or_default())
+}
+
+fn schedule_preview(run: usize, meta: &MetaPreview) {
+    ignite_delayed(
+        PLUGIN_NAME,
+        "preview-tick",
+        JsValue::from(run as u32),
+        meta.interval(),
+    );
+}
+
+/// Burns annotations in, embeds metadata and saves screenshot.
+fn save_screenshot(image: DynamicImage, capture: &Capture, meta: Meta) -> Result<(), JsValue> {
+    let session = capture.session();
+    let watermark = meta.annotate.watermark.as_deref();
+    let image =
+        if !capture.annotations.is_empty() || capture.caption.is_some() || watermark.is_some() {
+            let mut image = image.to_rgba8();
+            annotate::apply(
+                &mut image,
+                &capture.annotations,
+                capture.caption.as_deref(),
+                watermark,
+            );
+            DynamicImage::ImageRgba8(image)
+        } else {
+            image
+        };
+    let metadata = if meta.annotate.embed_metadata {
+        let profile = PROFILES
+            .read()
+            .ok()
+            .and_then(|profiles| profiles.get(session).cloned());
+        let mut metadata = annotate::metadata(session, profile.as_deref(), &capture.metadata)?;
+        if let Some(caption) = &capture.caption {
+            metadata.insert("Description".to_owned(), caption.to_owned());
+        }
+        metadata
+    } else {
+        Default::default()
+    };
+    let output = capture.output.clone().unwrap_or(meta.output);
+    let data = output.encode(&image, &metadata)?;
+    save_capture(
+        &data,
+        output.format.extension(),
+        "Save screenshot",
+        session,
+        &meta.gallery,
+    )
+}
+
+fn emit_recording_status(recording: Option<&Recording>) -> Result<(), JsValue> {
+    let status = RecordingStatus {
+        is_recording: recording.is_some(),
+        session: recording
+            .map(|recording| recording.session.clone())
+            .unwrap_or_default(),
+        fps: recording.map(|recording| recording.meta.fps).unwrap_or(0),
+        frames: recording
+            .map(|recording| recording.frames_count())
+            .unwrap_or(0),
+    };
+    if let Ok(status) = JsValue::from_serde(&status) {
+        emit("recording", status)?;
+    }
+    Ok(())
+}
+
+/// Finishes current recording, if any, and saves it as animation.
+fn stop_recording() -> Result<(), JsValue> {
+    let recording = match RECORDING.write() {
+        Ok(mut recording) => recording.take(),
+        Err(_) => None,
+    };
+    if let Some(recording) = recording {
+        emit_recording_status(None)?;
+        let session = recording.session.clone();
+        let extension = recording.meta.format.extension();
+        let data = recording.encode(js_sys::Date::now())?;
+        save_capture(
+            &data,
+            extension,
+            "Save recording",
+            &session,
+            &meta()?.gallery,
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes capture into gallery when auto save is enabled, otherwise asks user where to save it.
+fn save_capture(
+    data: &[u8],
+    extension: &str,
+    title: &str,
+    session: &str,
+    meta: &MetaGallery,
+) -> Result<(), JsValue> {
+    if !meta.auto_save {
+        return request_save(data, title, extension);
+    }
+    let directory = relative_to_project(&meta.session_directory(session))?;
+    let name = meta.file_name(session, extension, &gallery::existing_files(&directory))?;
+    let path = format!("{}/{}", directory, name);
+    write_buffer(&path, data, true)?;
+    let relative_path = relative(&project_path()?, &path)?.replace('\\', "/");
+    let session = if meta.session_folders {
+        Some(session.to_owned())
+    } else {
+        None
+    };
+    let entry = GalleryEntry::new(name, relative_path, session, data);
+    if let Ok(entry) = JsValue::from_serde(&entry) {
+        emit("screenshot-saved", entry)?;
+    }
+    Ok(())
+}
+
+/// Writes project preview unless it is disabled or the last one looks the same.
+fn update_preview(image: &DynamicImage, meta: &MetaPreview) -> Result<(), JsValue> {
+    if !meta.enabled {
+        return Ok(());
+    }
+    let path = relative_to_project(&meta.path())?;
+    let thumbnail = meta.thumbnail(image);
+    if let Ok(mut last) = LAST_PREVIEW.write() {
+        if last
+            .as_ref()
+            .map(|(last_path, _)| last_path != &path)
+            .unwrap_or(true)
+        {
+            *last = read_buffer(&path, true)
+                .ok()
+                .and_then(|data| image::load_from_memory(&data).ok())
+                .map(|image| (path.clone(), image));
+        }
+        let previous = last.as_ref().map(|(_, image)| image);
+        if !preview::should_update(previous, &thumbnail, meta.threshold) {
+            return Ok(());
+        }
+        if let Ok(result) = meta.output().encode(&thumbnail, &Default::default()) {
+            write_buffer(&path, &result, true)?;
+            *last = Some((path, thumbnail));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,197 @@
+use crate::{
+    encode::{ImageFormat, MetaOutput},
+    regression::pixel_differs,
+};
+use image::{DynamicImage, GenericImageView, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetaPreview {
+    /// Whether plugin periodically asks play view for project preview.
+    #[serde(default = "MetaPreview::default_enabled")]
+    pub enabled: bool,
+    /// Maximum preview width, aspect ratio of the captured frame is preserved.
+    #[serde(default = "MetaPreview::default_size")]
+    pub width: u32,
+    /// Maximum preview height, aspect ratio of the captured frame is preserved.
+    #[serde(default = "MetaPreview::default_size")]
+    pub height: u32,
+    #[serde(default)]
+    pub format: ImageFormat,
+    /// Quality in range 1-100, used by JPEG and lossy WebP.
+    #[serde(default = "MetaPreview::default_quality")]
+    pub quality: u8,
+    /// Project relative path to the preview file, `preview.<extension>` of
+    /// the format when not set.
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Percentage of changed pixels required to overwrite existing preview.
+    #[serde(default = "MetaPreview::default_threshold")]
+    pub threshold: f64,
+    /// Milliseconds between preview captures while play mode is running, at
+    /// least `MIN_INTERVAL`.
+    #[serde(default = "MetaPreview::default_interval")]
+    pub interval: usize,
+}
+
+impl Default for MetaPreview {
+    fn default() -> Self {
+        Self {
+            enabled: Self::default_enabled(),
+            width: Self::default_size(),
+            height: Self::default_size(),
+            format: Default::default(),
+            quality: Self::default_quality(),
+            path: None,
+            threshold: Self::default_threshold(),
+            interval: Self::default_interval(),
+        }
+    }
+}
+
+impl MetaPreview {
+    fn default_enabled() -> bool {
+        true
+    }
+
+    fn default_size() -> u32 {
+        512
+    }
+
+    fn default_quality() -> u8 {
+        80
+    }
+
+    fn default_threshold() -> f64 {
+        1.0
+    }
+
+    fn default_interval() -> usize {
+        60000
+    }
+
+    pub fn path(&self) -> String {
+        match &self.path {
+            Some(path) => path.to_owned(),
+            None => format!("preview.{}", self.format.extension()),
+        }
+    }
+
+    /// Delay of next preview capture.
+    pub fn interval(&self) -> usize {
+        self.interval.max(MIN_INTERVAL)
+    }
+
+    /// Scales captured frame down to preview size.
+    pub fn thumbnail(&self, image: &DynamicImage) -> DynamicImage {
+        image.thumbnail(self.width.max(1), self.height.max(1))
+    }
+
+    pub fn output(&self) -> MetaOutput {
+        MetaOutput {
+            format: self.format,
+            quality: Some(self.quality).filter(|_| self.format != ImageFormat::Png),
+            ..Default::default()
+        }
+    }
+}
+
+/// Shortest delay between preview captures, so previews do not slow down play mode.
+const MIN_INTERVAL: usize = 1000;
+
+/// Maximal per channel difference that still counts as the same pixel, so
+/// lossy formats and dithering do not trigger preview rewrites.
+const CHANNEL_TOLERANCE: u8 = 8;
+
+/// Percentage of pixels that differ between two images, images of different
+/// sizes are considered completely different.
+pub fn difference(a: &RgbaImage, b: &RgbaImage) -> f64 {
+    if a.dimensions() != b.dimensions() {
+        return 100.0;
+    }
+    let total = a.width() as usize * a.height() as usize;
+    if total == 0 {
+        return 0.0;
+    }
+    let changed = a
+        .pixels()
+        .zip(b.pixels())
+        .filter(|(a, b)| pixel_differs(a, b, CHANNEL_TOLERANCE))
+        .count();
+    changed as f64 * 100.0 / total as f64
+}
+
+/// Decides whether new preview differs enough from the last written one.
+pub fn should_update(
+    previous: Option<&DynamicImage>,
+    current: &DynamicImage,
+    threshold: f64,
+) -> bool {
+    match previous {
+        Some(previous) if previous.dimensions() == current.dimensions() => {
+            difference(&previous.to_rgba8(), &current.to_rgba8()) > threshold
+        }
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn image(width: u32, height: u32, changed: u32, delta: u8) -> DynamicImage {
+        let mut image = RgbaImage::from_pixel(width, height, Rgba([100, 100, 100, 255]));
+        for pixel in image.pixels_mut().take(changed as usize) {
+            pixel.0[0] += delta;
+        }
+        DynamicImage::ImageRgba8(image)
+    }
+
+    #[test]
+    fn test_default_path_follows_format() {
+        let mut meta = MetaPreview::default();
+        assert_eq!(meta.path(), "preview.png");
+        meta.format = ImageFormat::Jpeg;
+        assert_eq!(meta.path(), "preview.jpg");
+        meta.format = ImageFormat::Webp;
+        assert_eq!(meta.path(), "preview.webp");
+        meta.path = Some("docs/cover.webp".to_owned());
+        assert_eq!(meta.path(), "docs/cover.webp");
+    }
+
+    #[test]
+    fn test_interval_is_throttled() {
+        let mut meta = MetaPreview::default();
+        assert_eq!(meta.interval(), 60000);
+        meta.interval = 10;
+        assert_eq!(meta.interval(), MIN_INTERVAL);
+        meta.interval = 0;
+        assert_eq!(meta.interval(), MIN_INTERVAL);
+    }
+
+    #[test]
+    fn test_difference() {
+        let base = image(10, 10, 0, 0).to_rgba8();
+        assert_eq!(difference(&base, &base), 0.0);
+        assert_eq!(difference(&base, &image(10, 10, 5, 50).to_rgba8()), 5.0);
+        assert_eq!(
+            difference(&base, &image(10, 10, 50, CHANNEL_TOLERANCE).to_rgba8()),
+            0.0
+        );
+        assert_eq!(difference(&base, &image(5, 10, 0, 0).to_rgba8()), 100.0);
+        let empty = RgbaImage::new(0, 0);
+        assert_eq!(difference(&empty, &empty), 0.0);
+    }
+
+    #[test]
+    fn test_should_update() {
+        let base = image(10, 10, 0, 0);
+        assert!(should_update(None, &base, 1.0));
+        assert!(!should_update(Some(&base), &base, 1.0));
+        assert!(!should_update(Some(&base), &image(10, 10, 1, 50), 1.0));
+        assert!(should_update(Some(&base), &image(10, 10, 2, 50), 1.0));
+        assert!(should_update(Some(&base), &image(10, 10, 1, 50), 0.0));
+        assert!(should_update(Some(&base), &image(8, 8, 0, 0), 100.0));
+    }
+}
@@ -0,0 +1,265 @@
+use image::{imageops::FilterType, DynamicImage, GenericImageView, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordingFormat {
+    #[default]
+    Gif,
+    Apng,
+}
+
+impl RecordingFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Gif => "gif",
+            Self::Apng => "png",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetaRecording {
+    #[serde(default)]
+    pub format: RecordingFormat,
+    /// Frames per second, frames sent more often than that are skipped.
+    #[serde(default = "MetaRecording::default_fps")]
+    pub fps: u32,
+    /// Scale of recorded frames relative to captured ones.
+    #[serde(default = "MetaRecording::default_scale")]
+    pub scale: f32,
+    /// GIF palette quantization speed in range 1-30, lower values give better colors.
+    #[serde(default = "MetaRecording::default_palette_speed")]
+    pub palette_speed: i32,
+    /// Recording stops by itself after that many seconds.
+    #[serde(default = "MetaRecording::default_max_duration")]
+    pub max_duration: f64,
+    /// Megabytes that recorded frames can take before encoding, recording stops
+    /// with an error when next frame would exceed it.
+    #[serde(default = "MetaRecording::default_max_memory")]
+    pub max_memory: usize,
+}
+
+impl Default for MetaRecording {
+    fn default() -> Self {
+        Self {
+            format: Default::default(),
+            fps: Self::default_fps(),
+            scale: Self::default_scale(),
+            palette_speed: Self::default_palette_speed(),
+            max_duration: Self::default_max_duration(),
+            max_memory: Self::default_max_memory(),
+        }
+    }
+}
+
+impl MetaRecording {
+    fn default_fps() -> u32 {
+        10
+    }
+
+    fn default_scale() -> f32 {
+        0.5
+    }
+
+    fn default_palette_speed() -> i32 {
+        10
+    }
+
+    fn default_max_duration() -> f64 {
+        30.0
+    }
+
+    fn default_max_memory() -> usize {
+        256
+    }
+
+    /// Milliseconds between recorded frames.
+    pub fn frame_interval(&self) -> f64 {
+        1000.0 / self.fps.clamp(1, 60) as f64
+    }
+}
+
+#[derive(Debug)]
+pub struct Recording {
+    pub session: String,
+    pub meta: MetaRecording,
+    /// Milliseconds since UNIX epoch.
+    pub started: f64,
+    /// (timestamp, frame)
+    frames: Vec<(f64, RgbaImage)>,
+    /// Bytes taken by recorded frames.
+    bytes: usize,
+}
+
+impl Recording {
+    pub fn new(session: String, meta: MetaRecording, started: f64) -> Self {
+        Self {
+            session,
+            meta,
+            started,
+            frames: vec![],
+            bytes: 0,
+        }
+    }
+
+    pub fn frames_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_over(&self, timestamp: f64) -> bool {
+        timestamp - self.started >= self.meta.max_duration * 1000.0
+    }
+
+    /// Stores frame unless it came too early after previous one.
+    /// Fails when frame does not fit in memory budget.
+    pub fn push(&mut self, timestamp: f64, image: &DynamicImage) -> Result<bool, String> {
+        if let Some((last, _)) = self.frames.last() {
+            if timestamp - last < self.meta.frame_interval() {
+                return Ok(false);
+            }
+        }
+        let (width, height) = match self.frames.first() {
+            Some((_, first)) => first.dimensions(),
+            None => {
+                let scale = self.meta.scale.clamp(0.01, 1.0);
+                (
+                    ((image.width() as f32 * scale) as u32).max(1),
+                    ((image.height() as f32 * scale) as u32).max(1),
+                )
+            }
+        };
+        let bytes = width as usize * height as usize * 4;
+        if self.bytes + bytes > self.meta.max_memory * 1024 * 1024 {
+            return Err(format!(
+                "Recording stopped after {} frames, it exceeded memory limit of {} MB",
+                self.frames.len(),
+                self.meta.max_memory
+            ));
+        }
+        let frame = image
+            .resize_exact(width, height, FilterType::Triangle)
+            .to_rgba8();
+        self.frames.push((timestamp, frame));
+        self.bytes += bytes;
+        Ok(true)
+    }
+
+    /// Encodes recorded frames, timestamp closes the last frame.
+    pub fn encode(self, timestamp: f64) -> Result<Vec<u8>, String> {
+        if self.frames.is_empty() {
+            return Err("Recording has no frames".to_owned());
+        }
+        let delays = self
+            .frames
+            .iter()
+            .map(|(time, _)| *time)
+            .skip(1)
+            .chain(std::iter::once(timestamp.max(
+                self.frames.last().unwrap().0 + self.meta.frame_interval(),
+            )))
+            .zip(self.frames.iter())
+            .map(|(next, (time, _))| (next - time).max(10.0))
+            .collect::<Vec<_>>();
+        match self.meta.format {
+            RecordingFormat::Gif => encode_gif(&self.frames, &delays, self.meta.palette_speed),
+            RecordingFormat::Apng => encode_apng(&self.frames, &delays),
+        }
+    }
+}
+
+fn encode_gif(frames: &[(f64, RgbaImage)], delays: &[f64], speed: i32) -> Result<Vec<u8>, String> {
+    let (width, height) = frames[0].1.dimensions();
+    let mut result = vec![];
+    {
+        let mut encoder = gif::Encoder::new(&mut result, width as u16, height as u16, &[])
+            .map_err(|error| format!("{:?}", error))?;
+        encoder
+            .set_repeat(gif::Repeat::Infinite)
+            .map_err(|error| format!("{:?}", error))?;
+        for ((_, image), delay) in frames.iter().zip(delays.iter()) {
+            let mut pixels = image.as_raw().to_owned();
+            let mut frame = gif::Frame::from_rgba_speed(
+                width as u16,
+                height as u16,
+                &mut pixels,
+                speed.clamp(1, 30),
+            );
+            frame.delay = (delay / 10.0).round().min(u16::MAX as f64) as u16;
+            encoder
+                .write_frame(&frame)
+                .map_err(|error| format!("{:?}", error))?;
+        }
+    }
+    Ok(result)
+}
+
+fn encode_apng(frames: &[(f64, RgbaImage)], delays: &[f64]) -> Result<Vec<u8>, String> {
+    let (width, height) = frames[0].1.dimensions();
+    let mut result = vec![];
+    {
+        let mut encoder = png::Encoder::new(&mut result, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .set_animated(frames.len() as u32, 0)
+            .map_err(|error| format!("{:?}", error))?;
+        let mut writer = encoder
+            .write_header()
+            .map_err(|error| format!("{:?}", error))?;
+        for ((_, image), delay) in frames.iter().zip(delays.iter()) {
+            writer
+                .set_frame_delay(delay.round().min(u16::MAX as f64) as u16, 1000)
+                .map_err(|error| format!("{:?}", error))?;
+            writer
+                .write_image_data(image.as_raw())
+                .map_err(|error| format!("{:?}", error))?;
+        }
+        writer.finish().map_err(|error| format!("{:?}", error))?;
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::new(width, height))
+    }
+
+    #[test]
+    fn test_frame_interval() {
+        let meta = MetaRecording {
+            max_memory: 1,
+            ..Default::default()
+        };
+        let mut recording = Recording::new("default".to_owned(), meta, 0.0);
+        assert_eq!(recording.push(0.0, &image(8, 8)), Ok(true));
+        assert_eq!(recording.push(50.0, &image(8, 8)), Ok(false));
+        assert_eq!(recording.push(100.0, &image(8, 8)), Ok(true));
+        assert_eq!(recording.frames_count(), 2);
+        assert!(!recording.is_over(29_999.0));
+        assert!(recording.is_over(30_000.0));
+    }
+
+    #[test]
+    fn test_memory_limit() {
+        let meta = MetaRecording {
+            scale: 1.0,
+            max_memory: 1,
+            ..Default::default()
+        };
+        let mut recording = Recording::new("default".to_owned(), meta, 0.0);
+        // 256 x 256 RGBA frame takes 256 KB.
+        for index in 0..4 {
+            assert_eq!(
+                recording.push(index as f64 * 100.0, &image(256, 256)),
+                Ok(true)
+            );
+        }
+        assert!(recording.push(400.0, &image(256, 256)).is_err());
+        assert_eq!(recording.frames_count(), 4);
+        assert!(recording.encode(500.0).is_ok());
+    }
+}
@@ -0,0 +1,286 @@
+use ignite_plugin_utils::{
+    file_system::{delete_path, read_buffer, write_buffer},
+    paths::relative_to_project,
+};
+use image::{DynamicImage, ImageOutputFormat, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetaRegression {
+    /// Project relative directory with baseline images and comparison results.
+    #[serde(default = "MetaRegression::default_directory")]
+    pub directory: String,
+    /// Maximal per channel difference that still counts as the same pixel.
+    #[serde(default = "MetaRegression::default_tolerance")]
+    pub tolerance: u8,
+    /// Minimal similarity in percents that passes comparison.
+    #[serde(default = "MetaRegression::default_threshold")]
+    pub threshold: f64,
+    /// RGB color of changed pixels in generated diff image.
+    #[serde(default = "MetaRegression::default_highlight")]
+    pub highlight: [u8; 3],
+}
+
+impl Default for MetaRegression {
+    fn default() -> Self {
+        Self {
+            directory: Self::default_directory(),
+            tolerance: Self::default_tolerance(),
+            threshold: Self::default_threshold(),
+            highlight: Self::default_highlight(),
+        }
+    }
+}
+
+impl MetaRegression {
+    fn default_directory() -> String {
+        "regression".to_owned()
+    }
+
+    fn default_tolerance() -> u8 {
+        2
+    }
+
+    fn default_threshold() -> f64 {
+        99.9
+    }
+
+    fn default_highlight() -> [u8; 3] {
+        [255, 0, 255]
+    }
+
+    pub fn baseline_path(&self, name: &str) -> String {
+        format!("{}/{}.png", self.directory.trim_end_matches('/'), name)
+    }
+
+    pub fn actual_path(&self, name: &str) -> String {
+        format!(
+            "{}/{}.actual.png",
+            self.directory.trim_end_matches('/'),
+            name
+        )
+    }
+
+    pub fn diff_path(&self, name: &str) -> String {
+        format!("{}/{}.diff.png", self.directory.trim_end_matches('/'), name)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RegressionStatus {
+    Passed,
+    Failed,
+    MissingBaseline,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegressionResult {
+    pub name: String,
+    pub status: RegressionStatus,
+    /// Percentage of pixels that match the baseline.
+    pub similarity: f64,
+    pub changed: usize,
+    pub total: usize,
+    /// Project relative paths of written images.
+    pub actual: String,
+    pub diff: Option<String>,
+}
+
+pub struct Comparison {
+    pub changed: usize,
+    pub total: usize,
+    pub diff: RgbaImage,
+}
+
+impl Comparison {
+    pub fn similarity(&self) -> f64 {
+        if self.total == 0 {
+            100.0
+        } else {
+            (self.total - self.changed) as f64 * 100.0 / self.total as f64
+        }
+    }
+}
+
+pub fn pixel_differs(a: &Rgba<u8>, b: &Rgba<u8>, tolerance: u8) -> bool {
+    a.0.iter()
+        .zip(b.0.iter())
+        .any(|(a, b)| (*a as i16 - *b as i16).unsigned_abs() > tolerance as u16)
+}
+
+/// Compares actual image against baseline. Diff image shows dimmed baseline with
+/// changed pixels painted in highlight color, size mismatch counts every pixel
+/// of bigger area as changed.
+pub fn compare(baseline: &RgbaImage, actual: &RgbaImage, meta: &MetaRegression) -> Comparison {
+    let width = baseline.width().max(actual.width());
+    let height = baseline.height().max(actual.height());
+    let [r, g, b] = meta.highlight;
+    let mut changed = 0;
+    let diff = RgbaImage::from_fn(width, height, |x, y| {
+        let expected = if x < baseline.width() && y < baseline.height() {
+            Some(baseline.get_pixel(x, y))
+        } else {
+            None
+        };
+        let found = if x < actual.width() && y < actual.height() {
+            Some(actual.get_pixel(x, y))
+        } else {
+            None
+        };
+        match (expected, found) {
+            (Some(expected), Some(found)) if !pixel_differs(expected, found, meta.tolerance) => {
+                let [er, eg, eb, _] = expected.0;
+                let gray = ((er as u32 * 3 + eg as u32 * 6 + eb as u32) / 10) as u8;
+                let gray = 128 + gray / 4;
+                Rgba([gray, gray, gray, 255])
+            }
+            _ => {
+                changed += 1;
+                Rgba([r, g, b, 255])
+            }
+        }
+    });
+    Comparison {
+        changed,
+        total: width as usize * height as usize,
+        diff,
+    }
+}
+
+/// Stores actual image next to baseline and compares them, writing diff image
+/// when baseline exists.
+pub fn run(
+    name: &str,
+    image: &DynamicImage,
+    meta: &MetaRegression,
+) -> Result<RegressionResult, JsValue> {
+    let actual = image.to_rgba8();
+    write_png(&meta.actual_path(name), &actual)?;
+    let baseline = read_buffer(&relative_to_project(&meta.baseline_path(name))?, true)
+        .ok()
+        .and_then(|data| image::load_from_memory(&data).ok());
+    let baseline = match baseline {
+        Some(baseline) => baseline.to_rgba8(),
+        None => {
+            return Ok(RegressionResult {
+                name: name.to_owned(),
+                status: RegressionStatus::MissingBaseline,
+                similarity: 0.0,
+                changed: 0,
+                total: actual.width() as usize * actual.height() as usize,
+                actual: meta.actual_path(name),
+                diff: None,
+            })
+        }
+    };
+    let comparison = compare(&baseline, &actual, meta);
+    write_png(&meta.diff_path(name), &comparison.diff)?;
+    let similarity = comparison.similarity();
+    Ok(RegressionResult {
+        name: name.to_owned(),
+        status: if similarity >= meta.threshold {
+            RegressionStatus::Passed
+        } else {
+            RegressionStatus::Failed
+        },
+        similarity,
+        changed: comparison.changed,
+        total: comparison.total,
+        actual: meta.actual_path(name),
+        diff: Some(meta.diff_path(name)),
+    })
+}
+
+/// Replaces baseline with last captured image and removes comparison leftovers.
+pub fn accept(name: &str, meta: &MetaRegression) -> Result<(), JsValue> {
+    let actual = relative_to_project(&meta.actual_path(name))?;
+    let data = read_buffer(&actual, true)?;
+    write_buffer(
+        &relative_to_project(&meta.baseline_path(name))?,
+        &data,
+        true,
+    )?;
+    drop(delete_path(&actual, true));
+    drop(delete_path(
+        &relative_to_project(&meta.diff_path(name))?,
+        true,
+    ));
+    Ok(())
+}
+
+fn write_png(path: &str, image: &RgbaImage) -> Result<(), JsValue> {
+    let mut result = vec![];
+    match DynamicImage::ImageRgba8(image.clone()).write_to(&mut result, ImageOutputFormat::Png) {
+        Ok(_) => write_buffer(&relative_to_project(path)?, &result, true),
+        Err(error) => Err(format!("{:?}", error).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+
+    #[test]
+    fn test_pixel_tolerance() {
+        assert!(!pixel_differs(&RED, &Rgba([253, 2, 0, 255]), 2));
+        assert!(pixel_differs(&RED, &Rgba([252, 0, 0, 255]), 2));
+        assert!(pixel_differs(&RED, &Rgba([255, 0, 0, 250]), 2));
+        assert!(!pixel_differs(&RED, &Rgba([0, 0, 0, 0]), 255));
+    }
+
+    #[test]
+    fn test_identical_images() {
+        let image = RgbaImage::from_pixel(4, 4, RED);
+        let comparison = compare(&image, &image, &MetaRegression::default());
+        assert_eq!(comparison.changed, 0);
+        assert_eq!(comparison.total, 16);
+        assert_eq!(comparison.similarity(), 100.0);
+        assert!(comparison.diff.pixels().all(|pixel| pixel.0[0] >= 128));
+    }
+
+    #[test]
+    fn test_changed_pixels_are_highlighted() {
+        let baseline = RgbaImage::from_pixel(4, 4, RED);
+        let mut actual = baseline.clone();
+        actual.put_pixel(1, 2, Rgba([0, 255, 0, 255]));
+        let meta = MetaRegression::default();
+        let comparison = compare(&baseline, &actual, &meta);
+        assert_eq!(comparison.changed, 1);
+        assert_eq!(comparison.similarity(), 93.75);
+        assert_eq!(comparison.diff.get_pixel(1, 2), &Rgba([255, 0, 255, 255]));
+        assert_ne!(comparison.diff.get_pixel(0, 0), &Rgba([255, 0, 255, 255]));
+    }
+
+    #[test]
+    fn test_size_mismatch() {
+        let baseline = RgbaImage::from_pixel(2, 2, RED);
+        let actual = RgbaImage::from_pixel(4, 1, RED);
+        let comparison = compare(&baseline, &actual, &MetaRegression::default());
+        assert_eq!(comparison.diff.dimensions(), (4, 2));
+        assert_eq!(comparison.total, 8);
+        // only (0, 0) and (1, 0) exist in both images.
+        assert_eq!(comparison.changed, 6);
+    }
+
+    #[test]
+    fn test_empty_images() {
+        let image = RgbaImage::new(0, 0);
+        let comparison = compare(&image, &image, &MetaRegression::default());
+        assert_eq!(comparison.similarity(), 100.0);
+    }
+
+    #[test]
+    fn test_paths() {
+        let meta = MetaRegression {
+            directory: "tests/regression/".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(meta.baseline_path("menu"), "tests/regression/menu.png");
+        assert_eq!(meta.actual_path("menu"), "tests/regression/menu.actual.png");
+        assert_eq!(meta.diff_path("menu"), "tests/regression/menu.diff.png");
+    }
+}
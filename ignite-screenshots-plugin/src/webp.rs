@@ -0,0 +1,1255 @@
+//! Lossy WebP encoder, `image-webp` can only write lossless images. Produces
+//! single VP8 key frame using whole macroblock intra prediction and default
+//! token probabilities, which keeps it small at the cost of some file size.
+
+const MAX_SIZE: u32 = 16383;
+const MAX_LEVEL: i32 = 2048;
+
+const DC_PRED: i8 = 0;
+const V_PRED: i8 = 1;
+const H_PRED: i8 = 2;
+const TM_PRED: i8 = 3;
+const B_PRED: i8 = 4;
+const MODES: [i8; 4] = [DC_PRED, V_PRED, H_PRED, TM_PRED];
+
+const KEYFRAME_YMODE_TREE: [i8; 8] = [-B_PRED, 2, 4, 6, -DC_PRED, -V_PRED, -H_PRED, -TM_PRED];
+const KEYFRAME_YMODE_PROBS: [u8; 4] = [145, 156, 163, 128];
+const KEYFRAME_UV_MODE_TREE: [i8; 6] = [-DC_PRED, 2, -V_PRED, 4, -H_PRED, -TM_PRED];
+const KEYFRAME_UV_MODE_PROBS: [u8; 3] = [142, 114, 183];
+
+const DCT_0: i8 = 0;
+const DCT_CAT1: i8 = 5;
+const DCT_EOB: i8 = 11;
+
+/// Coefficient planes, indexes first dimension of token probabilities.
+const PLANE_Y_AFTER_Y2: usize = 0;
+const PLANE_Y2: usize = 1;
+const PLANE_CHROMA: usize = 2;
+
+/// Encodes RGBA pixels into lossy WebP with quality in range 1-100. Alpha goes
+/// uncompressed into separate chunk, only when some pixel is not fully opaque.
+pub fn encode_lossy(rgba: &[u8], width: u32, height: u32, quality: u8) -> Result<Vec<u8>, String> {
+    if width == 0 || height == 0 || width > MAX_SIZE || height > MAX_SIZE {
+        return Err(format!(
+            "Lossy WebP size must be in range 1-{}, got {}x{}",
+            MAX_SIZE, width, height
+        ));
+    }
+    if rgba.len() != width as usize * height as usize * 4 {
+        return Err("Image buffer does not match its size".to_owned());
+    }
+    let frame = encode_frame(&Planes::from_rgba(rgba, width, height), quality);
+    let mut result = b"RIFF\0\0\0\0WEBP".to_vec();
+    if rgba.chunks_exact(4).any(|pixel| pixel[3] < 255) {
+        let mut header = vec![0b0001_0000, 0, 0, 0];
+        header.extend_from_slice(&(width - 1).to_le_bytes()[..3]);
+        header.extend_from_slice(&(height - 1).to_le_bytes()[..3]);
+        write_chunk(&mut result, b"VP8X", &header);
+        let mut alpha = Vec::with_capacity(rgba.len() / 4 + 1);
+        // No preprocessing, filtering nor compression.
+        alpha.push(0);
+        alpha.extend(rgba.chunks_exact(4).map(|pixel| pixel[3]));
+        write_chunk(&mut result, b"ALPH", &alpha);
+    }
+    write_chunk(&mut result, b"VP8 ", &frame);
+    let size = (result.len() - 8) as u32;
+    result[4..8].copy_from_slice(&size.to_le_bytes());
+    Ok(result)
+}
+
+fn write_chunk(output: &mut Vec<u8>, fourcc: &[u8; 4], data: &[u8]) {
+    output.extend_from_slice(fourcc);
+    output.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    output.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        output.push(0);
+    }
+}
+
+/// Maps quality to quantizer index and loop filter level.
+fn quality_settings(quality: u8) -> (usize, u8) {
+    let index = (100 - quality.clamp(1, 100) as usize) * 127 / 99;
+    (index, (index / 4) as u8)
+}
+
+fn encode_frame(source: &Planes, quality: u8) -> Vec<u8> {
+    let (index, filter_level) = quality_settings(quality);
+    let quantizers = Quantizers::new(index);
+    let mut reconstructed = Planes::new(source.width, source.height);
+    let mut macroblocks = Vec::with_capacity(source.mb_width * source.mb_height);
+    for mby in 0..source.mb_height {
+        for mbx in 0..source.mb_width {
+            macroblocks.push(MacroBlock::encode(
+                source,
+                &mut reconstructed,
+                &quantizers,
+                mbx,
+                mby,
+            ));
+        }
+    }
+    let skipped = macroblocks.iter().filter(|mb| mb.is_empty()).count();
+    let prob_skip_false = if skipped > 0 {
+        let coded = (macroblocks.len() - skipped) * 256 / macroblocks.len();
+        Some(coded.clamp(1, 255) as u8)
+    } else {
+        None
+    };
+
+    let mut header = BoolEncoder::new();
+    // Color space and clamping type.
+    header.put_literal(1, 0);
+    header.put_literal(1, 0);
+    // Segmentation.
+    header.put_flag(false);
+    // Normal loop filter.
+    header.put_flag(false);
+    header.put_literal(6, filter_level as u32);
+    // Sharpness.
+    header.put_literal(3, 0);
+    // Loop filter adjustments.
+    header.put_flag(false);
+    // Single token partition.
+    header.put_literal(2, 0);
+    header.put_literal(7, index as u32);
+    // No quantizer deltas.
+    for _ in 0..5 {
+        header.put_flag(false);
+    }
+    // Refresh entropy probabilities.
+    header.put_literal(1, 0);
+    for prob in COEFF_UPDATE_PROBS.iter().flatten().flatten().flatten() {
+        header.put_bool(*prob, false);
+    }
+    match prob_skip_false {
+        Some(prob) => {
+            header.put_literal(1, 1);
+            header.put_literal(8, prob as u32);
+        }
+        None => header.put_literal(1, 0),
+    }
+    for mb in &macroblocks {
+        if let Some(prob) = prob_skip_false {
+            header.put_bool(prob, mb.is_empty());
+        }
+        header.put_tree(&KEYFRAME_YMODE_TREE, &KEYFRAME_YMODE_PROBS, mb.luma_mode, 0);
+        header.put_tree(
+            &KEYFRAME_UV_MODE_TREE,
+            &KEYFRAME_UV_MODE_PROBS,
+            mb.chroma_mode,
+            0,
+        );
+    }
+
+    let mut tokens = BoolEncoder::new();
+    let mut top = vec![[0u8; 9]; source.mb_width];
+    for row in macroblocks.chunks(source.mb_width) {
+        let mut left = [0u8; 9];
+        for (mb, top) in row.iter().zip(top.iter_mut()) {
+            if prob_skip_false.is_some() && mb.is_empty() {
+                *top = [0; 9];
+                left = [0; 9];
+            } else {
+                mb.put_tokens(&mut tokens, top, &mut left);
+            }
+        }
+    }
+
+    let header = header.finish();
+    let tokens = tokens.finish();
+    let mut result = Vec::with_capacity(10 + header.len() + tokens.len());
+    // Key frame, version 0, shown, followed by first partition size.
+    let tag = ((header.len() as u32) << 5) | 0b1_0000;
+    result.extend_from_slice(&tag.to_le_bytes()[..3]);
+    result.extend_from_slice(&[0x9d, 0x01, 0x2a]);
+    result.extend_from_slice(&(source.width as u16).to_le_bytes());
+    result.extend_from_slice(&(source.height as u16).to_le_bytes());
+    result.extend(header);
+    result.extend(tokens);
+    result
+}
+
+/// Boolean entropy encoder from RFC 6386 section 7.3.
+struct BoolEncoder {
+    output: Vec<u8>,
+    range: u32,
+    bottom: u32,
+    bit_count: i32,
+}
+
+impl BoolEncoder {
+    fn new() -> Self {
+        Self {
+            output: vec![],
+            range: 255,
+            bottom: 0,
+            bit_count: 24,
+        }
+    }
+
+    fn put_bool(&mut self, prob: u8, value: bool) {
+        let split = 1 + (((self.range - 1) * prob as u32) >> 8);
+        if value {
+            self.bottom = self.bottom.wrapping_add(split);
+            self.range -= split;
+        } else {
+            self.range = split;
+        }
+        while self.range < 128 {
+            self.range <<= 1;
+            if self.bottom & (1 << 31) != 0 {
+                self.add_one_to_output();
+            }
+            self.bottom <<= 1;
+            self.bit_count -= 1;
+            if self.bit_count == 0 {
+                self.output.push((self.bottom >> 24) as u8);
+                self.bottom &= (1 << 24) - 1;
+                self.bit_count = 8;
+            }
+        }
+    }
+
+    fn put_flag(&mut self, value: bool) {
+        self.put_bool(128, value);
+    }
+
+    fn put_literal(&mut self, bits: u32, value: u32) {
+        for bit in (0..bits).rev() {
+            self.put_flag((value >> bit) & 1 != 0);
+        }
+    }
+
+    /// Writes path to `value` leaf, starting at `start` node index.
+    fn put_tree(&mut self, tree: &[i8], probs: &[u8], value: i8, start: usize) {
+        let mut path = [(0, false); 16];
+        let length = tree_path(tree, start, value, &mut path, 0)
+            .unwrap_or_else(|| panic!("Value {} is not in tree", value));
+        for (node, bit) in &path[..length] {
+            self.put_bool(probs[node >> 1], *bit);
+        }
+    }
+
+    fn add_one_to_output(&mut self) {
+        for byte in self.output.iter_mut().rev() {
+            if *byte == 255 {
+                *byte = 0;
+            } else {
+                *byte += 1;
+                return;
+            }
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        let mut count = self.bit_count;
+        let mut value = self.bottom;
+        if value & (1 << (32 - count)) != 0 {
+            self.add_one_to_output();
+        }
+        value <<= count & 7;
+        count >>= 3;
+        for _ in 0..count {
+            value <<= 8;
+        }
+        for _ in 0..4 {
+            self.output.push((value >> 24) as u8);
+            value <<= 8;
+        }
+        self.output
+    }
+}
+
+/// Fills `path` with (node, bit) pairs leading to `value`, returns path length.
+fn tree_path(
+    tree: &[i8],
+    node: usize,
+    value: i8,
+    path: &mut [(usize, bool); 16],
+    depth: usize,
+) -> Option<usize> {
+    for bit in [false, true].iter() {
+        path[depth] = (node, *bit);
+        let next = tree[node + *bit as usize];
+        if next <= 0 {
+            if -next == value {
+                return Some(depth + 1);
+            }
+        } else if let Some(length) = tree_path(tree, next as usize, value, path, depth + 1) {
+            return Some(length);
+        }
+    }
+    None
+}
+
+/// YUV 4:2:0 planes padded to whole macroblocks.
+struct Planes {
+    width: u32,
+    height: u32,
+    mb_width: usize,
+    mb_height: usize,
+    y: Plane,
+    u: Plane,
+    v: Plane,
+}
+
+impl Planes {
+    fn new(width: u32, height: u32) -> Self {
+        let mb_width = (width as usize).div_ceil(16);
+        let mb_height = (height as usize).div_ceil(16);
+        Self {
+            width,
+            height,
+            mb_width,
+            mb_height,
+            y: Plane::new(mb_width * 16, mb_height * 16),
+            u: Plane::new(mb_width * 8, mb_height * 8),
+            v: Plane::new(mb_width * 8, mb_height * 8),
+        }
+    }
+
+    /// BT.601 conversion, padding repeats edge pixels.
+    fn from_rgba(rgba: &[u8], width: u32, height: u32) -> Self {
+        let mut result = Self::new(width, height);
+        let (width, height) = (width as usize, height as usize);
+        let pixel = |x: usize, y: usize| {
+            let index = (y.min(height - 1) * width + x.min(width - 1)) * 4;
+            [
+                rgba[index] as i32,
+                rgba[index + 1] as i32,
+                rgba[index + 2] as i32,
+            ]
+        };
+        let stride = result.y.stride;
+        for (index, luma) in result.y.data.iter_mut().enumerate() {
+            let [r, g, b] = pixel(index % stride, index / stride);
+            *luma = (((66 * r + 129 * g + 25 * b + 128) >> 8) + 16) as u8;
+        }
+        let stride = result.u.stride;
+        for index in 0..result.u.data.len() {
+            let (x, y) = (index % stride * 2, index / stride * 2);
+            let mut rgb = [0; 3];
+            for [r, g, b] in [
+                pixel(x, y),
+                pixel(x + 1, y),
+                pixel(x, y + 1),
+                pixel(x + 1, y + 1),
+            ]
+            .iter()
+            {
+                rgb[0] += r;
+                rgb[1] += g;
+                rgb[2] += b;
+            }
+            let [r, g, b] = rgb;
+            result.u.data[index] = (((-38 * r - 74 * g + 112 * b + 512) >> 10) + 128) as u8;
+            result.v.data[index] = (((112 * r - 94 * g - 18 * b + 512) >> 10) + 128) as u8;
+        }
+        result
+    }
+}
+
+struct Plane {
+    data: Vec<u8>,
+    stride: usize,
+}
+
+impl Plane {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            data: vec![0; width * height],
+            stride: width,
+        }
+    }
+
+    /// Predictions of `size` square block for every mode, using the same edge
+    /// values as decoder does for blocks at image border.
+    fn predictions(&self, x0: usize, y0: usize, size: usize) -> [[u8; 256]; 4] {
+        let mut above = [127u8; 16];
+        let mut left = [129u8; 16];
+        if y0 > 0 {
+            above[..size].copy_from_slice(&self.data[(y0 - 1) * self.stride + x0..][..size]);
+        }
+        if x0 > 0 {
+            for (y, left) in left[..size].iter_mut().enumerate() {
+                *left = self.data[(y0 + y) * self.stride + x0 - 1];
+            }
+        }
+        let corner = if y0 == 0 {
+            127
+        } else if x0 == 0 {
+            129
+        } else {
+            self.data[(y0 - 1) * self.stride + x0 - 1]
+        } as i32;
+        let mut shift = if size == 8 { 2 } else { 3 };
+        let mut sum = 0;
+        if x0 > 0 {
+            sum += left[..size].iter().map(|value| *value as u32).sum::<u32>();
+            shift += 1;
+        }
+        if y0 > 0 {
+            sum += above[..size].iter().map(|value| *value as u32).sum::<u32>();
+            shift += 1;
+        }
+        let dc = if x0 == 0 && y0 == 0 {
+            128
+        } else {
+            ((sum + (1 << (shift - 1))) >> shift) as u8
+        };
+        let mut result = [[0; 256]; 4];
+        for (y, left) in left[..size].iter().enumerate() {
+            for (x, above) in above[..size].iter().enumerate() {
+                let index = y * size + x;
+                result[DC_PRED as usize][index] = dc;
+                result[V_PRED as usize][index] = *above;
+                result[H_PRED as usize][index] = *left;
+                result[TM_PRED as usize][index] =
+                    (*left as i32 + *above as i32 - corner).clamp(0, 255) as u8;
+            }
+        }
+        result
+    }
+
+    fn sad(&self, x0: usize, y0: usize, size: usize, prediction: &[u8; 256]) -> u32 {
+        (0..size * size)
+            .map(|index| {
+                let value = self.data[(y0 + index / size) * self.stride + x0 + index % size];
+                (value as i32 - prediction[index] as i32).unsigned_abs()
+            })
+            .sum()
+    }
+
+    /// Difference between 4x4 subblock `(bx, by)` and its prediction.
+    fn residual(
+        &self,
+        x0: usize,
+        y0: usize,
+        size: usize,
+        prediction: &[u8; 256],
+        bx: usize,
+        by: usize,
+    ) -> [i32; 16] {
+        let mut result = [0; 16];
+        for (index, residual) in result.iter_mut().enumerate() {
+            let (x, y) = (bx * 4 + index % 4, by * 4 + index / 4);
+            *residual =
+                self.data[(y0 + y) * self.stride + x0 + x] as i32 - prediction[y * size + x] as i32;
+        }
+        result
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn reconstruct(
+        &mut self,
+        x0: usize,
+        y0: usize,
+        size: usize,
+        prediction: &[u8; 256],
+        bx: usize,
+        by: usize,
+        residual: &[i32; 16],
+    ) {
+        for (index, residual) in residual.iter().enumerate() {
+            let (x, y) = (bx * 4 + index % 4, by * 4 + index / 4);
+            self.data[(y0 + y) * self.stride + x0 + x] =
+                (prediction[y * size + x] as i32 + residual).clamp(0, 255) as u8;
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Quantizer {
+    dc: i32,
+    ac: i32,
+}
+
+impl Quantizer {
+    /// Returns levels in zigzag order.
+    fn quantize(self, coefficients: &[i32; 16]) -> [i32; 16] {
+        let mut result = [0; 16];
+        for (level, position) in result.iter_mut().zip(ZIGZAG.iter()) {
+            let value = coefficients[*position as usize];
+            let (step, bias) = if *position == 0 {
+                (self.dc, self.dc / 2)
+            } else {
+                (self.ac, self.ac / 3)
+            };
+            let magnitude = ((value.abs() + bias) / step).min(MAX_LEVEL);
+            *level = if value < 0 { -magnitude } else { magnitude };
+        }
+        result
+    }
+
+    fn dequantize(self, levels: &[i32; 16]) -> [i32; 16] {
+        let mut result = [0; 16];
+        for (level, position) in levels.iter().zip(ZIGZAG.iter()) {
+            let step = if *position == 0 { self.dc } else { self.ac };
+            result[*position as usize] = level * step;
+        }
+        result
+    }
+}
+
+/// Quantizers derived the same way as decoder does, without deltas.
+struct Quantizers {
+    y: Quantizer,
+    y2: Quantizer,
+    uv: Quantizer,
+}
+
+impl Quantizers {
+    fn new(index: usize) -> Self {
+        let dc = DC_QUANT[index] as i32;
+        let ac = AC_QUANT[index] as i32;
+        Self {
+            y: Quantizer { dc, ac },
+            y2: Quantizer {
+                dc: dc * 2,
+                ac: (ac * 155 / 100).max(8),
+            },
+            uv: Quantizer {
+                dc: dc.min(132),
+                ac,
+            },
+        }
+    }
+}
+
+/// Prediction modes and quantized coefficients of single macroblock.
+struct MacroBlock {
+    luma_mode: i8,
+    chroma_mode: i8,
+    y2: [i32; 16],
+    y: [[i32; 16]; 16],
+    u: [[i32; 16]; 4],
+    v: [[i32; 16]; 4],
+}
+
+impl MacroBlock {
+    /// Picks prediction modes, quantizes residuals and writes decoded result
+    /// into `reconstructed`, so following macroblocks predict from it.
+    fn encode(
+        source: &Planes,
+        reconstructed: &mut Planes,
+        quantizers: &Quantizers,
+        mbx: usize,
+        mby: usize,
+    ) -> Self {
+        let (x0, y0) = (mbx * 16, mby * 16);
+        let predictions = reconstructed.y.predictions(x0, y0, 16);
+        let luma_mode = best_mode(|mode| source.y.sad(x0, y0, 16, &predictions[mode]));
+        let prediction = &predictions[luma_mode as usize];
+        let mut coefficients = [[0; 16]; 16];
+        let mut dc = [0; 16];
+        for (index, coefficients) in coefficients.iter_mut().enumerate() {
+            *coefficients = fdct(
+                &source
+                    .y
+                    .residual(x0, y0, 16, prediction, index % 4, index / 4),
+            );
+            dc[index] = coefficients[0];
+        }
+        let y2 = quantizers.y2.quantize(&fwht(&dc));
+        let mut dc = quantizers.y2.dequantize(&y2);
+        iwht(&mut dc);
+        let mut y = [[0; 16]; 16];
+        for (index, (levels, coefficients)) in y.iter_mut().zip(coefficients.iter()).enumerate() {
+            *levels = quantizers.y.quantize(coefficients);
+            levels[0] = 0;
+            let mut residual = quantizers.y.dequantize(levels);
+            residual[0] = dc[index];
+            idct(&mut residual);
+            reconstructed
+                .y
+                .reconstruct(x0, y0, 16, prediction, index % 4, index / 4, &residual);
+        }
+
+        let (x0, y0) = (mbx * 8, mby * 8);
+        let u_predictions = reconstructed.u.predictions(x0, y0, 8);
+        let v_predictions = reconstructed.v.predictions(x0, y0, 8);
+        let chroma_mode = best_mode(|mode| {
+            source.u.sad(x0, y0, 8, &u_predictions[mode])
+                + source.v.sad(x0, y0, 8, &v_predictions[mode])
+        });
+        let u = encode_chroma(
+            &source.u,
+            &mut reconstructed.u,
+            x0,
+            y0,
+            &u_predictions[chroma_mode as usize],
+            quantizers.uv,
+        );
+        let v = encode_chroma(
+            &source.v,
+            &mut reconstructed.v,
+            x0,
+            y0,
+            &v_predictions[chroma_mode as usize],
+            quantizers.uv,
+        );
+
+        Self {
+            luma_mode,
+            chroma_mode,
+            y2,
+            y,
+            u,
+            v,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.y2
+            .iter()
+            .chain(self.y.iter().flatten())
+            .chain(self.u.iter().flatten())
+            .chain(self.v.iter().flatten())
+            .all(|level| *level == 0)
+    }
+
+    /// Writes coefficients in decoder order, updating nonzero contexts: Y2 at 0,
+    /// Y columns or rows at 1-4, U at 5-6 and V at 7-8.
+    fn put_tokens(&self, encoder: &mut BoolEncoder, top: &mut [u8; 9], left: &mut [u8; 9]) {
+        let nonzero = put_coefficients(encoder, PLANE_Y2, top[0] + left[0], &self.y2);
+        top[0] = nonzero;
+        left[0] = nonzero;
+        for (index, levels) in self.y.iter().enumerate() {
+            let (x, y) = (index % 4 + 1, index / 4 + 1);
+            let nonzero = put_coefficients(encoder, PLANE_Y_AFTER_Y2, top[x] + left[y], levels);
+            top[x] = nonzero;
+            left[y] = nonzero;
+        }
+        for (context, blocks) in [(5, &self.u), (7, &self.v)].iter() {
+            for (index, levels) in blocks.iter().enumerate() {
+                let (x, y) = (context + index % 2, context + index / 2);
+                let nonzero = put_coefficients(encoder, PLANE_CHROMA, top[x] + left[y], levels);
+                top[x] = nonzero;
+                left[y] = nonzero;
+            }
+        }
+    }
+}
+
+fn best_mode<F: Fn(usize) -> u32>(cost: F) -> i8 {
+    MODES
+        .iter()
+        .copied()
+        .min_by_key(|mode| cost(*mode as usize))
+        .unwrap_or(DC_PRED)
+}
+
+fn encode_chroma(
+    source: &Plane,
+    reconstructed: &mut Plane,
+    x0: usize,
+    y0: usize,
+    prediction: &[u8; 256],
+    quantizer: Quantizer,
+) -> [[i32; 16]; 4] {
+    let mut result = [[0; 16]; 4];
+    for (index, levels) in result.iter_mut().enumerate() {
+        let (bx, by) = (index % 2, index / 2);
+        *levels = quantizer.quantize(&fdct(&source.residual(x0, y0, 8, prediction, bx, by)));
+        let mut residual = quantizer.dequantize(levels);
+        idct(&mut residual);
+        reconstructed.reconstruct(x0, y0, 8, prediction, bx, by, &residual);
+    }
+    result
+}
+
+/// Writes tokens of single block, returns 1 when any coefficient was coded.
+fn put_coefficients(
+    encoder: &mut BoolEncoder,
+    plane: usize,
+    context: u8,
+    levels: &[i32; 16],
+) -> u8 {
+    let first = if plane == PLANE_Y_AFTER_Y2 { 1 } else { 0 };
+    let probs = &COEFF_PROBS[plane];
+    let mut context = context as usize;
+    let last = match (first..16).rev().find(|index| levels[*index] != 0) {
+        Some(last) => last,
+        None => {
+            let probs = &probs[COEFF_BANDS[first] as usize][context];
+            encoder.put_tree(&DCT_TOKEN_TREE, probs, DCT_EOB, 0);
+            return 0;
+        }
+    };
+    let mut after_zero = false;
+    for (index, level) in levels.iter().enumerate().take(last + 1).skip(first) {
+        let probs = &probs[COEFF_BANDS[index] as usize][context];
+        let magnitude = level.abs();
+        let start = if after_zero { 2 } else { 0 };
+        match DCT_CAT_BASE
+            .iter()
+            .rposition(|base| magnitude >= *base as i32)
+        {
+            Some(category) => {
+                encoder.put_tree(&DCT_TOKEN_TREE, probs, DCT_CAT1 + category as i8, start);
+                let extra = magnitude - DCT_CAT_BASE[category] as i32;
+                let probs = &PROB_DCT_CAT[category];
+                let bits = probs.iter().take_while(|prob| **prob > 0).count();
+                for (index, prob) in probs[..bits].iter().enumerate() {
+                    encoder.put_bool(*prob, (extra >> (bits - 1 - index)) & 1 != 0);
+                }
+            }
+            None => encoder.put_tree(&DCT_TOKEN_TREE, probs, DCT_0 + magnitude as i8, start),
+        }
+        if magnitude != 0 {
+            encoder.put_flag(*level < 0);
+        }
+        after_zero = magnitude == 0;
+        context = magnitude.min(2) as usize;
+    }
+    if last < 15 {
+        let probs = &probs[COEFF_BANDS[last + 1] as usize][context];
+        encoder.put_tree(&DCT_TOKEN_TREE, probs, DCT_EOB, 0);
+    }
+    1
+}
+
+/// Forward DCT matching decoder `idct`.
+fn fdct(input: &[i32; 16]) -> [i32; 16] {
+    let mut temp = [0; 16];
+    for (row, output) in input.chunks_exact(4).zip(temp.chunks_exact_mut(4)) {
+        let a1 = (row[0] + row[3]) * 8;
+        let b1 = (row[1] + row[2]) * 8;
+        let c1 = (row[1] - row[2]) * 8;
+        let d1 = (row[0] - row[3]) * 8;
+        output[0] = a1 + b1;
+        output[2] = a1 - b1;
+        output[1] = (c1 * 2217 + d1 * 5352 + 14500) >> 12;
+        output[3] = (d1 * 2217 - c1 * 5352 + 7500) >> 12;
+    }
+    let mut result = [0; 16];
+    for i in 0..4 {
+        let a1 = temp[i] + temp[12 + i];
+        let b1 = temp[4 + i] + temp[8 + i];
+        let c1 = temp[4 + i] - temp[8 + i];
+        let d1 = temp[i] - temp[12 + i];
+        result[i] = (a1 + b1 + 7) >> 4;
+        result[8 + i] = (a1 - b1 + 7) >> 4;
+        result[4 + i] = ((c1 * 2217 + d1 * 5352 + 12000) >> 16) + (d1 != 0) as i32;
+        result[12 + i] = (d1 * 2217 - c1 * 5352 + 51000) >> 16;
+    }
+    result
+}
+
+/// Forward Walsh-Hadamard transform of luma DC coefficients.
+fn fwht(input: &[i32; 16]) -> [i32; 16] {
+    let mut temp = [0; 16];
+    for (row, output) in input.chunks_exact(4).zip(temp.chunks_exact_mut(4)) {
+        let a1 = (row[0] + row[2]) * 4;
+        let d1 = (row[1] + row[3]) * 4;
+        let c1 = (row[1] - row[3]) * 4;
+        let b1 = (row[0] - row[2]) * 4;
+        output[0] = a1 + d1 + (a1 != 0) as i32;
+        output[1] = b1 + c1;
+        output[2] = b1 - c1;
+        output[3] = a1 - d1;
+    }
+    let mut result = [0; 16];
+    for i in 0..4 {
+        let a1 = temp[i] + temp[8 + i];
+        let d1 = temp[4 + i] + temp[12 + i];
+        let c1 = temp[4 + i] - temp[12 + i];
+        let b1 = temp[i] - temp[8 + i];
+        for (offset, value) in [a1 + d1, b1 + c1, b1 - c1, a1 - d1].iter().enumerate() {
+            let value = value + (*value < 0) as i32;
+            result[offset * 4 + i] = (value + 3) >> 3;
+        }
+    }
+    result
+}
+
+/// Inverse DCT, same as in decoder so reconstruction matches decoded image.
+fn idct(block: &mut [i32; 16]) {
+    const C1: i64 = 20091;
+    const C2: i64 = 35468;
+    let mut temp = [0i64; 16];
+    for i in 0..4 {
+        let [b0, b4, b8, b12] = [block[i], block[4 + i], block[8 + i], block[12 + i]];
+        let (b0, b4, b8, b12) = (b0 as i64, b4 as i64, b8 as i64, b12 as i64);
+        let a1 = b0 + b8;
+        let b1 = b0 - b8;
+        let c1 = ((b4 * C2) >> 16) - (b12 + ((b12 * C1) >> 16));
+        let d1 = (b4 + ((b4 * C1) >> 16)) + ((b12 * C2) >> 16);
+        temp[i] = a1 + d1;
+        temp[4 + i] = b1 + c1;
+        temp[8 + i] = b1 - c1;
+        temp[12 + i] = a1 - d1;
+    }
+    for (row, output) in temp.chunks_exact(4).zip(block.chunks_exact_mut(4)) {
+        let a1 = row[0] + row[2];
+        let b1 = row[0] - row[2];
+        let c1 = ((row[1] * C2) >> 16) - (row[3] + ((row[3] * C1) >> 16));
+        let d1 = (row[1] + ((row[1] * C1) >> 16)) + ((row[3] * C2) >> 16);
+        output[0] = ((a1 + d1 + 4) >> 3) as i32;
+        output[3] = ((a1 - d1 + 4) >> 3) as i32;
+        output[1] = ((b1 + c1 + 4) >> 3) as i32;
+        output[2] = ((b1 - c1 + 4) >> 3) as i32;
+    }
+}
+
+/// Inverse Walsh-Hadamard transform, same as in decoder.
+fn iwht(block: &mut [i32; 16]) {
+    for i in 0..4 {
+        let a1 = block[i] + block[12 + i];
+        let b1 = block[4 + i] + block[8 + i];
+        let c1 = block[4 + i] - block[8 + i];
+        let d1 = block[i] - block[12 + i];
+        block[i] = a1 + b1;
+        block[4 + i] = c1 + d1;
+        block[8 + i] = a1 - b1;
+        block[12 + i] = d1 - c1;
+    }
+    for row in block.chunks_exact_mut(4) {
+        let a1 = row[0] + row[3];
+        let b1 = row[1] + row[2];
+        let c1 = row[1] - row[2];
+        let d1 = row[0] - row[3];
+        row[0] = (a1 + b1 + 3) >> 3;
+        row[1] = (c1 + d1 + 3) >> 3;
+        row[2] = (a1 - b1 + 3) >> 3;
+        row[3] = (d1 - c1 + 3) >> 3;
+    }
+}
+
+// Tables below come from RFC 6386.
+
+const DCT_TOKEN_TREE: [i8; 22] = [
+    -DCT_EOB, 2, -DCT_0, 4, -1, 6, 8, 12, -2, 10, -3, -4, 14, 16, -5, -6, 18, 20, -7, -8, -9, -10,
+];
+
+const PROB_DCT_CAT: [[u8; 12]; 6] = [
+    [159, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [165, 145, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [173, 148, 140, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [176, 155, 140, 135, 0, 0, 0, 0, 0, 0, 0, 0],
+    [180, 157, 141, 134, 130, 0, 0, 0, 0, 0, 0, 0],
+    [254, 254, 243, 230, 196, 177, 153, 140, 133, 130, 129, 0],
+];
+
+const DCT_CAT_BASE: [u8; 6] = [5, 7, 11, 19, 35, 67];
+const COEFF_BANDS: [u8; 16] = [0, 1, 2, 3, 6, 4, 5, 6, 6, 6, 6, 6, 6, 6, 6, 7];
+const ZIGZAG: [u8; 16] = [0, 1, 4, 8, 5, 2, 3, 6, 9, 12, 13, 10, 7, 11, 14, 15];
+
+#[rustfmt::skip]
+const DC_QUANT: [i16; 128] = [
+      4,   5,   6,   7,   8,   9,  10,  10,
+     11,  12,  13,  14,  15,  16,  17,  17,
+     18,  19,  20,  20,  21,  21,  22,  22,
+     23,  23,  24,  25,  25,  26,  27,  28,
+     29,  30,  31,  32,  33,  34,  35,  36,
+     37,  37,  38,  39,  40,  41,  42,  43,
+     44,  45,  46,  46,  47,  48,  49,  50,
+     51,  52,  53,  54,  55,  56,  57,  58,
+     59,  60,  61,  62,  63,  64,  65,  66,
+     67,  68,  69,  70,  71,  72,  73,  74,
+     75,  76,  76,  77,  78,  79,  80,  81,
+     82,  83,  84,  85,  86,  87,  88,  89,
+     91,  93,  95,  96,  98, 100, 101, 102,
+    104, 106, 108, 110, 112, 114, 116, 118,
+    122, 124, 126, 128, 130, 132, 134, 136,
+    138, 140, 143, 145, 148, 151, 154, 157,
+];
+
+#[rustfmt::skip]
+const AC_QUANT: [i16; 128] = [
+      4,   5,   6,   7,   8,    9,  10,  11,
+      12,  13,  14,  15,  16,  17,  18,  19,
+      20,  21,  22,  23,  24,  25,  26,  27,
+      28,  29,  30,  31,  32,  33,  34,  35,
+      36,  37,  38,  39,  40,  41,  42,  43,
+      44,  45,  46,  47,  48,  49,  50,  51,
+      52,  53,  54,  55,  56,  57,  58,  60,
+      62,  64,  66,  68,  70,  72,  74,  76,
+      78,  80,  82,  84,  86,  88,  90,  92,
+      94,  96,  98, 100, 102, 104, 106, 108,
+     110, 112, 114, 116, 119, 122, 125, 128,
+     131, 134, 137, 140, 143, 146, 149, 152,
+     155, 158, 161, 164, 167, 170, 173, 177,
+     181, 185, 189, 193, 197, 201, 205, 209,
+     213, 217, 221, 225, 229, 234, 239, 245,
+     249, 254, 259, 264, 269, 274, 279, 284,
+];
+
+type TokenProbTables = [[[[u8; 11]; 3]; 8]; 4];
+
+// Probabilities that a token's probability will be updated
+const COEFF_UPDATE_PROBS: TokenProbTables = [
+    [
+        [
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [176, 246, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [223, 241, 252, 255, 255, 255, 255, 255, 255, 255, 255],
+            [249, 253, 253, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 244, 252, 255, 255, 255, 255, 255, 255, 255, 255],
+            [234, 254, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [253, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 246, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [239, 253, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [254, 255, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 248, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [251, 255, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 253, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [251, 254, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [254, 255, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 254, 253, 255, 254, 255, 255, 255, 255, 255, 255],
+            [250, 255, 254, 255, 254, 255, 255, 255, 255, 255, 255],
+            [254, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+    ],
+    [
+        [
+            [217, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [225, 252, 241, 253, 255, 255, 254, 255, 255, 255, 255],
+            [234, 250, 241, 250, 253, 255, 253, 254, 255, 255, 255],
+        ],
+        [
+            [255, 254, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [223, 254, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [238, 253, 254, 254, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 248, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [249, 254, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 253, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [247, 254, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 253, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [252, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 254, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [253, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 254, 253, 255, 255, 255, 255, 255, 255, 255, 255],
+            [250, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [254, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+    ],
+    [
+        [
+            [186, 251, 250, 255, 255, 255, 255, 255, 255, 255, 255],
+            [234, 251, 244, 254, 255, 255, 255, 255, 255, 255, 255],
+            [251, 251, 243, 253, 254, 255, 254, 255, 255, 255, 255],
+        ],
+        [
+            [255, 253, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [236, 253, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [251, 253, 253, 254, 254, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 254, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [254, 254, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 254, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [254, 254, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [254, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [254, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+    ],
+    [
+        [
+            [248, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [250, 254, 252, 254, 255, 255, 255, 255, 255, 255, 255],
+            [248, 254, 249, 253, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 253, 253, 255, 255, 255, 255, 255, 255, 255, 255],
+            [246, 253, 253, 255, 255, 255, 255, 255, 255, 255, 255],
+            [252, 254, 251, 254, 254, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 254, 252, 255, 255, 255, 255, 255, 255, 255, 255],
+            [248, 254, 253, 255, 255, 255, 255, 255, 255, 255, 255],
+            [253, 255, 254, 254, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 251, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [245, 251, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [253, 253, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 251, 253, 255, 255, 255, 255, 255, 255, 255, 255],
+            [252, 253, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 254, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 252, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [249, 255, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 255, 253, 255, 255, 255, 255, 255, 255, 255, 255],
+            [250, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [254, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+    ],
+];
+
+// Section 13.5
+// Default Probabilities for tokens
+const COEFF_PROBS: TokenProbTables = [
+    [
+        [
+            [128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128],
+            [128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128],
+            [128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128],
+        ],
+        [
+            [253, 136, 254, 255, 228, 219, 128, 128, 128, 128, 128],
+            [189, 129, 242, 255, 227, 213, 255, 219, 128, 128, 128],
+            [106, 126, 227, 252, 214, 209, 255, 255, 128, 128, 128],
+        ],
+        [
+            [1, 98, 248, 255, 236, 226, 255, 255, 128, 128, 128],
+            [181, 133, 238, 254, 221, 234, 255, 154, 128, 128, 128],
+            [78, 134, 202, 247, 198, 180, 255, 219, 128, 128, 128],
+        ],
+        [
+            [1, 185, 249, 255, 243, 255, 128, 128, 128, 128, 128],
+            [184, 150, 247, 255, 236, 224, 128, 128, 128, 128, 128],
+            [77, 110, 216, 255, 236, 230, 128, 128, 128, 128, 128],
+        ],
+        [
+            [1, 101, 251, 255, 241, 255, 128, 128, 128, 128, 128],
+            [170, 139, 241, 252, 236, 209, 255, 255, 128, 128, 128],
+            [37, 116, 196, 243, 228, 255, 255, 255, 128, 128, 128],
+        ],
+        [
+            [1, 204, 254, 255, 245, 255, 128, 128, 128, 128, 128],
+            [207, 160, 250, 255, 238, 128, 128, 128, 128, 128, 128],
+            [102, 103, 231, 255, 211, 171, 128, 128, 128, 128, 128],
+        ],
+        [
+            [1, 152, 252, 255, 240, 255, 128, 128, 128, 128, 128],
+            [177, 135, 243, 255, 234, 225, 128, 128, 128, 128, 128],
+            [80, 129, 211, 255, 194, 224, 128, 128, 128, 128, 128],
+        ],
+        [
+            [1, 1, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+            [246, 1, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+            [255, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128],
+        ],
+    ],
+    [
+        [
+            [198, 35, 237, 223, 193, 187, 162, 160, 145, 155, 62],
+            [131, 45, 198, 221, 172, 176, 220, 157, 252, 221, 1],
+            [68, 47, 146, 208, 149, 167, 221, 162, 255, 223, 128],
+        ],
+        [
+            [1, 149, 241, 255, 221, 224, 255, 255, 128, 128, 128],
+            [184, 141, 234, 253, 222, 220, 255, 199, 128, 128, 128],
+            [81, 99, 181, 242, 176, 190, 249, 202, 255, 255, 128],
+        ],
+        [
+            [1, 129, 232, 253, 214, 197, 242, 196, 255, 255, 128],
+            [99, 121, 210, 250, 201, 198, 255, 202, 128, 128, 128],
+            [23, 91, 163, 242, 170, 187, 247, 210, 255, 255, 128],
+        ],
+        [
+            [1, 200, 246, 255, 234, 255, 128, 128, 128, 128, 128],
+            [109, 178, 241, 255, 231, 245, 255, 255, 128, 128, 128],
+            [44, 130, 201, 253, 205, 192, 255, 255, 128, 128, 128],
+        ],
+        [
+            [1, 132, 239, 251, 219, 209, 255, 165, 128, 128, 128],
+            [94, 136, 225, 251, 218, 190, 255, 255, 128, 128, 128],
+            [22, 100, 174, 245, 186, 161, 255, 199, 128, 128, 128],
+        ],
+        [
+            [1, 182, 249, 255, 232, 235, 128, 128, 128, 128, 128],
+            [124, 143, 241, 255, 227, 234, 128, 128, 128, 128, 128],
+            [35, 77, 181, 251, 193, 211, 255, 205, 128, 128, 128],
+        ],
+        [
+            [1, 157, 247, 255, 236, 231, 255, 255, 128, 128, 128],
+            [121, 141, 235, 255, 225, 227, 255, 255, 128, 128, 128],
+            [45, 99, 188, 251, 195, 217, 255, 224, 128, 128, 128],
+        ],
+        [
+            [1, 1, 251, 255, 213, 255, 128, 128, 128, 128, 128],
+            [203, 1, 248, 255, 255, 128, 128, 128, 128, 128, 128],
+            [137, 1, 177, 255, 224, 255, 128, 128, 128, 128, 128],
+        ],
+    ],
+    [
+        [
+            [253, 9, 248, 251, 207, 208, 255, 192, 128, 128, 128],
+            [175, 13, 224, 243, 193, 185, 249, 198, 255, 255, 128],
+            [73, 17, 171, 221, 161, 179, 236, 167, 255, 234, 128],
+        ],
+        [
+            [1, 95, 247, 253, 212, 183, 255, 255, 128, 128, 128],
+            [239, 90, 244, 250, 211, 209, 255, 255, 128, 128, 128],
+            [155, 77, 195, 248, 188, 195, 255, 255, 128, 128, 128],
+        ],
+        [
+            [1, 24, 239, 251, 218, 219, 255, 205, 128, 128, 128],
+            [201, 51, 219, 255, 196, 186, 128, 128, 128, 128, 128],
+            [69, 46, 190, 239, 201, 218, 255, 228, 128, 128, 128],
+        ],
+        [
+            [1, 191, 251, 255, 255, 128, 128, 128, 128, 128, 128],
+            [223, 165, 249, 255, 213, 255, 128, 128, 128, 128, 128],
+            [141, 124, 248, 255, 255, 128, 128, 128, 128, 128, 128],
+        ],
+        [
+            [1, 16, 248, 255, 255, 128, 128, 128, 128, 128, 128],
+            [190, 36, 230, 255, 236, 255, 128, 128, 128, 128, 128],
+            [149, 1, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+        ],
+        [
+            [1, 226, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+            [247, 192, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+            [240, 128, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+        ],
+        [
+            [1, 134, 252, 255, 255, 128, 128, 128, 128, 128, 128],
+            [213, 62, 250, 255, 255, 128, 128, 128, 128, 128, 128],
+            [55, 93, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+        ],
+        [
+            [128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128],
+            [128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128],
+            [128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128],
+        ],
+    ],
+    [
+        [
+            [202, 24, 213, 235, 186, 191, 220, 160, 240, 175, 255],
+            [126, 38, 182, 232, 169, 184, 228, 174, 255, 187, 128],
+            [61, 46, 138, 219, 151, 178, 240, 170, 255, 216, 128],
+        ],
+        [
+            [1, 112, 230, 250, 199, 191, 247, 159, 255, 255, 128],
+            [166, 109, 228, 252, 211, 215, 255, 174, 128, 128, 128],
+            [39, 77, 162, 232, 172, 180, 245, 178, 255, 255, 128],
+        ],
+        [
+            [1, 52, 220, 246, 198, 199, 249, 220, 255, 255, 128],
+            [124, 74, 191, 243, 183, 193, 250, 221, 255, 255, 128],
+            [24, 71, 130, 219, 154, 170, 243, 182, 255, 255, 128],
+        ],
+        [
+            [1, 182, 225, 249, 219, 240, 255, 224, 128, 128, 128],
+            [149, 150, 226, 252, 216, 205, 255, 171, 128, 128, 128],
+            [28, 108, 170, 242, 183, 194, 254, 223, 255, 255, 128],
+        ],
+        [
+            [1, 81, 230, 252, 204, 203, 255, 192, 128, 128, 128],
+            [123, 102, 209, 247, 188, 196, 255, 233, 128, 128, 128],
+            [20, 95, 153, 243, 164, 173, 255, 203, 128, 128, 128],
+        ],
+        [
+            [1, 222, 248, 255, 216, 213, 128, 128, 128, 128, 128],
+            [168, 175, 246, 252, 235, 205, 255, 255, 128, 128, 128],
+            [47, 116, 215, 255, 211, 212, 255, 255, 128, 128, 128],
+        ],
+        [
+            [1, 121, 236, 253, 212, 214, 255, 255, 128, 128, 128],
+            [141, 84, 213, 252, 201, 202, 255, 219, 128, 128, 128],
+            [42, 80, 160, 240, 162, 185, 255, 205, 128, 128, 128],
+        ],
+        [
+            [1, 1, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+            [244, 1, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+            [238, 1, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+        ],
+    ],
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_size() {
+        assert!(encode_lossy(&[], 0, 0, 80).is_err());
+        assert!(encode_lossy(&[0; 4], MAX_SIZE + 1, 1, 80).is_err());
+        assert!(encode_lossy(&[0; 8], 1, 1, 80).is_err());
+        assert!(encode_lossy(&[0; 4], 1, 1, 80).is_ok());
+    }
+
+    #[test]
+    fn test_quality_settings() {
+        assert_eq!(quality_settings(100), (0, 0));
+        assert_eq!(quality_settings(1), (127, 31));
+        assert_eq!(quality_settings(0), quality_settings(1));
+        assert!(quality_settings(90).0 < quality_settings(50).0);
+    }
+
+    #[test]
+    fn test_tree_path() {
+        let mut path = [(0, false); 16];
+        assert_eq!(
+            tree_path(&KEYFRAME_YMODE_TREE, 0, DC_PRED, &mut path, 0),
+            Some(3)
+        );
+        assert_eq!(&path[..3], &[(0, true), (2, false), (4, false)]);
+        assert_eq!(tree_path(&DCT_TOKEN_TREE, 2, DCT_EOB, &mut path, 0), None);
+    }
+}